@@ -1,11 +1,24 @@
 //! This example aims at showing vertex buffer usage in a minimal wgpu setup.
-//! 
+//!
 //! The corner coordinates of the triangle are provided via a vertex buffer
 //! and the vertex indices in the face are provided as an index buffer.
+//!
+//! The colors of the vertices are interpolated in linear space by the GPU, and the shader outputs
+//! linear values. But PNG viewers expect sRGB encoded values, where the same byte value is brighter,
+//! so the raw output looks too dark in the gradients. There are two ways to fix it:
+//! - `cargo run -- --srgb` renders into an `Rgba8UnormSrgb` texture instead of `Rgba8Unorm`,
+//!   the GPU converting the linear values to sRGB when writing them (and blending in linear space).
+//!   The clear color is also considered linear, so the background looks brighter.
+//! - `cargo run -- --srgb-readback` converts the values from linear to sRGB on the CPU after the readback.
+//!   This loses precision in the dark colors, since they were stored with only 8 bits of linear values.
+//!
+//! With `--png-color-space`, the color space is also embedded in the PNG
+//! (an sRGB chunk, or a gamma of 1.0 for the raw linear output), for viewers that support it.
 //! The steps of this minimal program are the following.
 //!
 //! 1. (async) Initialize the connection with the GPU device
-//! 2. Initialize a wgpu Texture object that will serve as a write target for our pipeline
+//! 2. Initialize a wgpu Texture object that will serve as a write target for our pipeline,
+//!    **(new)** with an sRGB format if requested
 //! 3. Initialize a wgpu Buffer where the Texture output will be transferred to
 //! 4. **(new)** Create and initialize a vertex buffer containing the triangle coordinates
 //! 5. **(new)** Create and initialize an index buffer containing the vertex indices in the face
@@ -22,7 +35,8 @@
 //!       - Draw the primitive
 //!    2. Add a command to copy the texture output to the output buffer
 //! 9. Submit our commands to the device queue
-//! 10. (async) Transfer the output buffer into an image we can save to disk
+//! 10. (async) Transfer the output buffer into an image we can save to disk,
+//!     **(new)** converted to sRGB and with its color space if requested
 
 use wgpu::util::DeviceExt; // Utility trait to create and initialize buffers with device.create_buffer_init()
 
@@ -32,12 +46,26 @@ fn main() {
 }
 
 async fn run() {
+    // Color space options, provided as command line arguments
+    let srgb_texture = std::env::args().any(|arg| arg == "--srgb");
+    let srgb_readback = std::env::args().any(|arg| arg == "--srgb-readback");
+    let png_color_space = std::env::args().any(|arg| arg == "--png-color-space");
+    assert!(
+        !(srgb_texture && srgb_readback),
+        "--srgb and --srgb-readback would convert the colors to sRGB twice"
+    );
+
     // (1) Initializing WebGPU
     println!("Initializing WebGPU ...");
     let (device, queue) = init_wgpu_device().await.unwrap();
 
     // (2) Initialize the output texture
-    let texture = init_output_texture(&device, 256);
+    let format = if srgb_texture {
+        wgpu::TextureFormat::Rgba8UnormSrgb
+    } else {
+        wgpu::TextureFormat::Rgba8Unorm
+    };
+    let texture = init_output_texture(&device, 256, format);
     let texture_view = texture.create_view(&Default::default());
 
     // (3) Initialize a buffer for the texture output
@@ -97,10 +125,19 @@ async fn run() {
 
     // (10) Transfer the texture output buffer into an image buffer
     println!("Saving the GPU output into an image ...");
-    let img = to_image(&device, &output_buffer, texture.width(), texture.height()).await;
+    let mut img = to_image(&device, &output_buffer, texture.width(), texture.height()).await;
+    if srgb_readback {
+        println!("Converting the image from linear to sRGB ...");
+        linear_to_srgb_image(&mut img);
+    }
 
     println!("Saving the image to disk ...");
-    img.save("image.png").unwrap();
+    if png_color_space {
+        // The image is sRGB encoded if either the GPU or the CPU converted it
+        save_png_with_color_space(&img, "image.png", srgb_texture || srgb_readback);
+    } else {
+        img.save("image.png").unwrap();
+    }
 
     // Flushes any pending write operations and unmaps the buffer from host memory
     output_buffer.unmap();
@@ -133,7 +170,11 @@ async fn init_wgpu_device() -> Result<(wgpu::Device, wgpu::Queue), wgpu::Request
 }
 
 /// (2) Initialize the output texture
-fn init_output_texture(device: &wgpu::Device, texture_size: u32) -> wgpu::Texture {
+fn init_output_texture(
+    device: &wgpu::Device,
+    texture_size: u32,
+    format: wgpu::TextureFormat,
+) -> wgpu::Texture {
     let texture_desc = wgpu::TextureDescriptor {
         label: Some("output_texture"),
         // The texture size. (layers is set to 1)
@@ -145,8 +186,9 @@ fn init_output_texture(device: &wgpu::Device, texture_size: u32) -> wgpu::Textur
         dimension: wgpu::TextureDimension::D2,
         mip_level_count: 1, // the number of mip levels the texture will contain
         sample_count: 1,    // sample_count > 1 would indicate a multisampled texture
-        // Use RGBA format for the output
-        format: wgpu::TextureFormat::Rgba8Unorm,
+        // Use RGBA format for the output, Rgba8Unorm or Rgba8UnormSrgb.
+        // Both have the same bytes layout, only the conversion done by the GPU differs.
+        format,
         // RENDER_ATTACHMENT -> so that the GPU can render to the texture
         // COPY_SRC -> so that we can pull data out of the texture
         usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
@@ -157,7 +199,7 @@ fn init_output_texture(device: &wgpu::Device, texture_size: u32) -> wgpu::Textur
 }
 
 /// (3) Create a buffer descriptor of the correct size for the texture
-fn create_texture_buffer_descriptor(texture: &wgpu::Texture) -> wgpu::BufferDescriptor {
+fn create_texture_buffer_descriptor(texture: &wgpu::Texture) -> wgpu::BufferDescriptor<'_> {
     let texel_size = texture.format().block_copy_size(None).unwrap();
     wgpu::BufferDescriptor {
         size: (texel_size * texture.width() * texture.height()).into(),
@@ -284,7 +326,7 @@ fn draw_pipeline(
     let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
         label: Some("Render Pass"),
         color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-            view: &texture_view,  // the output texture
+            view: texture_view,   // the output texture
             resolve_target: None, // only useful for multisampling
             ops: wgpu::Operations {
                 // "load" specifies how data is read.
@@ -303,7 +345,7 @@ fn draw_pipeline(
     });
 
     // Draw the render pass for our pipeline
-    render_pass.set_pipeline(&pipeline);
+    render_pass.set_pipeline(pipeline);
     render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
     render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint16);
     render_pass.draw_indexed(0..num_indices, 0, 0..1);
@@ -320,13 +362,13 @@ fn copy_texture_to_buffer(
         // source
         wgpu::ImageCopyTexture {
             aspect: wgpu::TextureAspect::All,
-            texture: &texture,
+            texture,
             mip_level: 0,
             origin: wgpu::Origin3d::ZERO,
         },
         // destination
         wgpu::ImageCopyBuffer {
-            buffer: &output_buffer,
+            buffer: output_buffer,
             layout: wgpu::ImageDataLayout {
                 offset: 0,
                 bytes_per_row: Some(texel_size * texture.width()),
@@ -362,3 +404,40 @@ async fn to_image(
 
     image::RgbaImage::from_raw(width, height, Vec::from(&data as &[u8])).unwrap()
 }
+
+/// (10) Convert a linear color channel into an sRGB encoded one
+fn linear_to_srgb(value: u8) -> u8 {
+    let linear = value as f32 / 255.0;
+    let srgb = if linear <= 0.0031308 {
+        12.92 * linear
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    };
+    (srgb * 255.0).round() as u8
+}
+
+/// (10) Convert the colors of an image from linear to sRGB (the alpha channel is always linear)
+fn linear_to_srgb_image(img: &mut image::RgbaImage) {
+    for pixel in img.pixels_mut() {
+        for channel in &mut pixel.0[..3] {
+            *channel = linear_to_srgb(*channel);
+        }
+    }
+}
+
+/// (10) Save the image as PNG, with the color space of its values:
+/// an sRGB chunk if they are sRGB encoded, or a gamma of 1.0 if they are linear.
+/// The image crate does not write these chunks, so we use the png crate directly.
+fn save_png_with_color_space(img: &image::RgbaImage, path: &str, srgb: bool) {
+    let file = std::io::BufWriter::new(std::fs::File::create(path).unwrap());
+    let mut encoder = png::Encoder::new(file, img.width(), img.height());
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    if srgb {
+        encoder.set_srgb(png::SrgbRenderingIntent::Perceptual);
+    } else {
+        encoder.set_source_gamma(png::ScaledFloat::new(1.0));
+    }
+    let mut writer = encoder.write_header().unwrap();
+    writer.write_image_data(img.as_raw()).unwrap();
+}
@@ -1,8 +1,13 @@
 //! This example aims at showing OBJ model display in a minimal wgpu setup.
-//! 
+//!
 //! The vertices and faces (indices) are loaded from an OBJ file (bunny).
 //! The OBJ is projected with a simple orthogonal projection in the vertex shader with some scaling.
 //! The appearance is set to a simple white in the fragment shader.
+//!
+//! Run with `--wireframe` to draw the triangle edges as an overlay on top of the mesh.
+//! If the device supports `Features::POLYGON_MODE_LINE`, the wireframe is rasterized as lines.
+//! Otherwise, the triangles are filled and the fragment shader only keeps
+//! the fragments close to an edge, using their barycentric coordinates.
 //! The steps of this minimal program are the following.
 //!
 //! 1. (async) Initialize the connection with the GPU device
@@ -16,12 +21,16 @@
 //!    - the vertex shader: include our vertex buffer layout
 //!    - the fragment shader
 //!    - the primitive type (triangle list)
+//!
+//!    **(new)** And optionally, a second pipeline for the wireframe overlay,
+//!    with its own unrolled vertex buffer (3 vertices per triangle)
 //! 7. Define our command encoder:
 //!    1. Start by defining our render pass:
 //!       - Link to the texture output
 //!       - Link to the pipeline
 //!       - Provide vertex buffer and index buffer
 //!       - Draw the primitive
+//!       - **(new)** Draw the wireframe overlay on top of it
 //!    2. Add a command to copy the texture output to the output buffer
 //! 8. Submit our commands to the device queue
 //! 9. (async) Transfer the output buffer into an image we can save to disk
@@ -34,6 +43,9 @@ fn main() {
 }
 
 async fn run() {
+    // Draw the wireframe overlay if requested on the command line
+    let draw_wireframe = std::env::args().any(|arg| arg == "--wireframe");
+
     // (1) Initializing WebGPU
     println!("Initializing WebGPU ...");
     let (device, queue) = init_wgpu_device().await.unwrap();
@@ -80,6 +92,10 @@ async fn run() {
         vtx_buffer_layout(),
     );
 
+    // (6) Define the optional wireframe overlay
+    let wireframe =
+        draw_wireframe.then(|| Wireframe::new(&device, &shader_module, texture.format(), bunny));
+
     // Initialize a command encoder
     let mut encoder = device.create_command_encoder(&Default::default());
 
@@ -92,6 +108,7 @@ async fn run() {
         &vertex_buffer,
         &index_buffer,
         bunny.indices.len() as u32,
+        wireframe.as_ref(),
     );
 
     // (7.2) Copy the texture output into a buffer
@@ -134,8 +151,13 @@ async fn init_wgpu_device() -> Result<(wgpu::Device, wgpu::Queue), wgpu::Request
 
     // Request a connection to a physical device,
     // and also access to the queue for its command buffers.
-    // It is possible, if necessary, to add a description of required features.
-    adapter.request_device(&Default::default(), None).await
+    // We require the line polygon mode (for the wireframe), but only if the adapter supports it.
+    let device_desc = wgpu::DeviceDescriptor {
+        label: None,
+        required_features: adapter.features() & wgpu::Features::POLYGON_MODE_LINE,
+        required_limits: Default::default(),
+    };
+    adapter.request_device(&device_desc, None).await
 }
 
 /// (2) Initialize the output texture
@@ -163,7 +185,7 @@ fn init_output_texture(device: &wgpu::Device, texture_size: u32) -> wgpu::Textur
 }
 
 /// (3) Create a buffer descriptor of the correct size for the texture
-fn create_texture_buffer_descriptor(texture: &wgpu::Texture) -> wgpu::BufferDescriptor {
+fn create_texture_buffer_descriptor(texture: &wgpu::Texture) -> wgpu::BufferDescriptor<'_> {
     let texel_size = texture.format().block_copy_size(None).unwrap();
     wgpu::BufferDescriptor {
         size: (texel_size * texture.width() * texture.height()).into(),
@@ -224,7 +246,7 @@ fn build_simple_pipeline(
             strip_index_format: None,
             front_face: wgpu::FrontFace::Ccw,
             cull_mode: Some(wgpu::Face::Front),
-            // Setting this to anything other than Fill requires Features::NON_FILL_POLYGON_MODE
+            // Setting this to Line requires Features::POLYGON_MODE_LINE (see the wireframe)
             polygon_mode: wgpu::PolygonMode::Fill,
             // Requires Features::DEPTH_CLIP_CONTROL
             unclipped_depth: false,
@@ -243,6 +265,117 @@ fn build_simple_pipeline(
     })
 }
 
+/// (6) Wireframe overlay, drawn on top of the mesh
+struct Wireframe {
+    pipeline: wgpu::RenderPipeline,
+    /// Unrolled triangles (3 vertices per triangle, without index buffer),
+    /// so that the vertex index tells the shader which corner of its triangle each vertex is
+    vertex_buffer: wgpu::Buffer,
+    num_vertices: u32,
+}
+
+impl Wireframe {
+    fn new(
+        device: &wgpu::Device,
+        shader_module: &wgpu::ShaderModule,
+        texture_format: wgpu::TextureFormat,
+        mesh: &tobj::Mesh,
+    ) -> Self {
+        // Rasterize the triangle edges as lines if supported,
+        // otherwise fill the triangles and only keep the fragments close to the edges.
+        let polygon_mode = if device
+            .features()
+            .contains(wgpu::Features::POLYGON_MODE_LINE)
+        {
+            println!("Drawing the wireframe with the line polygon mode");
+            wgpu::PolygonMode::Line
+        } else {
+            println!("Line polygon mode not supported, drawing the wireframe with barycentric coordinates");
+            wgpu::PolygonMode::Fill
+        };
+
+        // Vertex i of the unrolled mesh is the corner (i % 3) of the triangle (i / 3)
+        let positions: Vec<f32> = mesh
+            .indices
+            .iter()
+            .flat_map(|&i| &mesh.positions[3 * i as usize..3 * i as usize + 3])
+            .copied()
+            .collect();
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Wireframe Vertex Buffer"),
+            contents: bytemuck::cast_slice(&positions),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        Self {
+            pipeline: build_wireframe_pipeline(
+                device,
+                shader_module,
+                texture_format,
+                vtx_buffer_layout(),
+                polygon_mode,
+            ),
+            vertex_buffer,
+            num_vertices: mesh.indices.len() as u32,
+        }
+    }
+}
+
+/// (6) Define the render pipeline of the wireframe overlay
+fn build_wireframe_pipeline(
+    device: &wgpu::Device,
+    shader_module: &wgpu::ShaderModule,
+    texture_format: wgpu::TextureFormat,
+    vertex_buffer_layout: wgpu::VertexBufferLayout,
+    polygon_mode: wgpu::PolygonMode,
+) -> wgpu::RenderPipeline {
+    // Filled triangles need the fallback fragment shader to only keep their edges
+    let fragment_entry_point = match polygon_mode {
+        wgpu::PolygonMode::Fill => "fragment_wireframe_fallback",
+        _ => "fragment_wireframe",
+    };
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Wireframe Pipeline"),
+        layout: None, // "auto"
+        vertex: wgpu::VertexState {
+            module: shader_module,
+            entry_point: "vertex_wireframe",
+            buffers: &[vertex_buffer_layout],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader_module,
+            entry_point: fragment_entry_point,
+            targets: &[Some(wgpu::ColorTargetState {
+                format: texture_format,
+                blend: Some(wgpu::BlendState {
+                    alpha: wgpu::BlendComponent::REPLACE,
+                    color: wgpu::BlendComponent::REPLACE,
+                }),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: Some(wgpu::Face::Front),
+            // Line requires Features::POLYGON_MODE_LINE
+            polygon_mode,
+            // Requires Features::DEPTH_CLIP_CONTROL
+            unclipped_depth: false,
+            // Requires Features::CONSERVATIVE_RASTERIZATION
+            conservative: false,
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+    })
+}
+
 /// (7.1) Draw our pipeline (add render pass to the command encoder).
 fn draw_pipeline(
     encoder: &mut wgpu::CommandEncoder,
@@ -251,12 +384,13 @@ fn draw_pipeline(
     vertex_buffer: &wgpu::Buffer,
     index_buffer: &wgpu::Buffer,
     num_indices: u32,
+    wireframe: Option<&Wireframe>,
 ) {
     // Setup the pass that will render into our texture
     let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
         label: Some("Render Pass"),
         color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-            view: &texture_view,  // the output texture
+            view: texture_view,   // the output texture
             resolve_target: None, // only useful for multisampling
             ops: wgpu::Operations {
                 // "load" specifies how data is read.
@@ -275,10 +409,17 @@ fn draw_pipeline(
     });
 
     // Draw the render pass for our pipeline
-    render_pass.set_pipeline(&pipeline);
+    render_pass.set_pipeline(pipeline);
     render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
     render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
     render_pass.draw_indexed(0..num_indices, 0, 0..1);
+
+    // Draw the wireframe overlay on top of the mesh, in the same render pass
+    if let Some(wireframe) = wireframe {
+        render_pass.set_pipeline(&wireframe.pipeline);
+        render_pass.set_vertex_buffer(0, wireframe.vertex_buffer.slice(..));
+        render_pass.draw(0..wireframe.num_vertices, 0..1);
+    }
 }
 
 /// (7.2) Copy the texture output into a buffer
@@ -292,13 +433,13 @@ fn copy_texture_to_buffer(
         // source
         wgpu::ImageCopyTexture {
             aspect: wgpu::TextureAspect::All,
-            texture: &texture,
+            texture,
             mip_level: 0,
             origin: wgpu::Origin3d::ZERO,
         },
         // destination
         wgpu::ImageCopyBuffer {
-            buffer: &output_buffer,
+            buffer: output_buffer,
             layout: wgpu::ImageDataLayout {
                 offset: 0,
                 bytes_per_row: Some(texel_size * texture.width()),
@@ -1,9 +1,13 @@
 // Vertex shader
 // Just a very simple orthogonal projection
 
+fn project(position: vec3<f32>) -> vec4<f32> {
+    return vec4<f32>(7.0 * position.x, 7.0 * (position.y - 0.1), 0.0, 1.0);
+}
+
 @vertex
 fn vertex_main(@location(0) position: vec3<f32>) -> @builtin(position) vec4<f32> {
-    return vec4<f32>(7.0 * position.x, 7.0 * (position.y - 0.1), 0.0, 1.0);
+    return project(position);
 }
 
 // Fragment shader
@@ -13,3 +17,52 @@ fn vertex_main(@location(0) position: vec3<f32>) -> @builtin(position) vec4<f32>
 fn fragment_main(@builtin(position) clip_position: vec4<f32>) -> @location(0) vec4<f32> {
     return vec4<f32>(1.0, 1.0, 1.0, 1.0);
 }
+
+// Wireframe overlay
+//
+// If the device supports it, the wireframe pipeline rasterizes the triangles
+// with PolygonMode::Line, so that only their edges reach the fragment shader.
+// Otherwise, the triangles are filled, and the fallback fragment shader only keeps
+// the fragments close to an edge, i.e. where one of the barycentric coordinates is close to 0.
+// For this, the mesh is drawn unrolled (3 vertices per triangle, without index buffer),
+// so that the vertex index tells which corner of its triangle each vertex is.
+
+struct WireframeVertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    // (1, 0, 0), (0, 1, 0) or (0, 0, 1) depending on the corner,
+    // the rasterizer interpolates it into the barycentric coordinates of the fragment
+    @location(0) barycentric: vec3<f32>,
+}
+
+@vertex
+fn vertex_wireframe(
+    @location(0) position: vec3<f32>,
+    @builtin(vertex_index) vertex_index: u32,
+) -> WireframeVertexOutput {
+    var barycentric = vec3<f32>(0.0);
+    barycentric[vertex_index % 3u] = 1.0;
+    return WireframeVertexOutput(
+        // clip_position
+        project(position),
+        // barycentric
+        barycentric,
+    );
+}
+
+const WIREFRAME_COLOR = vec4<f32>(1.0, 0.3, 0.0, 1.0);
+
+@fragment
+fn fragment_wireframe() -> @location(0) vec4<f32> {
+    return WIREFRAME_COLOR;
+}
+
+@fragment
+fn fragment_wireframe_fallback(in: WireframeVertexOutput) -> @location(0) vec4<f32> {
+    // Dividing by the screen-space rate of change gives the distances to the edges in pixels.
+    // Edges are shared by two triangles, each drawing half of the line width.
+    let edge_distance = in.barycentric / fwidth(in.barycentric);
+    if min(edge_distance.x, min(edge_distance.y, edge_distance.z)) > 0.5 {
+        discard;
+    }
+    return WIREFRAME_COLOR;
+}
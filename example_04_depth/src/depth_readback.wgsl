@@ -0,0 +1,52 @@
+// Readback of a depth (and stencil) texture into storage textures, with a compute shader.
+//
+// Only some depth formats can be copied into a buffer (Depth32Float, Depth16Unorm),
+// and the formats preferred by many GPUs (Depth24Plus, Depth24PlusStencil8) cannot.
+// But any depth format can be read by a shader, so we copy it into an R32Float storage texture,
+// which can then be copied into a buffer like any color texture.
+//
+// The depth is declared as a regular float texture rather than a texture_depth_2d,
+// which is valid with an "unfilterable float" layout (see main.rs) and supported by more backends.
+//
+// The stencil is not sampled, since sampling a stencil aspect does not work on every backend
+// (the OpenGL backend reads zeros). Instead, it is read with the stencil test itself:
+// a full-screen triangle is drawn once per stencil value, only passing the stencil test
+// where the stencil equals the reference value, which it writes into an R32Uint target
+// (R8Uint would do, but R32Uint is easier to read back).
+
+@group(0) @binding(0) var depth_texture: texture_2d<f32>;
+@group(0) @binding(1) var depth_output: texture_storage_2d<r32float, write>;
+
+@compute
+@workgroup_size(8, 8)
+fn read_depth(@builtin(global_invocation_id) global_id: vec3u) {
+    // Skip the threads in excess, when the size is not a multiple of the workgroup size (8x8)
+    let pixel = global_id.xy;
+    if any(pixel >= textureDimensions(depth_texture)) {
+        return;
+    }
+    textureStore(depth_output, pixel, textureLoad(depth_texture, pixel, 0));
+}
+
+struct StencilVertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    // The stencil value of the draw, which is also its stencil reference
+    @location(0) @interpolate(flat) stencil: u32,
+}
+
+/// Full-screen triangle, covering the whole clip space [-1, 1]x[-1, 1]
+/// (its vertices are (-1, -1), (3, -1) and (-1, 3)).
+/// The stencil value is given as the instance index, each draw having a single instance.
+@vertex
+fn stencil_vertex(
+    @builtin(vertex_index) vertex_index: u32,
+    @builtin(instance_index) stencil: u32,
+) -> StencilVertexOutput {
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    return StencilVertexOutput(vec4<f32>(2.0 * uv - 1.0, 0.0, 1.0), stencil);
+}
+
+@fragment
+fn stencil_fragment(in: StencilVertexOutput) -> @location(0) u32 {
+    return in.stencil;
+}
@@ -1,16 +1,35 @@
 //! This example aims at showing how to use and retrieve the depth (Z) in a minimal wgpu setup.
-//! 
+//!
 //! We reuse the bunny OBJ from the previous example,
 //! except this time we try to output a depth map instead of just a mask of the bunny.
 //! This example also shows the effect of the clipping space (0.0-1.0 for Z).
 //! Indeed, a small part of the bunny ear is cut, due to negative Z coordinates.
+//!
+//! Run with `--wireframe` to draw the triangle edges as an overlay on top of the mesh.
+//! If the device supports `Features::POLYGON_MODE_LINE`, the wireframe is rasterized as lines.
+//! Otherwise, the triangles are filled and the fragment shader only keeps
+//! the fragments close to an edge, using their barycentric coordinates.
+//! Thanks to the depth test, only the edges of visible triangles are drawn.
+//!
+//! Run with `--depth-format depth32float|depth24plus|depth24plus-stencil8` to change the depth format.
+//! Formats such as Depth24Plus cannot be copied into a buffer, so the depth is read back
+//! by a compute shader, copying it into a float storage texture that can be copied.
+//! With a stencil, the stencil counts the fragments passing the depth test, and is saved into "stencil.png".
+//! Since sampling the stencil does not work on every backend (OpenGL reads zeros),
+//! it is read back with the stencil test instead: a full-screen triangle is drawn
+//! once per stencil value, only where the stencil equals that value, writing it into a texture.
 //! The steps of this minimal program are the following.
 //!
 //! 1. (async) Initialize the connection with the GPU device
 //! 2. Initialize a wgpu Texture object that will serve as a write target for fragment shader
 //! 3. Initialize a wgpu Buffer where the Texture output will be transferred to
 //! 4. **(new)** Initialize a wgpu Texture object that will serve as a write target for the depth
-//! 5. **(new)** Initialize a wgpu Buffer where the depth texture will be transferred to
+//! 5. **(new)** Prepare the depth readback:
+//!    1. Initialize a storage texture (R32Float) that a compute shader will copy the depth into,
+//!       and a render target (R32Uint) that the stencil values will be drawn into, if any
+//!    2. Define the compute pipeline and its bind group with the depth aspect,
+//!       and the render pipeline testing the stencil against each value
+//!    3. Initialize wgpu Buffers where the storage textures will be transferred to
 //! 6. Load the OBJ bunny
 //!    1. Create and initialize a vertex buffer containing the triangle coordinates
 //!    2. Create and initialize an index buffer containing the vertex indices in the face
@@ -21,17 +40,26 @@
 //!    - the primitive type (triangle list)
 //!    - **(new)** the depth_stencil is configured to compare depths on fragments
 //!      and only keep it when it's closer ("Less").
-//!      Also specifies to store that final depth into our depth texture
+//!      Also specifies to store that final depth into our depth texture,
+//!      and to increment the stencil of the fragments passing the depth test
+//!
+//!    **(new)** And optionally, a second pipeline for the wireframe overlay,
+//!    with its own unrolled vertex buffer (3 vertices per triangle)
 //! 9. Define our command encoder:
 //!    1. Start by defining our render pass:
 //!       - Link to the texture output
 //!       - Link to the pipeline
 //!       - Provide vertex buffer and index buffer
 //!       - Draw the primitive
-//!    2. Add a command to copy the fragment and **(new)** depth textures into their respective buffers
+//!       - **(new)** Draw the wireframe overlay on top of it
+//!    2. **(new)** Add the compute pass copying the depth into its storage texture,
+//!       and the render pass drawing the stencil values
+//!    3. Add a command to copy the fragment and **(new)** depth textures into their respective buffers
 //! 10. Submit our commands to the device queue
-//! 11. (async) Transfer the output buffer into an image we can save to disk
+//! 11. (async) Transfer the output buffers into images we can save to disk,
+//!     **(new)** decoding the texels according to the texture format
 
+use texture_image::{padded_bytes_per_row, Texels, TextureImage}; // Decoding of the readbacks
 use wgpu::util::DeviceExt; // Utility trait to create and initialize buffers with device.create_buffer_init()
 
 fn main() {
@@ -40,6 +68,27 @@ fn main() {
 }
 
 async fn run() {
+    // Wireframe overlay and depth format, provided as command line arguments
+    let args: Vec<String> = std::env::args().collect();
+    let arg_value = |name: &str| {
+        let position = args.iter().position(|arg| arg == name)?;
+        Some(
+            args.get(position + 1)
+                .expect("Missing argument value")
+                .as_str(),
+        )
+    };
+    let draw_wireframe = args.iter().any(|arg| arg == "--wireframe");
+    let depth_format = arg_value("--depth-format").map_or(
+        wgpu::TextureFormat::Depth32Float,
+        |value| match value {
+            "depth32float" => wgpu::TextureFormat::Depth32Float,
+            "depth24plus" => wgpu::TextureFormat::Depth24Plus,
+            "depth24plus-stencil8" => wgpu::TextureFormat::Depth24PlusStencil8,
+            _ => panic!("Unknown depth format: {value}"),
+        },
+    );
+
     // (1) Initializing WebGPU
     println!("Initializing WebGPU ...");
     let (device, queue) = init_wgpu_device().await.unwrap();
@@ -54,12 +103,21 @@ async fn run() {
     let output_buffer = device.create_buffer(&output_buffer_desc);
 
     // (4) Initialize the depth texture
-    let depth_texture = init_depth_texture(&device, texture_size);
+    let depth_texture = init_depth_texture(&device, texture_size, depth_format);
     let depth_texture_view = depth_texture.create_view(&Default::default());
 
-    // (5) Initialize a buffer for the depth texture output
-    let depth_buffer_desc = create_texture_buffer_descriptor(&depth_texture);
+    // (5.1 & 5.2) Prepare the passes copying the depth (and stencil) into readable textures
+    let depth_readback = DepthReadback::new(&device, &depth_texture);
+
+    // (5.3) Initialize the buffers for the depth (and stencil) outputs
+    let depth_buffer_desc = create_texture_buffer_descriptor(&depth_readback.depth_output);
     let depth_buffer = device.create_buffer(&depth_buffer_desc);
+    let stencil_buffer = depth_readback
+        .stencil_output
+        .as_ref()
+        .map(|stencil_output| {
+            device.create_buffer(&create_texture_buffer_descriptor(stencil_output))
+        });
 
     // (6) Load the OBJ bunny
     let (models, _) = tobj::load_obj("bunny.obj", &tobj::GPU_LOAD_OPTIONS).unwrap();
@@ -92,9 +150,21 @@ async fn run() {
         &device,
         &shader_module,
         texture.format(),
+        depth_format,
         vtx_buffer_layout(),
     );
 
+    // (8) Define the optional wireframe overlay
+    let wireframe = draw_wireframe.then(|| {
+        Wireframe::new(
+            &device,
+            &shader_module,
+            texture.format(),
+            depth_format,
+            bunny,
+        )
+    });
+
     // Initialize a command encoder
     let mut encoder = device.create_command_encoder(&Default::default());
 
@@ -105,14 +175,24 @@ async fn run() {
         &pipeline,
         &texture_view,
         &depth_texture_view,
+        depth_format.has_stencil_aspect(),
         &vertex_buffer,
         &index_buffer,
         bunny.indices.len() as u32,
+        wireframe.as_ref(),
     );
 
-    // (9.2) Add commands to copy the textures into their respective buffers
+    // (9.2) Copy the depth (and stencil) into the readback textures
+    depth_readback.encode(&mut encoder);
+
+    // (9.3) Add commands to copy the textures into their respective buffers
     copy_texture_to_buffer(&mut encoder, &texture, &output_buffer);
-    copy_texture_to_buffer(&mut encoder, &depth_texture, &depth_buffer);
+    copy_texture_to_buffer(&mut encoder, &depth_readback.depth_output, &depth_buffer);
+    if let (Some(stencil_output), Some(stencil_buffer)) =
+        (&depth_readback.stencil_output, &stencil_buffer)
+    {
+        copy_texture_to_buffer(&mut encoder, stencil_output, stencil_buffer);
+    }
 
     // (10) Finalize the command encoder and send it to the queue
     println!("Submitting commands to the queue ...");
@@ -122,36 +202,53 @@ async fn run() {
     let width = texture.width();
     let height = texture.height();
 
-    // (11) Transfer both texture buffers into image buffers.
-    // New scope to encapsulate img_data BufferView and drop it before unmapping.
+    // (11) Transfer both texture buffers into images, decoded according to their texture format.
+    // New scope to encapsulate the BufferViews and drop them before unmapping.
     {
-        // Transfer the texture output buffer into an image buffer
+        // Transfer the texture output buffer into an image
         println!("Saving the GPU output into an image ...");
         let img_data = retrieve_texture_buffer_data(&device, &output_buffer).await;
-        let img = image::RgbaImage::from_raw(width, height, Vec::from(&img_data as &[u8])).unwrap();
+        let img = decode_texture_data(&texture, &img_data);
 
         println!("Saving the image to disk ...");
-        img.save("image.png").unwrap();
+        img.to_dynamic_image().save("image.png").unwrap();
 
-        // Do the same for the depth buffer
+        // Do the same for the depth buffer: its f32 values become a 16 bits grayscale image
         println!("Saving the GPU depth output into an image ...");
         let depth_data = retrieve_texture_buffer_data(&device, &depth_buffer).await;
-        let depth_data_f32: &[f32] = bytemuck::cast_slice(&depth_data);
+        let depth_img = decode_texture_data(&depth_readback.depth_output, &depth_data);
 
         println!("Saving the f32 data as a u16 image to disk ...");
-        let img_data_u16: Vec<u16> = depth_data_f32
-            .iter()
-            .map(|p| (p.max(0.0).min(1.0) * 65535.0) as u16)
-            .collect();
-        let img_u16 =
-            image::ImageBuffer::<image::Luma<u16>, _>::from_raw(width, height, img_data_u16)
+        depth_img.to_dynamic_image().save("depth.png").unwrap();
+
+        // And for the stencil, with the counts scaled to be visible
+        if let (Some(stencil_output), Some(stencil_buffer)) =
+            (&depth_readback.stencil_output, &stencil_buffer)
+        {
+            println!("Saving the GPU stencil output into an image ...");
+            let stencil_data = retrieve_texture_buffer_data(&device, stencil_buffer).await;
+            let stencil_img = decode_texture_data(stencil_output, &stencil_data);
+            let counts = match &stencil_img.texels {
+                Texels::U32(counts) => counts,
+                _ => panic!(
+                    "Expected u32 texels for the {:?} stencil readback",
+                    stencil_output.format()
+                ),
+            };
+            let pixels = counts.iter().map(|&count| (64 * count).min(255) as u8);
+            image::GrayImage::from_raw(width, height, pixels.collect())
+                .unwrap()
+                .save("stencil.png")
                 .unwrap();
-        img_u16.save("depth.png").unwrap();
+        }
     }
 
     // Flushes any pending write operations and unmaps the buffer from host memory
     output_buffer.unmap();
     depth_buffer.unmap();
+    if let Some(stencil_buffer) = &stencil_buffer {
+        stencil_buffer.unmap();
+    }
 
     println!("Terminating the program ...")
 }
@@ -176,8 +273,13 @@ async fn init_wgpu_device() -> Result<(wgpu::Device, wgpu::Queue), wgpu::Request
 
     // Request a connection to a physical device,
     // and also access to the queue for its command buffers.
-    // It is possible, if necessary, to add a description of required features.
-    adapter.request_device(&Default::default(), None).await
+    // We require the line polygon mode (for the wireframe), but only if the adapter supports it.
+    let device_desc = wgpu::DeviceDescriptor {
+        label: None,
+        required_features: adapter.features() & wgpu::Features::POLYGON_MODE_LINE,
+        required_limits: Default::default(),
+    };
+    adapter.request_device(&device_desc, None).await
 }
 
 /// (2) Initialize the output texture
@@ -204,11 +306,12 @@ fn init_output_texture(device: &wgpu::Device, texture_size: u32) -> wgpu::Textur
     device.create_texture(&texture_desc)
 }
 
-/// (3 & 5) Create a buffer descriptor of the correct size for the texture
-fn create_texture_buffer_descriptor(texture: &wgpu::Texture) -> wgpu::BufferDescriptor {
-    let texel_size = texture.format().block_copy_size(None).unwrap();
+/// (3 & 5) Create a buffer descriptor of the correct size for the texture,
+/// with its rows padded to the alignment required by texture copies
+fn create_texture_buffer_descriptor(texture: &wgpu::Texture) -> wgpu::BufferDescriptor<'_> {
+    let bytes_per_row = padded_bytes_per_row(texture.format(), texture.width());
     wgpu::BufferDescriptor {
-        size: (texel_size * texture.width() * texture.height()).into(),
+        size: (bytes_per_row * texture.height()).into(),
         usage: wgpu::BufferUsages::COPY_DST
             // this tells wpgu that we want to read this buffer from the cpu
             | wgpu::BufferUsages::MAP_READ,
@@ -218,7 +321,11 @@ fn create_texture_buffer_descriptor(texture: &wgpu::Texture) -> wgpu::BufferDesc
 }
 
 /// (4) Initialize a depth texture
-fn init_depth_texture(device: &wgpu::Device, texture_size: u32) -> wgpu::Texture {
+fn init_depth_texture(
+    device: &wgpu::Device,
+    texture_size: u32,
+    format: wgpu::TextureFormat,
+) -> wgpu::Texture {
     let texture_desc = wgpu::TextureDescriptor {
         label: Some("depth_texture"),
         // The texture size. (layers is set to 1)
@@ -230,17 +337,256 @@ fn init_depth_texture(device: &wgpu::Device, texture_size: u32) -> wgpu::Texture
         dimension: wgpu::TextureDimension::D2,
         mip_level_count: 1, // the number of mip levels the texture will contain
         sample_count: 1,    // sample_count > 1 would indicate a multisampled texture
-        // Use RGBA format for the output
-        format: wgpu::TextureFormat::Depth32Float,
+        // Depth32Float, Depth24Plus or Depth24PlusStencil8
+        format,
         // RENDER_ATTACHMENT -> so that the GPU can render to the texture
-        // COPY_SRC -> so that we can pull data out of the texture
-        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        // TEXTURE_BINDING -> so that the readback compute shader can read the texture
+        // (COPY_SRC is not allowed for all depth formats)
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
         // Specify the allowed formats when calling "texture.create_view()"
         view_formats: &[],
     };
     device.create_texture(&texture_desc)
 }
 
+/// (5.1) Initialize a storage texture, written by the readback compute shader
+fn init_storage_texture(
+    device: &wgpu::Device,
+    size: wgpu::Extent3d,
+    format: wgpu::TextureFormat,
+) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("readback_texture"),
+        size,
+        dimension: wgpu::TextureDimension::D2,
+        mip_level_count: 1,
+        sample_count: 1,
+        format,
+        // STORAGE_BINDING -> so that the compute shader can write to the texture
+        // COPY_SRC -> so that we can pull data out of the texture
+        usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    })
+}
+
+/// (5.1) Initialize the texture the stencil values are drawn into
+fn init_stencil_output_texture(device: &wgpu::Device, size: wgpu::Extent3d) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("stencil_readback_texture"),
+        size,
+        dimension: wgpu::TextureDimension::D2,
+        mip_level_count: 1,
+        sample_count: 1,
+        format: wgpu::TextureFormat::R32Uint,
+        // RENDER_ATTACHMENT -> so that the stencil passes can draw into the texture
+        // COPY_SRC -> so that we can pull data out of the texture
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    })
+}
+
+/// (5) Readback of the depth (and stencil) of a depth texture of any format.
+/// The depth is copied by a compute shader into a storage texture.
+/// The stencil cannot be sampled on every backend (OpenGL reads zeros),
+/// so it is read with the stencil test instead: one full-screen draw per stencil value,
+/// passing only where the stencil equals the value, and writing that value.
+struct DepthReadback {
+    /// Copy of the depth, as R32Float
+    depth_output: wgpu::Texture,
+    /// Compute pipeline and bind group copying the depth
+    depth_pass: (wgpu::ComputePipeline, wgpu::BindGroup),
+    /// Copy of the stencil, as R32Uint, if the depth format has a stencil
+    stencil_output: Option<wgpu::Texture>,
+    /// Render pipeline of the stencil passes, and the view of the depth texture they test
+    stencil_pass: Option<(wgpu::RenderPipeline, wgpu::TextureView)>,
+}
+
+impl DepthReadback {
+    fn new(device: &wgpu::Device, depth_texture: &wgpu::Texture) -> Self {
+        // (5.1) Initialize the output textures
+        let depth_output =
+            init_storage_texture(device, depth_texture.size(), wgpu::TextureFormat::R32Float);
+        let stencil_output = depth_texture
+            .format()
+            .has_stencil_aspect()
+            .then(|| init_stencil_output_texture(device, depth_texture.size()));
+
+        // (5.2) Define the compute pipeline copying the depth, and its bind group.
+        // The depth is read through a view of the depth aspect only.
+        let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("depth_readback_shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("depth_readback.wgsl").into()),
+        });
+        // The automatic layout would expect a filterable float texture for the depth,
+        // which depth textures are not, so we define the layout ourselves.
+        let bind_group_layout = depth_readback_bind_group_layout(device);
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("depth_readback_pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let depth_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("read_depth"),
+            layout: Some(&pipeline_layout),
+            module: &shader_module,
+            entry_point: "read_depth",
+        });
+        let depth_view = depth_texture.create_view(&wgpu::TextureViewDescriptor {
+            aspect: wgpu::TextureAspect::DepthOnly,
+            ..Default::default()
+        });
+        let output_view = depth_output.create_view(&Default::default());
+        let depth_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("read_depth"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&depth_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&output_view),
+                },
+            ],
+        });
+
+        // (5.2) Define the render pipeline of the stencil passes,
+        // which test the stencil of the depth texture without writing to it
+        let stencil_pass = stencil_output.as_ref().map(|stencil_output| {
+            let stencil_test = wgpu::StencilFaceState {
+                compare: wgpu::CompareFunction::Equal,
+                fail_op: wgpu::StencilOperation::Keep,
+                depth_fail_op: wgpu::StencilOperation::Keep,
+                pass_op: wgpu::StencilOperation::Keep,
+            };
+            let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("read_stencil"),
+                layout: None,
+                vertex: wgpu::VertexState {
+                    module: &shader_module,
+                    entry_point: "stencil_vertex",
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader_module,
+                    entry_point: "stencil_fragment",
+                    targets: &[Some(stencil_output.format().into())],
+                }),
+                primitive: Default::default(),
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: depth_texture.format(),
+                    depth_write_enabled: false,
+                    depth_compare: wgpu::CompareFunction::Always,
+                    stencil: wgpu::StencilState {
+                        front: stencil_test,
+                        back: stencil_test,
+                        read_mask: 0xff,
+                        write_mask: 0,
+                    },
+                    bias: Default::default(),
+                }),
+                multisample: Default::default(),
+                multiview: None,
+            });
+            (pipeline, depth_texture.create_view(&Default::default()))
+        });
+
+        Self {
+            depth_output,
+            depth_pass: (depth_pipeline, depth_bind_group),
+            stencil_output,
+            stencil_pass,
+        }
+    }
+
+    /// (9.2) Add the compute pass copying the depth into its storage texture,
+    /// and the render pass drawing the stencil values into their texture
+    fn encode(&self, encoder: &mut wgpu::CommandEncoder) {
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("depth_readback_pass"),
+                timestamp_writes: None,
+            });
+            // One thread per pixel, with workgroups of 8x8 threads
+            let size = self.depth_output.size();
+            let (pipeline, bind_group) = &self.depth_pass;
+            compute_pass.set_pipeline(pipeline);
+            compute_pass.set_bind_group(0, bind_group, &[]);
+            compute_pass.dispatch_workgroups(size.width.div_ceil(8), size.height.div_ceil(8), 1);
+        }
+
+        let (Some(stencil_output), Some((pipeline, depth_view))) =
+            (&self.stencil_output, &self.stencil_pass)
+        else {
+            return;
+        };
+        let output_view = stencil_output.create_view(&Default::default());
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("stencil_readback_pass"),
+            // The pixels with a stencil of 0 are not drawn, and keep the cleared 0
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &output_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: depth_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                }),
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_pass.set_pipeline(pipeline);
+        // One draw per (8 bits) stencil value, with the value as stencil reference
+        // and as instance index, so that the fragment shader can write it
+        for value in 1..=u8::MAX as u32 {
+            render_pass.set_stencil_reference(value);
+            render_pass.draw(0..3, value..value + 1);
+        }
+    }
+}
+
+/// (5.2) Layout of the depth readback bind group: the depth aspect of the depth texture,
+/// as an unfilterable float texture, and the storage texture it is copied into
+fn depth_readback_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("depth_readback_bind_group_layout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::StorageTexture {
+                    access: wgpu::StorageTextureAccess::WriteOnly,
+                    format: wgpu::TextureFormat::R32Float,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                },
+                count: None,
+            },
+        ],
+    })
+}
+
 /// Define the layout of Vertex buffers
 pub fn vtx_buffer_layout() -> wgpu::VertexBufferLayout<'static> {
     wgpu::VertexBufferLayout {
@@ -263,8 +609,28 @@ fn build_simple_pipeline(
     device: &wgpu::Device,
     shader_module: &wgpu::ShaderModule,
     texture_format: wgpu::TextureFormat,
+    depth_format: wgpu::TextureFormat,
     vertex_buffer_layout: wgpu::VertexBufferLayout,
 ) -> wgpu::RenderPipeline {
+    // Increment the stencil of the fragments passing the depth test,
+    // which counts how many times each pixel is drawn over.
+    // Only if the depth format has a stencil, since stencil writes are invalid otherwise.
+    let stencil_face = wgpu::StencilFaceState {
+        compare: wgpu::CompareFunction::Always,
+        fail_op: wgpu::StencilOperation::Keep,
+        depth_fail_op: wgpu::StencilOperation::Keep,
+        pass_op: wgpu::StencilOperation::IncrementClamp,
+    };
+    let stencil = if depth_format.has_stencil_aspect() {
+        wgpu::StencilState {
+            front: stencil_face,
+            back: stencil_face,
+            read_mask: 0xff,
+            write_mask: 0xff,
+        }
+    } else {
+        wgpu::StencilState::default()
+    };
     device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
         label: Some("Render Pipeline"),
         layout: None, // "auto"
@@ -290,7 +656,7 @@ fn build_simple_pipeline(
             strip_index_format: None,
             front_face: wgpu::FrontFace::Ccw,
             cull_mode: Some(wgpu::Face::Front),
-            // Setting this to anything other than Fill requires Features::NON_FILL_POLYGON_MODE
+            // Setting this to Line requires Features::POLYGON_MODE_LINE (see the wireframe)
             polygon_mode: wgpu::PolygonMode::Fill,
             // Requires Features::DEPTH_CLIP_CONTROL
             unclipped_depth: false,
@@ -298,13 +664,12 @@ fn build_simple_pipeline(
             conservative: false,
         },
         depth_stencil: Some(wgpu::DepthStencilState {
-            format: wgpu::TextureFormat::Depth32Float,
+            format: depth_format,
             // Enable WebGPU to write the depth (position.z) to the provided texture
             depth_write_enabled: true,
             // Keep the depth value closest to us (lower values)
             depth_compare: wgpu::CompareFunction::Less,
-            // Not using stencil stuff
-            stencil: wgpu::StencilState::default(),
+            stencil,
             bias: wgpu::DepthBiasState::default(),
         }),
         multisample: wgpu::MultisampleState {
@@ -318,21 +683,154 @@ fn build_simple_pipeline(
     })
 }
 
+/// (8) Wireframe overlay, drawn on top of the mesh
+struct Wireframe {
+    pipeline: wgpu::RenderPipeline,
+    /// Unrolled triangles (3 vertices per triangle, without index buffer),
+    /// so that the vertex index tells the shader which corner of its triangle each vertex is
+    vertex_buffer: wgpu::Buffer,
+    num_vertices: u32,
+}
+
+impl Wireframe {
+    fn new(
+        device: &wgpu::Device,
+        shader_module: &wgpu::ShaderModule,
+        texture_format: wgpu::TextureFormat,
+        depth_format: wgpu::TextureFormat,
+        mesh: &tobj::Mesh,
+    ) -> Self {
+        // Rasterize the triangle edges as lines if supported,
+        // otherwise fill the triangles and only keep the fragments close to the edges.
+        let polygon_mode = if device
+            .features()
+            .contains(wgpu::Features::POLYGON_MODE_LINE)
+        {
+            println!("Drawing the wireframe with the line polygon mode");
+            wgpu::PolygonMode::Line
+        } else {
+            println!("Line polygon mode not supported, drawing the wireframe with barycentric coordinates");
+            wgpu::PolygonMode::Fill
+        };
+
+        // Vertex i of the unrolled mesh is the corner (i % 3) of the triangle (i / 3)
+        let positions: Vec<f32> = mesh
+            .indices
+            .iter()
+            .flat_map(|&i| &mesh.positions[3 * i as usize..3 * i as usize + 3])
+            .copied()
+            .collect();
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Wireframe Vertex Buffer"),
+            contents: bytemuck::cast_slice(&positions),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        Self {
+            pipeline: build_wireframe_pipeline(
+                device,
+                shader_module,
+                texture_format,
+                depth_format,
+                vtx_buffer_layout(),
+                polygon_mode,
+            ),
+            vertex_buffer,
+            num_vertices: mesh.indices.len() as u32,
+        }
+    }
+}
+
+/// (8) Define the render pipeline of the wireframe overlay
+fn build_wireframe_pipeline(
+    device: &wgpu::Device,
+    shader_module: &wgpu::ShaderModule,
+    texture_format: wgpu::TextureFormat,
+    depth_format: wgpu::TextureFormat,
+    vertex_buffer_layout: wgpu::VertexBufferLayout,
+    polygon_mode: wgpu::PolygonMode,
+) -> wgpu::RenderPipeline {
+    // Filled triangles need the fallback fragment shader to only keep their edges
+    let fragment_entry_point = match polygon_mode {
+        wgpu::PolygonMode::Fill => "fragment_wireframe_fallback",
+        _ => "fragment_wireframe",
+    };
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Wireframe Pipeline"),
+        layout: None, // "auto"
+        vertex: wgpu::VertexState {
+            module: shader_module,
+            entry_point: "vertex_wireframe",
+            buffers: &[vertex_buffer_layout],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader_module,
+            entry_point: fragment_entry_point,
+            targets: &[Some(wgpu::ColorTargetState {
+                format: texture_format,
+                blend: Some(wgpu::BlendState {
+                    alpha: wgpu::BlendComponent::REPLACE,
+                    color: wgpu::BlendComponent::REPLACE,
+                }),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: Some(wgpu::Face::Front),
+            // Line requires Features::POLYGON_MODE_LINE
+            polygon_mode,
+            // Requires Features::DEPTH_CLIP_CONTROL
+            unclipped_depth: false,
+            // Requires Features::CONSERVATIVE_RASTERIZATION
+            conservative: false,
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: depth_format,
+            // The mesh depth is already there, no need to write it again
+            depth_write_enabled: false,
+            // Keep the edges at the same depth as the mesh triangles,
+            // and skip those hidden behind other triangles
+            depth_compare: wgpu::CompareFunction::LessEqual,
+            // Not using stencil stuff
+            stencil: wgpu::StencilState::default(),
+            // Slightly move the edges towards the camera,
+            // since lines are not rasterized exactly like the triangles they belong to
+            bias: wgpu::DepthBiasState {
+                constant: 0,
+                slope_scale: -1.0,
+                clamp: 0.0,
+            },
+        }),
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+    })
+}
+
 /// (9.1) Draw our pipeline (add render pass to the command encoder).
+#[allow(clippy::too_many_arguments)]
 fn draw_pipeline(
     encoder: &mut wgpu::CommandEncoder,
     pipeline: &wgpu::RenderPipeline,
     texture_view: &wgpu::TextureView,
     depth_texture_view: &wgpu::TextureView,
+    has_stencil: bool,
     vertex_buffer: &wgpu::Buffer,
     index_buffer: &wgpu::Buffer,
     num_indices: u32,
+    wireframe: Option<&Wireframe>,
 ) {
     // Setup the pass that will render into our texture
     let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
         label: Some("Render Pass"),
         color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-            view: &texture_view,  // the output texture
+            view: texture_view,   // the output texture
             resolve_target: None, // only useful for multisampling
             ops: wgpu::Operations {
                 // "load" specifies how data is read.
@@ -353,16 +851,27 @@ fn draw_pipeline(
                 load: wgpu::LoadOp::Clear(1.0),
                 store: wgpu::StoreOp::Store,
             }),
-            stencil_ops: None,
+            // The stencil (if any) is cleared to 0, and incremented by the pipeline
+            stencil_ops: has_stencil.then_some(wgpu::Operations {
+                load: wgpu::LoadOp::Clear(0),
+                store: wgpu::StoreOp::Store,
+            }),
         }),
         ..Default::default()
     });
 
     // Draw the render pass for our pipeline
-    render_pass.set_pipeline(&pipeline);
+    render_pass.set_pipeline(pipeline);
     render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
     render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
     render_pass.draw_indexed(0..num_indices, 0, 0..1);
+
+    // Draw the wireframe overlay on top of the mesh, in the same render pass
+    if let Some(wireframe) = wireframe {
+        render_pass.set_pipeline(&wireframe.pipeline);
+        render_pass.set_vertex_buffer(0, wireframe.vertex_buffer.slice(..));
+        render_pass.draw(0..wireframe.num_vertices, 0..1);
+    }
 }
 
 /// (9.2) Copy the texture output into a buffer
@@ -371,21 +880,20 @@ fn copy_texture_to_buffer(
     texture: &wgpu::Texture,
     output_buffer: &wgpu::Buffer,
 ) {
-    let texel_size = texture.format().block_copy_size(None).unwrap();
     encoder.copy_texture_to_buffer(
         // source
         wgpu::ImageCopyTexture {
             aspect: wgpu::TextureAspect::All,
-            texture: &texture,
+            texture,
             mip_level: 0,
             origin: wgpu::Origin3d::ZERO,
         },
         // destination
         wgpu::ImageCopyBuffer {
-            buffer: &output_buffer,
+            buffer: output_buffer,
             layout: wgpu::ImageDataLayout {
                 offset: 0,
-                bytes_per_row: Some(texel_size * texture.width()),
+                bytes_per_row: Some(padded_bytes_per_row(texture.format(), texture.width())),
                 rows_per_image: Some(texture.height()),
             },
         },
@@ -414,3 +922,18 @@ async fn retrieve_texture_buffer_data<'a>(
     // Will panic if buffer_slice.map_async() did not finish yet.
     buffer_slice.get_mapped_range()
 }
+
+/// (11) Decode the data copied from a texture, with its padded rows, according to its format
+fn decode_texture_data(texture: &wgpu::Texture, data: &[u8]) -> TextureImage {
+    let (format, width, height) = (texture.format(), texture.width(), texture.height());
+    let bytes_per_row = padded_bytes_per_row(format, width);
+    TextureImage::decode(
+        format,
+        wgpu::TextureAspect::All,
+        data,
+        width,
+        height,
+        bytes_per_row,
+    )
+    .unwrap_or_else(|error| panic!("Cannot decode the texture data: {error}"))
+}
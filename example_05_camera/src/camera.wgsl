@@ -6,17 +6,46 @@
 // But for this minimal example it's fine.
 
 /// Perspective projection parameters
+/// (booleans are encoded as u32, 0 is false)
 struct ProjCamParams {
     focal: f32,
     ratio: f32,
     near: f32,
     far: f32,
+    reverse_z: u32,
+    infinite_far: u32,
+    // Angle (in radians) of the camera orbiting around the bunny, for the turntable animation
+    orbit_angle: f32,
 }
 
 @group(0) @binding(0) var<uniform> proj_cam_params: ProjCamParams;
 
+/// Primitive i is made of the vertex_stride vertices starting at vertex i * vertex_stride,
+/// depending on the topology: 3 for a triangle list, 2 for a line list, 1 for a point list.
+/// With a strip topology, the vertices are shared between primitives, so vertex_stride is 0,
+/// and the triangle IDs and barycentric coordinates are not written (left to 0).
+struct PrimitiveParams {
+    vertex_stride: u32,
+}
+
+@group(0) @binding(1) var<uniform> primitive_params: PrimitiveParams;
+
+/// The mesh is drawn without index buffer, each triangle having its own 3 vertices.
+/// So the vertex index tells us both the triangle and the corner of that triangle.
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    // Integers cannot be interpolated, and it's the same for all the vertices of a primitive
+    @location(0) @interpolate(flat) triangle_id: u32,
+    // (1, 0, 0), (0, 1, 0) or (0, 0, 1) depending on the corner,
+    // the rasterizer interpolates it into the barycentric coordinates of the fragment
+    @location(1) barycentric: vec3<f32>,
+}
+
 @vertex
-fn vertex_main(@location(0) position: vec3<f32>) -> @builtin(position) vec4<f32> {
+fn vertex_main(
+    @location(0) position: vec3<f32>,
+    @builtin(vertex_index) vertex_index: u32,
+) -> VertexOutput {
     let focal = proj_cam_params.focal;
     let ratio = proj_cam_params.ratio;
     let near = proj_cam_params.near;
@@ -53,19 +82,45 @@ fn vertex_main(@location(0) position: vec3<f32>) -> @builtin(position) vec4<f32>
     // 
     // > Note: WGSL uses column-major matrices,
     // > so yo might need to transpose your matrices if you build them in row-major.
+    //
+    // With a "reverse Z" mapping, the near plane is mapped to 1.0 and the far plane to 0.0,
+    // simply by swapping near and far in the formula above.
+    // Since floating point numbers are much denser close to 0.0,
+    // this compensates the 1/z distribution of depth values,
+    // resulting in a much more even precision over the whole depth range.
+    // Then the far plane can even be sent to infinity, where the depth simply becomes near / z:
+    //
+    // ```txt
+    // 0.0  , 0.0          , 0.0               , near                      ,
+    // ```
+    // (far / (far - near) is NaN with an infinite far plane, so it is only computed when finite)
+    var depth_row: vec2<f32>;
+    if proj_cam_params.reverse_z == 0u {
+        depth_row = vec2<f32>(far / (far - near), -far * near / (far - near));
+    } else if proj_cam_params.infinite_far != 0u {
+        depth_row = vec2<f32>(0.0, near);
+    } else {
+        depth_row = vec2<f32>(near / (near - far), -near * far / (near - far));
+    }
     let proj_mat = transpose(mat4x4f(
-        focal, 0.0          , 0.0               , 0.0                       ,
-        0.0  , focal * ratio, 0.0               , 0.0                       ,
-        0.0  , 0.0          , far / (far - near), -far * near / (far - near),
-        0.0  , 0.0          , 1.0               , 0.0                       ,
+        focal, 0.0          , 0.0        , 0.0        ,
+        0.0  , focal * ratio, 0.0        , 0.0        ,
+        0.0  , 0.0          , depth_row.x, depth_row.y,
+        0.0  , 0.0          , 1.0        , 0.0        ,
     ));
 
-    // Build the view matrix (extrinsics)
+    // Build the view matrix (extrinsics).
+    // The camera orbits around the vertical axis going through the bunny, at (0, 0, 0.5).
+    // Its view matrix translates that point to the origin, rotates around the Y axis,
+    // and translates back: p' = R * (p - center) + center.
+    // With an angle of 0.0, this is the identity.
+    let c = cos(proj_cam_params.orbit_angle);
+    let s = sin(proj_cam_params.orbit_angle);
     let view_mat = transpose(mat4x4f(
-        1.0, 0.0, 0.0, 0.0,
-        0.0, 1.0, 0.0, 0.0,
-        0.0, 0.0, 1.0, 0.0,
-        0.0, 0.0, 0.0, 1.0,
+        c  , 0.0, -s , 0.5 * s      ,
+        0.0, 1.0, 0.0, 0.0          ,
+        s  , 0.0, c  , 0.5 - 0.5 * c,
+        0.0, 0.0, 0.0, 1.0          ,
     ));
 
     // Build the model matrix applying a small translation.
@@ -77,13 +132,73 @@ fn vertex_main(@location(0) position: vec3<f32>) -> @builtin(position) vec4<f32>
     ));
 
     let homogeneous_pos = vec4<f32>(position, 1.0);
-    return proj_mat * view_mat * model_mat * homogeneous_pos;
+
+    let vertex_stride = primitive_params.vertex_stride;
+    var barycentric = vec3<f32>(0.0);
+    if vertex_stride != 0u {
+        barycentric[vertex_index % 3u] = 1.0;
+    }
+    return VertexOutput(
+        // clip_position
+        proj_mat * view_mat * model_mat * homogeneous_pos,
+        // triangle_id (the primitive ID with other list topologies),
+        // shifted by 1 since 0 is kept for the background
+        select(0u, vertex_index / max(vertex_stride, 1u) + 1u, vertex_stride != 0u),
+        // barycentric
+        barycentric,
+    );
 }
 
 // Fragment shader
-// Output the normalized Z clip coordinate
+//
+// Outputs to 3 render targets at once:
+// 0. the normalized Z clip coordinate
+//    (flipped back with reverse Z, so that closer fragments stay darker)
+// 1. the triangle ID
+// 2. the barycentric coordinates of the fragment in that triangle.
+//    The third one is implicit since they sum up to 1.
+
+struct FragmentOutput {
+    @location(0) color: vec4<f32>,
+    @location(1) triangle_id: u32,
+    @location(2) barycentric: vec2<f32>,
+}
+
+@fragment
+fn fragment_main(in: VertexOutput) -> FragmentOutput {
+    let depth = select(in.clip_position.z, 1.0 - in.clip_position.z, proj_cam_params.reverse_z != 0u);
+    return FragmentOutput(
+        // color
+        vec4<f32>(vec3<f32>(depth), 1.0),
+        // triangle_id
+        in.triangle_id,
+        // barycentric
+        in.barycentric.xy,
+    );
+}
+
+// Wireframe overlay
+//
+// If the device supports it, the wireframe pipeline rasterizes the triangles
+// with PolygonMode::Line, so that only their edges reach the fragment shader.
+// Otherwise, the triangles are filled, and the fallback fragment shader only keeps
+// the fragments close to an edge, i.e. where one of the barycentric coordinates is close to 0.
+// Only the color target is written, the triangle IDs and barycentrics are those of the mesh.
+
+const WIREFRAME_COLOR = vec4<f32>(1.0, 0.3, 0.0, 1.0);
+
+@fragment
+fn fragment_wireframe() -> @location(0) vec4<f32> {
+    return WIREFRAME_COLOR;
+}
 
 @fragment
-fn fragment_main(@builtin(position) clip_pos: vec4<f32>) -> @location(0) vec4<f32> {
-    return vec4<f32>(vec3<f32>(clip_pos.z), 1.0);
+fn fragment_wireframe_fallback(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Dividing by the screen-space rate of change gives the distances to the edges in pixels.
+    // Edges are shared by two triangles, each drawing half of the line width.
+    let edge_distance = in.barycentric / fwidth(in.barycentric);
+    if min(edge_distance.x, min(edge_distance.y, edge_distance.z)) > 0.5 {
+        discard;
+    }
+    return WIREFRAME_COLOR;
 }
@@ -0,0 +1,23 @@
+// Readback of the depth texture into a storage texture, with a compute shader.
+//
+// Depth textures can only be copied into buffers on some devices (not on OpenGL, for instance).
+// But any depth format can be read by a shader, so we copy it into an R32Float storage texture,
+// which can then be copied into a buffer like any color texture.
+//
+// As in the SSAO shader, the depth is declared as a regular float texture rather than
+// a texture_depth_2d, which is valid with an "unfilterable float" layout (see main.rs)
+// and supported by more backends.
+
+@group(0) @binding(0) var depth_texture: texture_2d<f32>;
+@group(0) @binding(1) var depth_output: texture_storage_2d<r32float, write>;
+
+@compute
+@workgroup_size(8, 8)
+fn read_depth(@builtin(global_invocation_id) global_id: vec3u) {
+    // Skip the threads in excess, when the size is not a multiple of the workgroup size (8x8)
+    let pixel = global_id.xy;
+    if any(pixel >= textureDimensions(depth_texture)) {
+        return;
+    }
+    textureStore(depth_output, pixel, textureLoad(depth_texture, pixel, 0));
+}
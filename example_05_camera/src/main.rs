@@ -4,36 +4,103 @@
 //! except this time we provide camera parameters to the vertex shader with uniform buffers
 //! to perform a perspective projection instead of a simple orthogonal projection.
 //! In addition, we also introduce the notion of near/far planes to control Z clipping.
+//!
+//! Optionally, the camera can use a "reverse Z" depth mapping (run with `--reverse-z`),
+//! where the near plane maps to a depth of 1.0 and the far plane to 0.0.
+//! Combined with the floating point depth format, this spreads the depth precision
+//! much more evenly, and even allows a far plane at infinity (add `--infinite-far`).
+//!
+//! Next to the color output, the fragment shader also writes to two other render targets:
+//! the ID of the triangle visible in each pixel (`R32Uint`, 0 is the background),
+//! and the barycentric coordinates of the pixel inside that triangle (`Rg32Float`).
+//! This gives an exact pixel-to-triangle correspondence, straight from the rasterizer.
+//! To get these, the mesh is drawn without index buffer, each triangle having its own 3 vertices.
+//! With a line or point list topology, the IDs are those of the primitives
+//! (see `PrimitiveParams`).
+//! With a strip topology, the triangles sharing an edge are joined into strips, drawn with
+//! an index buffer whose strips are separated by the primitive restart index (see `triangle_strips`).
+//! The vertices are then shared between primitives, so there are no triangle IDs
+//! nor barycentric coordinates: they are left to 0, and not saved.
+//!
+//! Run with `--wireframe` to draw the triangle edges as an overlay on top of the mesh.
+//! If the device supports `Features::POLYGON_MODE_LINE`, the wireframe is rasterized as lines.
+//! Otherwise, the triangles are filled and the fragment shader only keeps
+//! the fragments close to an edge, using their barycentric coordinates.
+//!
+//! The rasterizer state of the pipelines is also configurable from the command line
+//! (see `RasterizerConfig`): the topology, the winding of front faces, which faces are culled,
+//! as well as the unclipped depth and conservative rasterization device features.
+//!
+//! Run with `--ssao` to add a screen-space ambient occlusion (SSAO) post-process,
+//! made of compute passes reading the depth texture after the render pass.
+//! The ambient occlusion darkens the creases of the bunny, where less ambient light gets in,
+//! which gives a much better sense of its shape (see `SsaoParams` for its settings).
+//!
+//! Run with `--turntable 36` to also render an animation of 36 frames, the camera orbiting
+//! around the bunny. Each frame updates the camera uniform buffer with `queue.write_buffer`.
+//! The frames are saved as an animated "turntable.gif",
+//! or as a sequence of "turntable_000.png", "turntable_001.png", ... with `--turntable-format png`.
+//! The near and far planes of the animation frames are moved apart, so that the bunny is not
+//! clipped while rotating (the still "image.png" and "depth.png" keep their planes).
 //! The steps of this minimal program are the following.
 //!
 //! 1. (async) Initialize the connection with the GPU device
-//! 2. Initialize a wgpu Texture object that will serve as a write target for fragment shader
-//! 3. Initialize a wgpu Buffer where the Texture output will be transferred to
+//! 2. Initialize a wgpu Texture object that will serve as a write target for fragment shader.
+//!    **(new)** Same for the triangle IDs and barycentric coordinates textures
+//! 3. Initialize a wgpu Buffer where each Texture output will be transferred to
 //! 4. Initialize a wgpu Texture object that will serve as a write target for the depth
+//!    and a wgpu Buffer where the depth texture will be transferred to.
+//!    **(new)** Depth textures cannot be copied into buffers on every device (e.g. on OpenGL),
+//!    so the depth is first copied into an R32Float storage texture by a compute pass.
 //! 5. Load the OBJ bunny
-//!    1. Create and initialize a vertex buffer containing the triangle coordinates
-//!    2. Create and initialize an index buffer containing the vertex indices in the face
+//!    1. **(new)** Unroll the indexed triangles, so that each triangle has its own 3 vertices
+//!    2. Create and initialize a vertex buffer containing the triangle coordinates
+//!    3. **(new)** With a strip topology, join the triangles into strips instead,
+//!       and create an index buffer for them, with the original (shared) vertices
 //! 6. Load the shader module, containing both the vertex and fragment shaders
 //! 7. Define our render pipeline, including:
 //!    - the vertex shader: include our vertex buffer layout
-//!    - the fragment shader
-//!    - the primitive type (triangle list)
+//!    - the fragment shader, **(new)** with its 3 render targets
+//!    - **(new)** the rasterizer configuration (primitive type, front face, culling, ...)
 //!    - the depth_stencil is configured to compare depths on fragments
-//!      and only keep it when it's closer ("Less").
+//!      and only keep it when it's closer ("Less", or "Greater" with reverse Z).
 //!      Also specifies to store that final depth into our depth texture
+//!
+//!    **(new)** And optionally, a second pipeline for the wireframe overlay
 //! 8. **(new)** Create the camera
-//!    1. Create a perspective projection camera and put it into a uniform buffer
+//!    1. Put the perspective projection camera parameters into a uniform buffer,
+//!       **(new)** and the vertex stride between primitives into another one
 //!    2. Create a bind group and let WebGPU derive the layout implicitely
-//! 9. Define our command encoder:
-//!    1. Start by defining our render pass:
-//!       - Link to the texture output
-//!       - Link to the pipeline
-//!       - **(new)** Provide the camera bind group
-//!       - Provide vertex buffer and index buffer
-//!       - Draw the primitive
-//!    2. Add a command to copy the fragment texture into the output buffer
-//! 10. Submit our commands to the device queue
-//! 11. (async) Transfer the output buffer into an image we can save to disk
+//! 9. **(new)** Optionally, prepare the SSAO compute passes:
+//!    1. Generate the sample kernel, and put it with the SSAO parameters into buffers
+//!    2. Initialize the storage textures written by the compute passes
+//!    3. Define the compute pipelines, and their bind groups
+//! 10. Define our command encoder:
+//!     1. **(new)** Clear the triangle IDs and barycentric coordinates textures,
+//!        each in its own render pass.
+//!        Then define our render pass:
+//!        - Link to the texture outputs
+//!        - Link to the pipeline
+//!        - **(new)** Provide the camera bind group
+//!        - Provide vertex buffer, **(new)** and the index buffer of the strips
+//!        - Draw the primitive
+//!        - **(new)** Draw the wireframe overlay on top of it
+//!     2. **(new)** Optionally, compute the ambient occlusion and modulate the color with it
+//!     3. **(new)** Copy the depth into its storage texture with the readback compute pass
+//!     4. Add a command to copy the fragment and depth textures into their respective buffers
+//! 11. Submit our commands to the device queue
+//! 12. (async) Transfer the output buffers into images we can save to disk.
+//!     The depth is converted back to the conventional (non-reversed) depth mapping.
+//!     **(new)** The triangle IDs and barycentric coordinates are saved as raw binary files
+//!     (little-endian, row-major), and the barycentric coordinates also as an RGB image.
+//!     **(new)** With SSAO, the raw and blurred ambient occlusion maps are also saved.
+//! 13. **(new)** Optionally, render the turntable animation. For each frame:
+//!     1. Update the orbit angle of the camera in its uniform buffer,
+//!        with the near and far planes of the animation
+//!     2. Encode the same commands as above, only copying the color output.
+//!        The triangle IDs and barycentric coordinates textures are cleared again by each frame,
+//!        so they always hold the primitives of the last frame only.
+//!     3. Submit them, transfer the output buffer into an image, and add it to the animation
 
 use wgpu::util::DeviceExt; // Utility trait to create and initialize buffers with device.create_buffer_init()
 
@@ -43,43 +110,139 @@ fn main() {
 }
 
 async fn run() {
+    // Optional depth mapping and wireframe settings, provided as command line flags
+    let args: Vec<String> = std::env::args().collect();
+    let reverse_z = args.iter().any(|arg| arg == "--reverse-z");
+    let infinite_far = args.iter().any(|arg| arg == "--infinite-far");
+    let draw_wireframe = args.iter().any(|arg| arg == "--wireframe");
+    assert!(
+        reverse_z || !infinite_far,
+        "An infinite far plane is only supported with reverse Z (--reverse-z)"
+    );
+    let rasterizer_config = RasterizerConfig::from_args(&args);
+    let ssao_params = args
+        .iter()
+        .any(|arg| arg == "--ssao")
+        .then(|| SsaoParams::from_args(&args));
+    assert!(
+        !draw_wireframe || rasterizer_config.topology == wgpu::PrimitiveTopology::TriangleList,
+        "The wireframe is only supported with the triangle-list topology"
+    );
+    let arg_value = |name: &str| {
+        let position = args.iter().position(|arg| arg == name)?;
+        Some(
+            args.get(position + 1)
+                .expect("Missing argument value")
+                .as_str(),
+        )
+    };
+    let turntable_frames: Option<u32> =
+        arg_value("--turntable").map(|value| value.parse().unwrap());
+    let turntable_format = arg_value("--turntable-format").unwrap_or("gif");
+    assert!(
+        ["gif", "png"].contains(&turntable_format),
+        "Unknown turntable format: {turntable_format}"
+    );
+
     // (1) Initializing WebGPU
     println!("Initializing WebGPU ...");
-    let (device, queue) = init_wgpu_device().await.unwrap();
+    let (device, queue, adapter_info) = init_wgpu_device().await.unwrap();
+    rasterizer_config.validate(device.features());
 
-    // (2) Initialize the output texture
+    // (2) Initialize the output textures
     let width = 256;
     let height = 256;
-    let texture = init_output_texture(&device, width, height);
+    let texture = init_output_texture(&device, width, height, wgpu::TextureFormat::Rgba8Unorm);
     let texture_view = texture.create_view(&Default::default());
+    let triangle_id_texture =
+        init_output_texture(&device, width, height, wgpu::TextureFormat::R32Uint);
+    let triangle_id_texture_view = triangle_id_texture.create_view(&Default::default());
+    let barycentric_texture =
+        init_output_texture(&device, width, height, wgpu::TextureFormat::Rg32Float);
+    let barycentric_texture_view = barycentric_texture.create_view(&Default::default());
 
-    // (3) Initialize a buffer for the texture output
+    // (3) Initialize a buffer for each texture output
     let output_buffer_desc = create_texture_buffer_descriptor(&texture);
     let output_buffer = device.create_buffer(&output_buffer_desc);
+    let triangle_id_buffer_desc = create_texture_buffer_descriptor(&triangle_id_texture);
+    let triangle_id_buffer = device.create_buffer(&triangle_id_buffer_desc);
+    let barycentric_buffer_desc = create_texture_buffer_descriptor(&barycentric_texture);
+    let barycentric_buffer = device.create_buffer(&barycentric_buffer_desc);
 
     // (4) Initialize the depth texture
     let depth_texture = init_depth_texture(&device, width, height);
     let depth_texture_view = depth_texture.create_view(&Default::default());
+    let depth_readback = DepthReadback::new(&device, &depth_texture_view, depth_texture.size());
+    let depth_buffer_desc = create_texture_buffer_descriptor(&depth_readback.depth_output);
+    let depth_buffer = device.create_buffer(&depth_buffer_desc);
 
     // (5) Load the OBJ bunny
     let (models, _) = tobj::load_obj("bunny.obj", &tobj::GPU_LOAD_OPTIONS).unwrap();
     let bunny = &models[0].mesh;
 
-    // (5.1) Create and initialize the vertex buffer for the vertices in the bunny mesh
+    // (5.1) Unroll the triangles: vertex i of the unrolled mesh is the corner (i % 3)
+    // of the triangle (i / 3) in the bunny mesh, which the shader retrieves with the vertex index.
+    let positions: Vec<f32> = bunny
+        .indices
+        .iter()
+        .flat_map(|&i| &bunny.positions[3 * i as usize..3 * i as usize + 3])
+        .copied()
+        .collect();
+
+    // (5.2) Create and initialize the vertex buffer for the unrolled vertices
     // (needs the DeviceExt trait)
     let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
         label: Some("Vertex Buffer"),
-        contents: bytemuck::cast_slice(&bunny.positions),
+        contents: bytemuck::cast_slice(&positions),
         usage: wgpu::BufferUsages::VERTEX,
     });
+    // Ranges of vertices (or indices) drawn, with one draw call each
+    let mut draw_ranges: Vec<_> = std::iter::once(0..bunny.indices.len() as u32).collect();
 
-    // (5.2) Create and initialize the index buffer for the indices of the vertices in the bunny mesh
-    // (needs the DeviceExt trait)
-    let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-        label: Some("Index Buffer"),
-        contents: bytemuck::cast_slice(&bunny.indices),
-        usage: wgpu::BufferUsages::INDEX,
+    // (5.3) With a strip topology, draw the strips with an index buffer into the original vertices
+    let strips = rasterizer_config.strip_index_format.map(|index_format| {
+        let strips = triangle_strips(&bunny.indices);
+        println!(
+            "Drawing the {} triangles as {} strips",
+            bunny.indices.len() / 3,
+            strips.len()
+        );
+        // The indices of the strips, and a restart index between two strips
+        let mut start = 0;
+        let strip_ranges: Vec<_> = strips
+            .iter()
+            .map(|strip| {
+                let range = start..start + strip.len() as u32;
+                start = range.end + 1;
+                range
+            })
+            .collect();
+        // The GL backend of wgpu does not enable the primitive restart,
+        // so each strip is drawn by its own call there, skipping the restart indices
+        draw_ranges = if adapter_info.backend == wgpu::Backend::Gl {
+            strip_ranges
+        } else {
+            std::iter::once(0..strip_ranges.last().unwrap().end).collect()
+        };
+        let index_data = strip_index_data(&strips, bunny.positions.len() / 3, index_format);
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Strip Vertex Buffer"),
+            contents: bytemuck::cast_slice(&bunny.positions),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Strip Index Buffer"),
+            contents: &index_data,
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        (vertex_buffer, index_buffer, index_format)
     });
+    let (vertex_buffer, index_buffer) = match &strips {
+        Some((vertex_buffer, index_buffer, index_format)) => {
+            (vertex_buffer, Some((index_buffer, *index_format)))
+        }
+        None => (&vertex_buffer, None),
+    };
 
     // (6) Load the shader module, containing both the vertex and fragment shaders
     let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
@@ -87,51 +250,147 @@ async fn run() {
         source: wgpu::ShaderSource::Wgsl(include_str!("camera.wgsl").into()),
     });
 
+    // Perspective projection camera parameters.
+    // Created before the pipeline since the depth test depends on the camera depth mapping.
+    let camera = Camera::new(
+        5.0,
+        (width as f32) / (height as f32),
+        0.45,
+        if infinite_far { f32::INFINITY } else { 0.49 },
+        reverse_z,
+    );
+
     // (7) Define our pipeline
     let pipeline = build_simple_pipeline(
         &device,
         &shader_module,
-        texture.format(),
+        &[
+            texture.format(),
+            triangle_id_texture.format(),
+            barycentric_texture.format(),
+        ],
         vtx_buffer_layout(),
+        camera.depth_compare(),
+        &rasterizer_config,
     );
 
-    // (8.1) Create a perspective projection camera and put it into a uniform buffer
-    let camera = Camera {
-        focal_length: 5.0,
-        aspect_ratio: (width as f32) / (height as f32),
-        near_plane: 0.45,
-        far_plane: 0.49,
-    };
+    // (7) Define the optional wireframe overlay pipeline.
+    // Rasterize the triangle edges as lines if supported,
+    // otherwise fill the triangles and only keep the fragments close to the edges.
+    let wireframe_pipeline = draw_wireframe.then(|| {
+        let polygon_mode = if device.features().contains(wgpu::Features::POLYGON_MODE_LINE) {
+            println!("Drawing the wireframe with the line polygon mode");
+            wgpu::PolygonMode::Line
+        } else {
+            println!("Line polygon mode not supported, drawing the wireframe with barycentric coordinates");
+            wgpu::PolygonMode::Fill
+        };
+        build_wireframe_pipeline(
+            &device,
+            &shader_module,
+            &[
+                texture.format(),
+                triangle_id_texture.format(),
+                barycentric_texture.format(),
+            ],
+            vtx_buffer_layout(),
+            camera.depth_compare(),
+            &rasterizer_config,
+            polygon_mode,
+        )
+    });
+
+    // (8.1) Put the perspective projection camera into a uniform buffer,
+    // and the parameters deriving the primitive IDs from the vertex indices into another one
     let camera_buffer = camera.create_uniform_buffer(&device);
+    let primitive_buffer =
+        PrimitiveParams::new(rasterizer_config.topology).create_uniform_buffer(&device);
     // (8.2) For the bind group layout, we let WebGPU derive it implicitely.
     // We just tell it that it's the first one at index 0.
-    let camera_bind_group =
-        Camera::create_bind_group(&device, &camera_buffer, &pipeline.get_bind_group_layout(0));
+    let camera_bind_group = Camera::create_bind_group(
+        &device,
+        &camera_buffer,
+        &primitive_buffer,
+        &pipeline.get_bind_group_layout(0),
+    );
+    // Each implicit layout is specific to its pipeline, so the wireframe needs its own bind group
+    let wireframe = wireframe_pipeline.as_ref().map(|wireframe_pipeline| {
+        let layout = wireframe_pipeline.get_bind_group_layout(0);
+        let bind_group =
+            Camera::create_bind_group(&device, &camera_buffer, &primitive_buffer, &layout);
+        (wireframe_pipeline, bind_group)
+    });
+
+    // (9) Optionally, prepare the SSAO compute passes
+    let ssao = ssao_params.map(|(params, num_samples)| {
+        Ssao::new(
+            &device,
+            &camera_buffer,
+            &params,
+            num_samples,
+            &depth_texture_view,
+            &texture_view,
+            depth_texture.size(),
+        )
+    });
+
+    // (10.1 & 10.2) The rendering commands of a frame,
+    // reused by each frame of the optional turntable animation
+    let encode_frame = |encoder: &mut wgpu::CommandEncoder| {
+        // (10.1) Draw our pipeline (add render pass to the command encoder)
+        // This needs to be inside {...} or a function so that the &pipeline lifetime works.
+        draw_pipeline(
+            encoder,
+            &pipeline,
+            &[
+                &texture_view,
+                &triangle_id_texture_view,
+                &barycentric_texture_view,
+            ],
+            &depth_texture_view,
+            camera.depth_clear_value(),
+            &camera_bind_group,
+            vertex_buffer,
+            index_buffer,
+            &draw_ranges,
+            wireframe
+                .as_ref()
+                .map(|(pipeline, bind_group)| (*pipeline, bind_group)),
+        );
+
+        // (10.2) Compute the ambient occlusion after the render pass, which wrote the depth
+        if let Some(ssao) = &ssao {
+            ssao.compute(encoder);
+        }
+    };
 
     // Initialize a command encoder
     let mut encoder = device.create_command_encoder(&Default::default());
+    encode_frame(&mut encoder);
 
-    // (9.1) Draw our pipeline (add render pass to the command encoder)
-    // This needs to be inside {...} or a function so that the &pipeline lifetime works.
-    draw_pipeline(
-        &mut encoder,
-        &pipeline,
-        &texture_view,
-        &depth_texture_view,
-        &camera_bind_group,
-        &vertex_buffer,
-        &index_buffer,
-        bunny.indices.len() as u32,
-    );
+    // (10.3) Copy the depth into a storage texture, which can be copied into a buffer
+    depth_readback.compute(&mut encoder);
 
-    // (9.2) Add commands to copy the textures into their respective buffers
-    copy_texture_to_buffer(&mut encoder, &texture, &output_buffer);
+    // (10.4) Add commands to copy the textures into their respective buffers.
+    // With SSAO, the color output is the one modulated by the ambient occlusion.
+    let color_texture = ssao.as_ref().map_or(&texture, |ssao| &ssao.color_texture);
+    copy_texture_to_buffer(&mut encoder, color_texture, &output_buffer);
+    copy_texture_to_buffer(&mut encoder, &triangle_id_texture, &triangle_id_buffer);
+    copy_texture_to_buffer(&mut encoder, &barycentric_texture, &barycentric_buffer);
+    copy_texture_to_buffer(&mut encoder, &depth_readback.depth_output, &depth_buffer);
+    let ao_buffers = ssao.as_ref().map(|ssao| {
+        [&ssao.raw_ao_texture, &ssao.ao_texture].map(|ao_texture| {
+            let ao_buffer = device.create_buffer(&create_texture_buffer_descriptor(ao_texture));
+            copy_texture_to_buffer(&mut encoder, ao_texture, &ao_buffer);
+            ao_buffer
+        })
+    });
 
-    // (10) Finalize the command encoder and send it to the queue
+    // (11) Finalize the command encoder and send it to the queue
     println!("Submitting commands to the queue ...");
     queue.submit(Some(encoder.finish()));
 
-    // (11) Transfer texture buffers into image buffers.
+    // (12) Transfer texture buffers into image buffers.
     // New scope to encapsulate img_data BufferView and drop it before unmapping.
     {
         // Transfer the texture output buffer into an image buffer
@@ -141,16 +400,137 @@ async fn run() {
 
         println!("Saving the image to disk ...");
         img.save("image.png").unwrap();
+
+        // Do the same for the depth buffer, converted back to the conventional depth mapping
+        println!("Saving the GPU depth output into an image ...");
+        let depth_data = retrieve_texture_buffer_data(&device, &depth_buffer).await;
+        let depth_data_f32: &[f32] = bytemuck::cast_slice(&depth_data);
+        let img_data_u16: Vec<u16> = depth_data_f32
+            .iter()
+            .map(|&d| (camera.standard_depth(d).clamp(0.0, 1.0) * 65535.0) as u16)
+            .collect();
+        let img_u16 =
+            image::ImageBuffer::<image::Luma<u16>, _>::from_raw(width, height, img_data_u16)
+                .unwrap();
+        img_u16.save("depth.png").unwrap();
+
+        // Save the triangle IDs and barycentric coordinates as raw binary files,
+        // only available with the list topologies
+        if strips.is_some() {
+            println!("No triangle IDs nor barycentric coordinates with a strip topology");
+        } else {
+            println!("Saving the triangle IDs and barycentric coordinates ...");
+            let triangle_id_data = retrieve_texture_buffer_data(&device, &triangle_id_buffer).await;
+            std::fs::write("triangle_id.bin", &triangle_id_data as &[u8]).unwrap();
+            let barycentric_data = retrieve_texture_buffer_data(&device, &barycentric_buffer).await;
+            std::fs::write("barycentric.bin", &barycentric_data as &[u8]).unwrap();
+
+            // And visualize the barycentric coordinates (b0, b1, b2) as an RGB image
+            let triangle_ids: &[u32] = bytemuck::cast_slice(&triangle_id_data);
+            let barycentrics: &[[f32; 2]] = bytemuck::cast_slice(&barycentric_data);
+            let img_data_bary: Vec<u8> = triangle_ids
+                .iter()
+                .zip(barycentrics)
+                .flat_map(|(&id, &[b0, b1])| {
+                    let b2 = if id == 0 { 0.0 } else { 1.0 - b0 - b1 };
+                    [b0, b1, b2].map(|b| (b.clamp(0.0, 1.0) * 255.0) as u8)
+                })
+                .collect();
+            let img_bary = image::RgbImage::from_raw(width, height, img_data_bary).unwrap();
+            img_bary.save("barycentric.png").unwrap();
+        }
+
+        // Save the raw and blurred ambient occlusion maps
+        if let Some(ao_buffers) = &ao_buffers {
+            println!("Saving the ambient occlusion maps ...");
+            for (name, ao_buffer) in ["ao_raw.png", "ao.png"].iter().zip(ao_buffers) {
+                let ao_data = retrieve_texture_buffer_data(&device, ao_buffer).await;
+                let img_ao = image::RgbaImage::from_raw(width, height, ao_data.to_vec()).unwrap();
+                img_ao.save(name).unwrap();
+            }
+        }
     }
 
     // Flushes any pending write operations and unmaps the buffer from host memory
     output_buffer.unmap();
+    depth_buffer.unmap();
+    if strips.is_none() {
+        triangle_id_buffer.unmap();
+        barycentric_buffer.unmap();
+    }
+    for ao_buffer in ao_buffers.iter().flatten() {
+        ao_buffer.unmap();
+    }
+
+    // (13) Optionally, render the turntable animation
+    if let Some(num_frames) = turntable_frames {
+        println!("Rendering the turntable animation ({num_frames} frames) ...");
+        let mut gif_encoder = (turntable_format == "gif").then(|| {
+            let file = std::fs::File::create("turntable.gif").unwrap();
+            let mut gif_encoder =
+                gif::Encoder::new(file, width as u16, height as u16, &[]).unwrap();
+            gif_encoder.set_repeat(gif::Repeat::Infinite).unwrap();
+            gif_encoder
+        });
+        // The bunny is about 0.12 wide around its center at Z = 0.5, so the near and far planes
+        // of the animation surround the whole circle it covers while rotating.
+        // The still frame above keeps the tighter planes.
+        let turntable_camera = Camera {
+            near_plane: 0.38,
+            far_plane: if infinite_far { f32::INFINITY } else { 0.62 },
+            ..camera
+        };
+        for frame_index in 0..num_frames {
+            // (13.1) Update the camera uniform buffer (which needs the COPY_DST usage).
+            // The write is scheduled before the commands of the next submission.
+            let orbit_angle = 2.0 * std::f32::consts::PI * frame_index as f32 / num_frames as f32;
+            let frame_camera = Camera {
+                orbit_angle,
+                ..turntable_camera
+            };
+            queue.write_buffer(&camera_buffer, 0, bytemuck::bytes_of(&frame_camera));
+
+            // (13.2) Render the frame, and copy its color output.
+            // The auxiliary targets are cleared by draw_pipeline(), so they don't accumulate
+            // the primitives of the previous frames.
+            let mut encoder = device.create_command_encoder(&Default::default());
+            encode_frame(&mut encoder);
+            copy_texture_to_buffer(&mut encoder, color_texture, &output_buffer);
+
+            // (13.3) Submit the commands, and save the frame
+            queue.submit(Some(encoder.finish()));
+            {
+                let img_data = retrieve_texture_buffer_data(&device, &output_buffer).await;
+                let mut pixels = Vec::from(&img_data as &[u8]);
+                match &mut gif_encoder {
+                    Some(gif_encoder) => {
+                        // GIF is a paletted format, so the colors are quantized
+                        let mut frame = gif::Frame::from_rgba_speed(
+                            width as u16,
+                            height as u16,
+                            &mut pixels,
+                            10,
+                        );
+                        frame.delay = 4; // in hundredths of a second
+                        gif_encoder.write_frame(&frame).unwrap();
+                    }
+                    None => {
+                        let img = image::RgbaImage::from_raw(width, height, pixels).unwrap();
+                        img.save(format!("turntable_{frame_index:03}.png")).unwrap();
+                    }
+                }
+            }
+            output_buffer.unmap();
+        }
+    }
 
     println!("Terminating the program ...")
 }
 
 /// (1) Initializing WebGPU
-async fn init_wgpu_device() -> Result<(wgpu::Device, wgpu::Queue), wgpu::RequestDeviceError> {
+/// Also returns the adapter information, since the strips are drawn differently on the GL backend.
+async fn init_wgpu_device(
+) -> Result<(wgpu::Device, wgpu::Queue, wgpu::AdapterInfo), wgpu::RequestDeviceError> {
     // Start an "Instance", which is the context for all things wgpu.
     let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
         backends: wgpu::Backends::all(),
@@ -169,12 +549,28 @@ async fn init_wgpu_device() -> Result<(wgpu::Device, wgpu::Queue), wgpu::Request
 
     // Request a connection to a physical device,
     // and also access to the queue for its command buffers.
-    // It is possible, if necessary, to add a description of required features.
-    adapter.request_device(&Default::default(), None).await
+    // We require the line polygon mode (for the wireframe), the unclipped depth
+    // and the conservative rasterization (for the rasterizer configuration),
+    // but only if the adapter supports them.
+    let optional_features = wgpu::Features::POLYGON_MODE_LINE
+        | wgpu::Features::DEPTH_CLIP_CONTROL
+        | wgpu::Features::CONSERVATIVE_RASTERIZATION;
+    let device_desc = wgpu::DeviceDescriptor {
+        label: None,
+        required_features: adapter.features() & optional_features,
+        required_limits: Default::default(),
+    };
+    let (device, queue) = adapter.request_device(&device_desc, None).await?;
+    Ok((device, queue, adapter.get_info()))
 }
 
-/// (2) Initialize the output texture
-fn init_output_texture(device: &wgpu::Device, width: u32, height: u32) -> wgpu::Texture {
+/// (2) Initialize an output texture
+fn init_output_texture(
+    device: &wgpu::Device,
+    width: u32,
+    height: u32,
+    format: wgpu::TextureFormat,
+) -> wgpu::Texture {
     let texture_desc = wgpu::TextureDescriptor {
         label: Some("output_texture"),
         // The texture size. (layers is set to 1)
@@ -186,11 +582,14 @@ fn init_output_texture(device: &wgpu::Device, width: u32, height: u32) -> wgpu::
         dimension: wgpu::TextureDimension::D2,
         mip_level_count: 1, // the number of mip levels the texture will contain
         sample_count: 1,    // sample_count > 1 would indicate a multisampled texture
-        // Use RGBA format for the output
-        format: wgpu::TextureFormat::Rgba8Unorm,
+        // RGBA for the color output, R32Uint for triangle IDs, Rg32Float for barycentrics
+        format,
         // RENDER_ATTACHMENT -> so that the GPU can render to the texture
         // COPY_SRC -> so that we can pull data out of the texture
-        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        // TEXTURE_BINDING -> so that the SSAO compute pass can read the color
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+            | wgpu::TextureUsages::COPY_SRC
+            | wgpu::TextureUsages::TEXTURE_BINDING,
         // Specify the allowed formats when calling "texture.create_view()"
         view_formats: &[],
     };
@@ -198,7 +597,7 @@ fn init_output_texture(device: &wgpu::Device, width: u32, height: u32) -> wgpu::
 }
 
 /// (3 & 5) Create a buffer descriptor of the correct size for the texture
-fn create_texture_buffer_descriptor(texture: &wgpu::Texture) -> wgpu::BufferDescriptor {
+fn create_texture_buffer_descriptor(texture: &wgpu::Texture) -> wgpu::BufferDescriptor<'_> {
     let texel_size = texture.format().block_copy_size(None).unwrap();
     wgpu::BufferDescriptor {
         size: (texel_size * texture.width() * texture.height()).into(),
@@ -226,14 +625,111 @@ fn init_depth_texture(device: &wgpu::Device, width: u32, height: u32) -> wgpu::T
         // Use RGBA format for the output
         format: wgpu::TextureFormat::Depth32Float,
         // RENDER_ATTACHMENT -> so that the GPU can render to the texture
-        // COPY_SRC -> so that we can pull data out of the texture
-        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        // TEXTURE_BINDING -> so that the SSAO and readback compute passes can read the depth
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
         // Specify the allowed formats when calling "texture.create_view()"
         view_formats: &[],
     };
     device.create_texture(&texture_desc)
 }
 
+/// (4) Compute pass copying the depth texture into an R32Float storage texture,
+/// since depth textures cannot be copied into buffers on every device (e.g. on OpenGL)
+struct DepthReadback {
+    /// Copy of the depth, which can be copied into a buffer
+    depth_output: wgpu::Texture,
+    pipeline: wgpu::ComputePipeline,
+    bind_group: wgpu::BindGroup,
+}
+
+impl DepthReadback {
+    fn new(
+        device: &wgpu::Device,
+        depth_texture_view: &wgpu::TextureView,
+        size: wgpu::Extent3d,
+    ) -> Self {
+        let depth_output =
+            init_storage_texture(device, "depth_output", size, wgpu::TextureFormat::R32Float);
+        let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("depth_readback_shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("depth_readback.wgsl").into()),
+        });
+
+        // The automatic layout would expect a filterable float texture for the depth,
+        // which depth textures are not, so we define the layout ourselves.
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("depth_readback_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::StorageTexture {
+                        access: wgpu::StorageTextureAccess::WriteOnly,
+                        format: wgpu::TextureFormat::R32Float,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("depth_readback_pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("depth_readback_pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader_module,
+            entry_point: "read_depth",
+        });
+        let output_view = depth_output.create_view(&Default::default());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("depth_readback_bind_group"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(depth_texture_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&output_view),
+                },
+            ],
+        });
+
+        Self {
+            depth_output,
+            pipeline,
+            bind_group,
+        }
+    }
+
+    /// (10.3) Add the compute pass copying the depth into the storage texture
+    fn compute(&self, encoder: &mut wgpu::CommandEncoder) {
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("depth_readback_pass"),
+            timestamp_writes: None,
+        });
+        // One thread per pixel, with workgroups of 8x8 threads
+        let size = self.depth_output.size();
+        compute_pass.set_pipeline(&self.pipeline);
+        compute_pass.set_bind_group(0, &self.bind_group, &[]);
+        compute_pass.dispatch_workgroups(size.width.div_ceil(8), size.height.div_ceil(8), 1);
+    }
+}
+
 /// Define the layout of Vertex buffers
 fn vtx_buffer_layout() -> wgpu::VertexBufferLayout<'static> {
     wgpu::VertexBufferLayout {
@@ -253,50 +749,361 @@ fn vtx_buffer_layout() -> wgpu::VertexBufferLayout<'static> {
 
 /// Perspective camera
 /// Bytemuck is used to enable easy casting to a &[u8].
+/// Booleans are stored as u32 (0 or 1) since bool is not a valid uniform type,
+/// and the struct is padded to a multiple of 16 bytes, as required for uniform buffers.
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 struct Camera {
     focal_length: f32,
     aspect_ratio: f32,
     near_plane: f32,
-    far_plane: f32,
+    far_plane: f32, // f32::INFINITY for an infinite far plane (requires reverse Z)
+    reverse_z: u32,
+    infinite_far: u32,
+    orbit_angle: f32, // in radians, around the bunny, for the turntable animation
+    _padding: [u32; 1],
 }
 
 impl Camera {
+    fn new(
+        focal_length: f32,
+        aspect_ratio: f32,
+        near_plane: f32,
+        far_plane: f32,
+        reverse_z: bool,
+    ) -> Self {
+        Self {
+            focal_length,
+            aspect_ratio,
+            near_plane,
+            far_plane,
+            reverse_z: reverse_z as u32,
+            infinite_far: far_plane.is_infinite() as u32,
+            orbit_angle: 0.0,
+            _padding: [0; 1],
+        }
+    }
+
+    /// Depth comparison keeping the closest fragment.
+    /// With reverse Z, closer fragments have higher depth values.
+    fn depth_compare(&self) -> wgpu::CompareFunction {
+        if self.reverse_z != 0 {
+            wgpu::CompareFunction::Greater
+        } else {
+            wgpu::CompareFunction::Less
+        }
+    }
+
+    /// Depth value the depth texture is cleared to, i.e. the depth of the far plane.
+    fn depth_clear_value(&self) -> f32 {
+        if self.reverse_z != 0 {
+            0.0
+        } else {
+            1.0
+        }
+    }
+
+    /// Convert a depth value read back from the depth texture
+    /// into the depth that the conventional projection (not reversed) would have produced.
+    ///
+    /// With reverse Z, depth = near * (far - z) / ((far - near) * z),
+    /// while conventionally, depth = far * (z - near) / ((far - near) * z).
+    /// Both simply sum up to 1.0, and this still holds at the limit when far goes to infinity
+    /// (reverse Z depth = near / z).
+    fn standard_depth(&self, depth: f32) -> f32 {
+        if self.reverse_z != 0 {
+            1.0 - depth
+        } else {
+            depth
+        }
+    }
+
     fn create_uniform_buffer(&self, device: &wgpu::Device) -> wgpu::Buffer {
         device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Camera Buffer"),
             contents: bytemuck::bytes_of(self),
-            usage: wgpu::BufferUsages::UNIFORM, // | wgpu::BufferUsages::COPY_DST,
+            // COPY_DST -> so that the turntable animation can update the camera
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         })
     }
+    /// Bind group of the render pipelines, with the camera and the primitive parameters
     fn create_bind_group(
         device: &wgpu::Device,
         buffer: &wgpu::Buffer,
+        primitive_buffer: &wgpu::Buffer,
         layout: &wgpu::BindGroupLayout,
     ) -> wgpu::BindGroup {
         device.create_bind_group(&wgpu::BindGroupDescriptor {
             layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-                    buffer,
-                    offset: 0,
-                    size: None, // automatic size from offset to buffer end
-                }),
-            }],
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                        buffer,
+                        offset: 0,
+                        size: None, // automatic size from offset to buffer end
+                    }),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: primitive_buffer.as_entire_binding(),
+                },
+            ],
             label: Some("camera_bind_group"),
         })
     }
 }
 
+/// (8.1) How the vertex shader derives the primitive ID from the vertex index.
+///
+/// With a list topology, primitive i is made of the vertex_stride vertices starting at
+/// vertex i * vertex_stride: 3 for a triangle list, 2 for a line list, 1 for a point list.
+/// So all the vertices of a primitive give it the same ID, whichever one the rasterizer
+/// takes the flat-interpolated value from.
+///
+/// With a strip topology, consecutive primitives share vertices, so a vertex can't tell
+/// its primitive: vertex_stride is 0, and the shader leaves the IDs and barycentrics to 0.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct PrimitiveParams {
+    vertex_stride: u32,
+    _padding: [u32; 3],
+}
+
+impl PrimitiveParams {
+    fn new(topology: wgpu::PrimitiveTopology) -> Self {
+        let vertex_stride = match topology {
+            wgpu::PrimitiveTopology::TriangleList => 3,
+            wgpu::PrimitiveTopology::LineList => 2,
+            wgpu::PrimitiveTopology::PointList => 1,
+            wgpu::PrimitiveTopology::TriangleStrip | wgpu::PrimitiveTopology::LineStrip => 0,
+        };
+        Self {
+            vertex_stride,
+            _padding: [0; 3],
+        }
+    }
+
+    fn create_uniform_buffer(&self, device: &wgpu::Device) -> wgpu::Buffer {
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Primitive Buffer"),
+            contents: bytemuck::bytes_of(self),
+            usage: wgpu::BufferUsages::UNIFORM,
+        })
+    }
+}
+
+/// (5.3) Join the triangles of an indexed mesh into strips, greedily: each strip starts with
+/// an unused triangle, and grows with the unused triangle sharing its last edge, if any.
+/// Triangle k of a strip is made of its vertices k, k + 1 and k + 2, the GPU swapping the first two
+/// when k is odd to keep the same winding. So the next triangle must contain the last edge
+/// in the same direction (or the opposite one after an odd triangle).
+fn triangle_strips(indices: &[u32]) -> Vec<Vec<u32>> {
+    // Each directed edge of the (consistently wound) triangles, and the triangle containing it
+    // with its third vertex
+    let mut edges = std::collections::HashMap::new();
+    for (triangle, corners) in indices.chunks_exact(3).enumerate() {
+        for k in 0..3 {
+            edges.insert(
+                (corners[k], corners[(k + 1) % 3]),
+                (triangle, corners[(k + 2) % 3]),
+            );
+        }
+    }
+
+    let mut used = vec![false; indices.len() / 3];
+    let mut strips = Vec::new();
+    for (triangle, corners) in indices.chunks_exact(3).enumerate() {
+        if used[triangle] {
+            continue;
+        }
+        used[triangle] = true;
+        let mut strip = corners.to_vec();
+        loop {
+            let (a, b) = (strip[strip.len() - 2], strip[strip.len() - 1]);
+            let edge = if strip.len() % 2 == 1 { (b, a) } else { (a, b) };
+            match edges.get(&edge) {
+                Some(&(next, c)) if !used[next] => {
+                    used[next] = true;
+                    strip.push(c);
+                }
+                _ => break,
+            }
+        }
+        strips.push(strip);
+    }
+    strips
+}
+
+/// (5.3) Bytes of the index buffer of the strips, separated by the primitive restart index:
+/// the largest value of the index format
+fn strip_index_data(
+    strips: &[Vec<u32>],
+    num_vertices: usize,
+    index_format: wgpu::IndexFormat,
+) -> Vec<u8> {
+    let indices = strips.iter().enumerate().flat_map(|(i, strip)| {
+        let restart = (i > 0).then_some(u32::MAX);
+        restart.into_iter().chain(strip.iter().copied())
+    });
+    match index_format {
+        wgpu::IndexFormat::Uint16 => {
+            assert!(
+                num_vertices < u16::MAX as usize,
+                "Too many vertices ({num_vertices}) for the uint16 strip index format"
+            );
+            let indices: Vec<u16> = indices.map(|index| index as u16).collect();
+            bytemuck::cast_slice(&indices).to_vec()
+        }
+        wgpu::IndexFormat::Uint32 => {
+            let indices: Vec<u32> = indices.collect();
+            bytemuck::cast_slice(&indices).to_vec()
+        }
+    }
+}
+
+/// (7) Rasterizer configuration, shared by the mesh and wireframe pipelines
+#[derive(Clone, Copy, Debug)]
+struct RasterizerConfig {
+    /// How vertices are assembled into primitives (triangles, lines or points).
+    /// The triangle ID output is then the ID of the primitive (see `PrimitiveParams`).
+    topology: wgpu::PrimitiveTopology,
+    /// Only for strip topologies, which are drawn with an index buffer of this format:
+    /// the largest index value (0xFFFF or 0xFFFFFFFF) then restarts the strip.
+    /// Uint32 by default, Uint16 only works for meshes of less than 0xFFFF vertices.
+    strip_index_format: Option<wgpu::IndexFormat>,
+    /// Winding order (counter clockwise or clockwise) of front facing triangles on screen
+    front_face: wgpu::FrontFace,
+    /// Which faces are discarded (None to keep both)
+    cull_mode: Option<wgpu::Face>,
+    /// Do not clip primitives beyond the near/far planes, clamp their depth instead.
+    /// Requires Features::DEPTH_CLIP_CONTROL
+    unclipped_depth: bool,
+    /// Rasterize every pixel touched by a primitive, instead of those with a covered center.
+    /// Requires Features::CONSERVATIVE_RASTERIZATION
+    conservative: bool,
+}
+
+impl Default for RasterizerConfig {
+    fn default() -> Self {
+        Self {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            // The OBJ is right-handed while our camera (X right, Y up, Z forward) is left-handed.
+            // So the counter clockwise triangles of the bunny appear clockwise on screen,
+            // and we cull the "front" faces to actually remove the back of the bunny.
+            cull_mode: Some(wgpu::Face::Front),
+            unclipped_depth: false,
+            conservative: false,
+        }
+    }
+}
+
+impl RasterizerConfig {
+    /// Override the default configuration with command line arguments:
+    /// --topology triangle-list|triangle-strip|line-list|line-strip|point-list
+    /// --strip-index-format uint16|uint32
+    /// --front-face ccw|cw
+    /// --cull-mode front|back|none
+    /// --unclipped-depth
+    /// --conservative
+    fn from_args(args: &[String]) -> Self {
+        let arg_value = |name: &str| {
+            let position = args.iter().position(|arg| arg == name)?;
+            Some(
+                args.get(position + 1)
+                    .expect("Missing argument value")
+                    .as_str(),
+            )
+        };
+        let default = Self::default();
+        let topology = arg_value("--topology").map_or(default.topology, |value| match value {
+            "triangle-list" => wgpu::PrimitiveTopology::TriangleList,
+            "triangle-strip" => wgpu::PrimitiveTopology::TriangleStrip,
+            "line-list" => wgpu::PrimitiveTopology::LineList,
+            "line-strip" => wgpu::PrimitiveTopology::LineStrip,
+            "point-list" => wgpu::PrimitiveTopology::PointList,
+            _ => panic!("Unknown topology: {value}"),
+        });
+        Self {
+            topology,
+            strip_index_format: arg_value("--strip-index-format")
+                .map(|value| match value {
+                    "uint16" => wgpu::IndexFormat::Uint16,
+                    "uint32" => wgpu::IndexFormat::Uint32,
+                    _ => panic!("Unknown strip index format: {value}"),
+                })
+                .or(topology.is_strip().then_some(wgpu::IndexFormat::Uint32)),
+            front_face: arg_value("--front-face").map_or(default.front_face, |value| match value {
+                "ccw" => wgpu::FrontFace::Ccw,
+                "cw" => wgpu::FrontFace::Cw,
+                _ => panic!("Unknown front face: {value}"),
+            }),
+            cull_mode: arg_value("--cull-mode").map_or(default.cull_mode, |value| match value {
+                "front" => Some(wgpu::Face::Front),
+                "back" => Some(wgpu::Face::Back),
+                "none" => None,
+                _ => panic!("Unknown cull mode: {value}"),
+            }),
+            unclipped_depth: args.iter().any(|arg| arg == "--unclipped-depth"),
+            conservative: args.iter().any(|arg| arg == "--conservative"),
+        }
+    }
+
+    /// Check that the configuration is valid and supported by the device features,
+    /// to fail with a clear message instead of a pipeline creation error.
+    fn validate(&self, features: wgpu::Features) {
+        assert!(
+            self.strip_index_format.is_some() == self.topology.is_strip(),
+            "A strip index format is required with a strip topology, and only valid with it"
+        );
+        assert!(
+            !self.unclipped_depth || features.contains(wgpu::Features::DEPTH_CLIP_CONTROL),
+            "Unclipped depth requires the DEPTH_CLIP_CONTROL feature, not supported by this device"
+        );
+        assert!(
+            !self.conservative || features.contains(wgpu::Features::CONSERVATIVE_RASTERIZATION),
+            "Conservative rasterization requires the CONSERVATIVE_RASTERIZATION feature, not supported by this device"
+        );
+    }
+
+    fn primitive_state(&self, polygon_mode: wgpu::PolygonMode) -> wgpu::PrimitiveState {
+        wgpu::PrimitiveState {
+            topology: self.topology,
+            strip_index_format: self.strip_index_format,
+            front_face: self.front_face,
+            cull_mode: self.cull_mode,
+            polygon_mode,
+            unclipped_depth: self.unclipped_depth,
+            // Conservative rasterization is only valid for filled polygons
+            conservative: self.conservative && polygon_mode == wgpu::PolygonMode::Fill,
+        }
+    }
+}
+
 /// (7) Define our simple render pipeline
 fn build_simple_pipeline(
     device: &wgpu::Device,
     shader_module: &wgpu::ShaderModule,
-    texture_format: wgpu::TextureFormat,
+    texture_formats: &[wgpu::TextureFormat],
     vertex_buffer_layout: wgpu::VertexBufferLayout,
+    depth_compare: wgpu::CompareFunction,
+    rasterizer_config: &RasterizerConfig,
 ) -> wgpu::RenderPipeline {
+    // One target per fragment shader output location.
+    // Integer and 32 bits float formats do not support blending,
+    // but replacing the previous value is exactly what "no blending" does anyway.
+    let targets: Vec<_> = texture_formats
+        .iter()
+        .map(|&format| {
+            Some(wgpu::ColorTargetState {
+                format,
+                blend: None,
+                write_mask: wgpu::ColorWrites::ALL,
+            })
+        })
+        .collect();
     device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
         label: Some("Render Pipeline"),
         layout: None, // "auto"
@@ -308,33 +1115,17 @@ fn build_simple_pipeline(
         fragment: Some(wgpu::FragmentState {
             module: shader_module,
             entry_point: "fragment_main",
-            targets: &[Some(wgpu::ColorTargetState {
-                format: texture_format,
-                blend: Some(wgpu::BlendState {
-                    alpha: wgpu::BlendComponent::REPLACE,
-                    color: wgpu::BlendComponent::REPLACE,
-                }),
-                write_mask: wgpu::ColorWrites::ALL,
-            })],
+            targets: &targets,
         }),
-        primitive: wgpu::PrimitiveState {
-            topology: wgpu::PrimitiveTopology::TriangleList,
-            strip_index_format: None,
-            front_face: wgpu::FrontFace::Ccw,
-            cull_mode: Some(wgpu::Face::Front),
-            // Setting this to anything other than Fill requires Features::NON_FILL_POLYGON_MODE
-            polygon_mode: wgpu::PolygonMode::Fill,
-            // Requires Features::DEPTH_CLIP_CONTROL
-            unclipped_depth: false,
-            // Requires Features::CONSERVATIVE_RASTERIZATION
-            conservative: false,
-        },
+        // Setting the polygon mode to Line requires Features::POLYGON_MODE_LINE (see the wireframe)
+        primitive: rasterizer_config.primitive_state(wgpu::PolygonMode::Fill),
         depth_stencil: Some(wgpu::DepthStencilState {
             format: wgpu::TextureFormat::Depth32Float,
             // Enable WebGPU to write the depth (position.z) to the provided texture
             depth_write_enabled: true,
-            // Keep the depth value closest to us (lower values)
-            depth_compare: wgpu::CompareFunction::Less,
+            // Keep the depth value closest to us
+            // (lower values, or higher values with reverse Z)
+            depth_compare,
             // Not using stencil stuff
             stencil: wgpu::StencilState::default(),
             bias: wgpu::DepthBiasState::default(),
@@ -350,40 +1141,454 @@ fn build_simple_pipeline(
     })
 }
 
-/// (9.1) Draw our pipeline (add render pass to the command encoder).
+/// (7) Define the render pipeline of the wireframe overlay
+fn build_wireframe_pipeline(
+    device: &wgpu::Device,
+    shader_module: &wgpu::ShaderModule,
+    texture_formats: &[wgpu::TextureFormat],
+    vertex_buffer_layout: wgpu::VertexBufferLayout,
+    mesh_depth_compare: wgpu::CompareFunction,
+    rasterizer_config: &RasterizerConfig,
+    polygon_mode: wgpu::PolygonMode,
+) -> wgpu::RenderPipeline {
+    // Filled triangles need the fallback fragment shader to only keep their edges
+    let fragment_entry_point = match polygon_mode {
+        wgpu::PolygonMode::Fill => "fragment_wireframe_fallback",
+        _ => "fragment_wireframe",
+    };
+    // Only write the color, and leave the triangle IDs and barycentrics of the mesh untouched
+    let targets: Vec<_> = texture_formats
+        .iter()
+        .enumerate()
+        .map(|(i, &format)| {
+            Some(wgpu::ColorTargetState {
+                format,
+                blend: None,
+                write_mask: if i == 0 {
+                    wgpu::ColorWrites::ALL
+                } else {
+                    wgpu::ColorWrites::empty()
+                },
+            })
+        })
+        .collect();
+    // Keep the edges at the same depth as the mesh triangles, and skip those hidden behind.
+    // Also slightly move the edges towards the camera,
+    // since lines are not rasterized exactly like the triangles they belong to.
+    let (depth_compare, slope_scale) = match mesh_depth_compare {
+        wgpu::CompareFunction::Greater => (wgpu::CompareFunction::GreaterEqual, 1.0),
+        _ => (wgpu::CompareFunction::LessEqual, -1.0),
+    };
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Wireframe Pipeline"),
+        layout: None, // "auto"
+        vertex: wgpu::VertexState {
+            module: shader_module,
+            entry_point: "vertex_main",
+            buffers: &[vertex_buffer_layout],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader_module,
+            entry_point: fragment_entry_point,
+            targets: &targets,
+        }),
+        // Line requires Features::POLYGON_MODE_LINE
+        primitive: rasterizer_config.primitive_state(polygon_mode),
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: wgpu::TextureFormat::Depth32Float,
+            // The mesh depth is already there, no need to write it again
+            depth_write_enabled: false,
+            depth_compare,
+            // Not using stencil stuff
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState {
+                constant: 0,
+                slope_scale,
+                clamp: 0.0,
+            },
+        }),
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+    })
+}
+
+/// SSAO parameters, in a uniform buffer.
+/// Bytemuck is used to enable easy casting to a &[u8].
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct SsaoParams {
+    /// Radius of the sampled hemisphere, in view-space units.
+    /// The whole bunny is about 0.15 wide, and 0.04 deep.
+    radius: f32,
+    /// Minimum depth difference for a sample to be occluded, avoiding self-occlusion
+    bias: f32,
+    /// Exponent applied to the ambient occlusion, larger values for a darker occlusion
+    power: f32,
+    /// The blur averages the (2 * blur_radius + 1)^2 pixels around each pixel.
+    /// The kernel rotation repeats every 4x4 pixels, so a radius of 2 removes most of the noise.
+    blur_radius: u32,
+}
+
+impl SsaoParams {
+    /// Override the default parameters with command line arguments.
+    /// Also returns the number of samples of the kernel (--ssao-samples, 16 by default).
+    /// --ssao-radius 0.01
+    /// --ssao-bias 0.0002
+    /// --ssao-power 2
+    /// --ssao-blur 2
+    fn from_args(args: &[String]) -> (Self, u32) {
+        let arg_value = |name: &str| {
+            let position = args.iter().position(|arg| arg == name)?;
+            Some(
+                args.get(position + 1)
+                    .expect("Missing argument value")
+                    .as_str(),
+            )
+        };
+        let params = Self {
+            radius: arg_value("--ssao-radius").map_or(0.01, |value| value.parse().unwrap()),
+            bias: arg_value("--ssao-bias").map_or(0.0002, |value| value.parse().unwrap()),
+            power: arg_value("--ssao-power").map_or(2.0, |value| value.parse().unwrap()),
+            blur_radius: arg_value("--ssao-blur").map_or(2, |value| value.parse().unwrap()),
+        };
+        let num_samples = arg_value("--ssao-samples").map_or(16, |value| value.parse().unwrap());
+        assert!(num_samples > 0, "The SSAO kernel needs at least one sample");
+        (params, num_samples)
+    }
+}
+
+/// (9.1) Generate the SSAO kernel: sample points in the unit hemisphere around the Z axis.
+/// The directions follow a spiral over the hemisphere (Fibonacci sphere),
+/// and the points get further from the center along the spiral,
+/// with more samples close to the center, where occlusion matters most.
+/// Points are vec4 since arrays of vec3 have a 16 bytes stride in WGSL.
+fn ssao_kernel(num_samples: u32) -> Vec<[f32; 4]> {
+    let golden_angle = std::f32::consts::PI * (3.0 - 5f32.sqrt());
+    (0..num_samples)
+        .map(|i| {
+            let t = (i as f32 + 0.5) / num_samples as f32;
+            // Uniformly distributed directions over the hemisphere
+            let z = 1.0 - t;
+            let r = (1.0 - z * z).sqrt();
+            let angle = i as f32 * golden_angle;
+            let scale = 0.1 + 0.9 * t * t;
+            [
+                scale * r * angle.cos(),
+                scale * r * angle.sin(),
+                scale * z,
+                0.0,
+            ]
+        })
+        .collect()
+}
+
+/// (4 & 9.2) Initialize a texture written by a compute pass (SSAO or depth readback)
+fn init_storage_texture(
+    device: &wgpu::Device,
+    label: &str,
+    size: wgpu::Extent3d,
+    format: wgpu::TextureFormat,
+) -> wgpu::Texture {
+    let texture_desc = wgpu::TextureDescriptor {
+        label: Some(label),
+        size,
+        dimension: wgpu::TextureDimension::D2,
+        mip_level_count: 1, // the number of mip levels the texture will contain
+        sample_count: 1,    // sample_count > 1 would indicate a multisampled texture
+        // One of the few formats usable as a storage texture, on all devices
+        // (Rgba8Unorm for SSAO, R32Float for the depth)
+        format,
+        // STORAGE_BINDING -> so that a compute shader can write to the texture
+        // TEXTURE_BINDING -> so that the next compute pass can read the texture
+        // COPY_SRC -> so that we can pull data out of the texture
+        usage: wgpu::TextureUsages::STORAGE_BINDING
+            | wgpu::TextureUsages::TEXTURE_BINDING
+            | wgpu::TextureUsages::COPY_SRC,
+        // Specify the allowed formats when calling "texture.create_view()"
+        view_formats: &[],
+    };
+    device.create_texture(&texture_desc)
+}
+
+/// (9.3) Explicitly create the bind group layout of the "ssao" compute pass.
+///
+/// The depth texture is read with textureLoad(), but the OpenGL backend does not support it
+/// on depth textures (texture_depth_2d). Instead, it is declared as a regular float texture,
+/// which accepts depth textures only if it is not filterable.
+/// An implicit ("auto") layout would consider it filterable, hence this explicit layout.
+fn ssao_depth_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    let buffer_entry = |binding, ty| wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    };
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("ssao_bind_group_layout"),
+        entries: &[
+            // Camera and SSAO parameters
+            buffer_entry(0, wgpu::BufferBindingType::Uniform),
+            buffer_entry(1, wgpu::BufferBindingType::Uniform),
+            // Kernel
+            buffer_entry(2, wgpu::BufferBindingType::Storage { read_only: true }),
+            // Depth texture
+            wgpu::BindGroupLayoutEntry {
+                binding: 3,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            // Raw ambient occlusion output
+            wgpu::BindGroupLayoutEntry {
+                binding: 4,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::StorageTexture {
+                    access: wgpu::StorageTextureAccess::WriteOnly,
+                    format: wgpu::TextureFormat::Rgba8Unorm,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                },
+                count: None,
+            },
+        ],
+    })
+}
+
+/// (9) The SSAO post-process, made of 3 compute passes:
+/// the ambient occlusion itself, a blur, and the modulation of the color output.
+struct Ssao {
+    /// Ambient occlusion, noisy due to the per-pixel rotation of the kernel
+    raw_ao_texture: wgpu::Texture,
+    /// Blurred ambient occlusion
+    ao_texture: wgpu::Texture,
+    /// Color output multiplied by the ambient occlusion
+    color_texture: wgpu::Texture,
+    /// One pipeline per compute pass, with its bind group
+    passes: Vec<(wgpu::ComputePipeline, wgpu::BindGroup)>,
+}
+
+impl Ssao {
+    fn new(
+        device: &wgpu::Device,
+        camera_buffer: &wgpu::Buffer,
+        params: &SsaoParams,
+        num_samples: u32,
+        depth_texture_view: &wgpu::TextureView,
+        color_texture_view: &wgpu::TextureView,
+        size: wgpu::Extent3d,
+    ) -> Self {
+        // (9.1) Put the parameters and the kernel into buffers
+        let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("SSAO Params Buffer"),
+            contents: bytemuck::bytes_of(params),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let kernel_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("SSAO Kernel Buffer"),
+            contents: bytemuck::cast_slice(&ssao_kernel(num_samples)),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+
+        // (9.2) Initialize the storage textures, with the same size as the depth texture
+        let raw_ao_texture = init_storage_texture(
+            device,
+            "raw_ao_texture",
+            size,
+            wgpu::TextureFormat::Rgba8Unorm,
+        );
+        let ao_texture =
+            init_storage_texture(device, "ao_texture", size, wgpu::TextureFormat::Rgba8Unorm);
+        let color_texture = init_storage_texture(
+            device,
+            "ssao_color_texture",
+            size,
+            wgpu::TextureFormat::Rgba8Unorm,
+        );
+        let raw_ao_view = raw_ao_texture.create_view(&Default::default());
+        let ao_view = ao_texture.create_view(&Default::default());
+        let color_view = color_texture.create_view(&Default::default());
+
+        // (9.3) Define the compute pipelines, with their bind groups.
+        // Each entry point uses its own bindings of the shader, see ssao.wgsl.
+        let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("ssao_shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("ssao.wgsl").into()),
+        });
+        let texture = wgpu::BindingResource::TextureView;
+        let depth_layout = ssao_depth_bind_group_layout(device);
+        let passes_bindings = [
+            (
+                "ssao",
+                Some(&depth_layout),
+                vec![
+                    (0, camera_buffer.as_entire_binding()),
+                    (1, params_buffer.as_entire_binding()),
+                    (2, kernel_buffer.as_entire_binding()),
+                    (3, texture(depth_texture_view)),
+                    (4, texture(&raw_ao_view)),
+                ],
+            ),
+            (
+                "blur",
+                None,
+                vec![
+                    (1, params_buffer.as_entire_binding()),
+                    (5, texture(&raw_ao_view)),
+                    (6, texture(&ao_view)),
+                ],
+            ),
+            (
+                "composite",
+                None,
+                vec![
+                    (7, texture(&ao_view)),
+                    (8, texture(color_texture_view)),
+                    (9, texture(&color_view)),
+                ],
+            ),
+        ];
+        let passes = passes_bindings
+            .into_iter()
+            .map(|(entry_point, bind_group_layout, bindings)| {
+                // Explicit layout if provided, "auto" otherwise
+                let pipeline_layout = bind_group_layout.map(|bind_group_layout| {
+                    device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                        label: Some(entry_point),
+                        bind_group_layouts: &[bind_group_layout],
+                        push_constant_ranges: &[],
+                    })
+                });
+                let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                    label: Some(entry_point),
+                    layout: pipeline_layout.as_ref(),
+                    module: &shader_module,
+                    entry_point,
+                });
+                let entries: Vec<_> = bindings
+                    .into_iter()
+                    .map(|(binding, resource)| wgpu::BindGroupEntry { binding, resource })
+                    .collect();
+                let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    layout: &pipeline.get_bind_group_layout(0),
+                    entries: &entries,
+                    label: Some(entry_point),
+                });
+                (pipeline, bind_group)
+            })
+            .collect();
+
+        Self {
+            raw_ao_texture,
+            ao_texture,
+            color_texture,
+            passes,
+        }
+    }
+
+    /// (10.2) Add the compute passes to the command encoder, one thread per pixel.
+    /// Each pass only starts once the textures written by the previous one are complete.
+    fn compute(&self, encoder: &mut wgpu::CommandEncoder) {
+        // The workgroup size (8x8) is defined in the shader
+        let size = self.ao_texture.size();
+        let workgroups_x = size.width.div_ceil(8);
+        let workgroups_y = size.height.div_ceil(8);
+        for (pipeline, bind_group) in &self.passes {
+            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("SSAO Pass"),
+                timestamp_writes: None,
+            });
+            compute_pass.set_pipeline(pipeline);
+            compute_pass.set_bind_group(0, bind_group, &[]);
+            compute_pass.dispatch_workgroups(workgroups_x, workgroups_y, 1);
+        }
+    }
+}
+
+/// (10.1) Draw our pipeline (add render pass to the command encoder).
+#[allow(clippy::too_many_arguments)]
 fn draw_pipeline(
     encoder: &mut wgpu::CommandEncoder,
     pipeline: &wgpu::RenderPipeline,
-    texture_view: &wgpu::TextureView,
+    texture_views: &[&wgpu::TextureView],
     depth_texture_view: &wgpu::TextureView,
+    depth_clear_value: f32,
     camera_bind_group: &wgpu::BindGroup,
     vertex_buffer: &wgpu::Buffer,
-    index_buffer: &wgpu::Buffer,
-    num_indices: u32,
+    index_buffer: Option<(&wgpu::Buffer, wgpu::IndexFormat)>,
+    draw_ranges: &[std::ops::Range<u32>],
+    wireframe: Option<(&wgpu::RenderPipeline, &wgpu::BindGroup)>,
 ) {
-    // Setup the pass that will render into our texture
-    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-        label: Some("Render Pass"),
-        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-            view: &texture_view,  // the output texture
-            resolve_target: None, // only useful for multisampling
-            ops: wgpu::Operations {
-                // "load" specifies how data is read.
-                // "Clear" is the lightest way to initialize the texture.
-                load: wgpu::LoadOp::Clear(wgpu::Color {
+    // The auxiliary outputs (all but the first one) are cleared to zeros
+    // (triangle ID 0 is the background), so that they never keep the primitives
+    // of a previous render pass.
+    // The GL backend currently fails to clear float targets other than the first one
+    // of a render pass, so each one is cleared by its own render pass, as its only target.
+    for &view in &texture_views[1..] {
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Clear Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            ..Default::default()
+        });
+    }
+
+    // One color attachment per texture output.
+    // The first one (color) is cleared to a blue background,
+    // the others were just cleared and are loaded.
+    let color_attachments: Vec<_> = texture_views
+        .iter()
+        .enumerate()
+        .map(|(i, &view)| {
+            let load = if i == 0 {
+                wgpu::LoadOp::Clear(wgpu::Color {
                     r: 0.1,
                     g: 0.2,
                     b: 0.3,
                     a: 1.0,
-                }),
-                // whether data is written to or not. Store | Discard
-                store: wgpu::StoreOp::Store,
-            },
-        })],
+                })
+            } else {
+                wgpu::LoadOp::Load
+            };
+            Some(wgpu::RenderPassColorAttachment {
+                view,                 // the output texture
+                resolve_target: None, // only useful for multisampling
+                ops: wgpu::Operations {
+                    // "load" specifies how data is read.
+                    // "Clear" is the lightest way to initialize the texture.
+                    load,
+                    // whether data is written to or not. Store | Discard
+                    store: wgpu::StoreOp::Store,
+                },
+            })
+        })
+        .collect();
+
+    // Setup the pass that will render into our textures
+    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("Render Pass"),
+        color_attachments: &color_attachments,
         depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
             view: depth_texture_view,
             depth_ops: Some(wgpu::Operations {
-                load: wgpu::LoadOp::Clear(1.0),
+                // Clear to the far plane depth (1.0, or 0.0 with reverse Z)
+                load: wgpu::LoadOp::Clear(depth_clear_value),
                 store: wgpu::StoreOp::Store,
             }),
             stencil_ops: None,
@@ -392,14 +1597,33 @@ fn draw_pipeline(
     });
 
     // Draw the render pass for our pipeline
-    render_pass.set_pipeline(&pipeline);
-    render_pass.set_bind_group(0, &camera_bind_group, &[]);
+    render_pass.set_pipeline(pipeline);
+    render_pass.set_bind_group(0, camera_bind_group, &[]);
     render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
-    render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
-    render_pass.draw_indexed(0..num_indices, 0, 0..1);
+    // Without index buffer, the vertex buffer is read in order, 3 vertices per triangle.
+    // The strips are drawn with their index buffer, whose format must match the pipeline one.
+    if let Some((index_buffer, index_format)) = index_buffer {
+        render_pass.set_index_buffer(index_buffer.slice(..), index_format);
+    }
+    let draw = |render_pass: &mut wgpu::RenderPass| {
+        for range in draw_ranges {
+            match index_buffer {
+                Some(_) => render_pass.draw_indexed(range.clone(), 0, 0..1),
+                None => render_pass.draw(range.clone(), 0..1),
+            }
+        }
+    };
+    draw(&mut render_pass);
+
+    // Draw the wireframe overlay on top of the mesh, in the same render pass
+    if let Some((wireframe_pipeline, wireframe_bind_group)) = wireframe {
+        render_pass.set_pipeline(wireframe_pipeline);
+        render_pass.set_bind_group(0, wireframe_bind_group, &[]);
+        draw(&mut render_pass);
+    }
 }
 
-/// (9.2) Copy the texture output into a buffer
+/// (10.3) Copy the texture output into a buffer
 fn copy_texture_to_buffer(
     encoder: &mut wgpu::CommandEncoder,
     texture: &wgpu::Texture,
@@ -410,13 +1634,13 @@ fn copy_texture_to_buffer(
         // source
         wgpu::ImageCopyTexture {
             aspect: wgpu::TextureAspect::All,
-            texture: &texture,
+            texture,
             mip_level: 0,
             origin: wgpu::Origin3d::ZERO,
         },
         // destination
         wgpu::ImageCopyBuffer {
-            buffer: &output_buffer,
+            buffer: output_buffer,
             layout: wgpu::ImageDataLayout {
                 offset: 0,
                 bytes_per_row: Some(texel_size * texture.width()),
@@ -428,7 +1652,7 @@ fn copy_texture_to_buffer(
     );
 }
 
-/// (11) Retrieve the texture buffer data from the GPU
+/// (12) Retrieve the texture buffer data from the GPU
 async fn retrieve_texture_buffer_data<'a>(
     device: &wgpu::Device,
     texture_buffer: &'a wgpu::Buffer,
@@ -448,3 +1672,50 @@ async fn retrieve_texture_buffer_data<'a>(
     // Will panic if buffer_slice.map_async() did not finish yet.
     buffer_slice.get_mapped_range()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The triangles of the strips, in the winding order the GPU assembles them with
+    fn strip_triangles(strips: &[Vec<u32>]) -> Vec<[u32; 3]> {
+        strips
+            .iter()
+            .flat_map(|strip| {
+                strip.windows(3).enumerate().map(|(k, w)| match k % 2 {
+                    0 => [w[0], w[1], w[2]],
+                    _ => [w[1], w[0], w[2]],
+                })
+            })
+            .collect()
+    }
+
+    /// Rotate a triangle so that it starts with its smallest vertex, keeping its winding
+    fn canonical([a, b, c]: [u32; 3]) -> [u32; 3] {
+        match a.min(b).min(c) {
+            m if m == a => [a, b, c],
+            m if m == b => [b, c, a],
+            _ => [c, a, b],
+        }
+    }
+
+    #[test]
+    fn triangle_strips_keep_the_triangles_and_their_winding() {
+        let (models, _) = tobj::load_obj("bunny.obj", &tobj::GPU_LOAD_OPTIONS).unwrap();
+        let indices = &models[0].mesh.indices;
+        let strips = triangle_strips(indices);
+        assert!(strips.len() < indices.len() / 3);
+
+        let mut expected: Vec<_> = indices
+            .chunks_exact(3)
+            .map(|t| canonical([t[0], t[1], t[2]]))
+            .collect();
+        let mut triangles: Vec<_> = strip_triangles(&strips)
+            .into_iter()
+            .map(canonical)
+            .collect();
+        expected.sort();
+        triangles.sort();
+        assert_eq!(triangles, expected);
+    }
+}
@@ -0,0 +1,205 @@
+// Screen-space ambient occlusion (SSAO), as a post-process in 3 compute passes.
+//
+// 1. "ssao": for each pixel, the view-space position is reconstructed from the depth texture,
+//    and the normal from the positions of its neighbours.
+//    Then sample points are taken in the hemisphere around that normal (the kernel),
+//    and projected back to the screen. When the depth texture shows something in front of
+//    a sample point, this sample is occluded. The ambient occlusion is the ratio of unoccluded samples.
+// 2. "blur": the kernel is randomly rotated for each pixel of a 4x4 tile,
+//    which trades banding artifacts for noise. A box blur removes that noise.
+// 3. "composite": the color output is multiplied by the blurred ambient occlusion.
+//
+// Each entry point only uses some of the bindings below,
+// so each compute pipeline has its own layout, and its own bind group.
+// Keep the bindings declared in increasing order, the OpenGL backend of wgpu relies on it.
+// The ambient occlusion is stored in RGBA textures, which are valid storage textures
+// and can be read back with the textureLoad() of a regular (filterable) texture.
+
+/// Perspective projection parameters, same as in the camera shader
+/// (booleans are encoded as u32, 0 is false)
+struct ProjCamParams {
+    focal: f32,
+    ratio: f32,
+    near: f32,
+    far: f32,
+    reverse_z: u32,
+    infinite_far: u32,
+    orbit_angle: f32,
+}
+
+/// SSAO parameters
+struct SsaoParams {
+    // Radius of the kernel hemisphere, in view-space units
+    radius: f32,
+    // Depth difference under which a sample is not considered occluded, to avoid self-occlusion
+    bias: f32,
+    // Exponent applied to the ambient occlusion, to make it stronger (> 1.0) or weaker
+    power: f32,
+    // The blur averages the (2 * blur_radius + 1)^2 pixels around each pixel
+    blur_radius: u32,
+}
+
+@group(0) @binding(0) var<uniform> proj_cam_params: ProjCamParams;
+@group(0) @binding(1) var<uniform> ssao_params: SsaoParams;
+// Sample points in the unit hemisphere, around the Z axis
+@group(0) @binding(2) var<storage, read> kernel: array<vec4<f32>>;
+// The depth is read as a regular float texture, see the SSAO layout in main.rs
+@group(0) @binding(3) var depth_texture: texture_2d<f32>;
+@group(0) @binding(4) var raw_ao_output: texture_storage_2d<rgba8unorm, write>;
+@group(0) @binding(5) var raw_ao_input: texture_2d<f32>;
+@group(0) @binding(6) var ao_output: texture_storage_2d<rgba8unorm, write>;
+@group(0) @binding(7) var ao_input: texture_2d<f32>;
+@group(0) @binding(8) var color_input: texture_2d<f32>;
+@group(0) @binding(9) var color_output: texture_storage_2d<rgba8unorm, write>;
+
+/// Convert a value of the depth texture into the view-space Z coordinate,
+/// by inverting the projection of the camera shader.
+/// Returns a negative value for the background (depth of the far plane).
+fn linear_depth(depth: f32) -> f32 {
+    let near = proj_cam_params.near;
+    let far = proj_cam_params.far;
+    // Back to the conventional depth mapping, where the far plane is at 1.0
+    let standard_depth = select(depth, 1.0 - depth, proj_cam_params.reverse_z != 0u);
+    if standard_depth >= 1.0 {
+        return -1.0;
+    }
+    if proj_cam_params.infinite_far != 0u {
+        // Reverse Z with infinite far plane: depth = near / z
+        return near / depth;
+    }
+    // Conventional mapping: depth = far * (z - near) / ((far - near) * z)
+    return far * near / (far - standard_depth * (far - near));
+}
+
+/// View-space position of the surface visible at the given pixel
+/// (with a negative Z for the background)
+fn view_position(pixel: vec2<i32>) -> vec3<f32> {
+    let size = vec2<f32>(textureDimensions(depth_texture));
+    let z = linear_depth(textureLoad(depth_texture, pixel, 0).r);
+    // Pixel centers to normalized device coordinates, Y being up in NDC but down in the texture
+    let ndc = vec2<f32>(
+        2.0 * (f32(pixel.x) + 0.5) / size.x - 1.0,
+        1.0 - 2.0 * (f32(pixel.y) + 0.5) / size.y,
+    );
+    let focal = proj_cam_params.focal;
+    return vec3<f32>(ndc.x * z / focal, ndc.y * z / (focal * proj_cam_params.ratio), z);
+}
+
+/// Pick the neighbour with the closest depth, to avoid reconstructing normals across edges
+fn closest_difference(center: vec3<f32>, before: vec3<f32>, after: vec3<f32>) -> vec3<f32> {
+    let difference_after = after - center;
+    let difference_before = center - before;
+    return select(difference_after, difference_before, abs(difference_before.z) < abs(difference_after.z));
+}
+
+@compute
+@workgroup_size(8, 8)
+fn ssao(@builtin(global_invocation_id) global_id: vec3u) {
+    let size = vec2<i32>(textureDimensions(depth_texture));
+    let pixel = vec2<i32>(global_id.xy);
+    if pixel.x >= size.x || pixel.y >= size.y {
+        return;
+    }
+    let position = view_position(pixel);
+    if position.z < 0.0 {
+        // Nothing to occlude in the background
+        textureStore(raw_ao_output, pixel, vec4<f32>(1.0));
+        return;
+    }
+
+    // Normal from the positions of the neighbours (clamped to the image borders),
+    // turned towards the camera, which is at the origin of the view space.
+    let max_pixel = size - 1;
+    let dx = closest_difference(
+        position,
+        view_position(clamp(pixel - vec2<i32>(1, 0), vec2<i32>(0), max_pixel)),
+        view_position(clamp(pixel + vec2<i32>(1, 0), vec2<i32>(0), max_pixel)),
+    );
+    let dy = closest_difference(
+        position,
+        view_position(clamp(pixel - vec2<i32>(0, 1), vec2<i32>(0), max_pixel)),
+        view_position(clamp(pixel + vec2<i32>(0, 1), vec2<i32>(0), max_pixel)),
+    );
+    var normal = normalize(cross(dx, dy));
+    if dot(normal, position) > 0.0 {
+        normal = -normal;
+    }
+
+    // Rotate the kernel around the normal, with one of 16 angles depending on the pixel
+    let tile_index = f32((pixel.x % 4) + 4 * (pixel.y % 4));
+    let angle = tile_index * 2.399963; // golden angle, to spread the 16 angles evenly
+    let random_vec = vec3<f32>(cos(angle), sin(angle), 0.0);
+    let tangent = normalize(random_vec - normal * dot(random_vec, normal));
+    let bitangent = cross(normal, tangent);
+    let tbn = mat3x3<f32>(tangent, bitangent, normal);
+
+    let focal = proj_cam_params.focal;
+    let ratio = proj_cam_params.ratio;
+    let radius = ssao_params.radius;
+    let num_samples = arrayLength(&kernel);
+    var occlusion = 0.0;
+    for (var i = 0u; i < num_samples; i++) {
+        let sample_position = position + radius * (tbn * kernel[i].xyz);
+
+        // Project the sample point back to the screen
+        let ndc = vec2<f32>(
+            focal * sample_position.x / sample_position.z,
+            focal * ratio * sample_position.y / sample_position.z,
+        );
+        let sample_pixel = vec2<i32>(floor(vec2<f32>(0.5 * ndc.x + 0.5, 0.5 - 0.5 * ndc.y) * vec2<f32>(size)));
+        if any(sample_pixel < vec2<i32>(0)) || any(sample_pixel >= size) {
+            continue;
+        }
+        let scene_depth = linear_depth(textureLoad(depth_texture, sample_pixel, 0).r);
+        if scene_depth < 0.0 {
+            continue;
+        }
+
+        // The sample is occluded if the visible surface is in front of it.
+        // Surfaces much further in front than the kernel radius should not occlude,
+        // such as the bunny ear in front of its back.
+        let range_check = smoothstep(0.0, 1.0, radius / abs(position.z - scene_depth));
+        if scene_depth <= sample_position.z - ssao_params.bias {
+            occlusion += range_check;
+        }
+    }
+    let ao = pow(1.0 - occlusion / f32(num_samples), ssao_params.power);
+    textureStore(raw_ao_output, pixel, vec4<f32>(vec3<f32>(ao), 1.0));
+}
+
+@compute
+@workgroup_size(8, 8)
+fn blur(@builtin(global_invocation_id) global_id: vec3u) {
+    let size = vec2<i32>(textureDimensions(raw_ao_input));
+    let pixel = vec2<i32>(global_id.xy);
+    if pixel.x >= size.x || pixel.y >= size.y {
+        return;
+    }
+    // Box blur, only averaging the pixels inside the image
+    let blur_radius = i32(ssao_params.blur_radius);
+    var sum = 0.0;
+    var count = 0.0;
+    for (var y = -blur_radius; y <= blur_radius; y++) {
+        for (var x = -blur_radius; x <= blur_radius; x++) {
+            let neighbour = pixel + vec2<i32>(x, y);
+            if all(neighbour >= vec2<i32>(0)) && all(neighbour < size) {
+                sum += textureLoad(raw_ao_input, neighbour, 0).r;
+                count += 1.0;
+            }
+        }
+    }
+    textureStore(ao_output, pixel, vec4<f32>(vec3<f32>(sum / count), 1.0));
+}
+
+@compute
+@workgroup_size(8, 8)
+fn composite(@builtin(global_invocation_id) global_id: vec3u) {
+    let size = vec2<i32>(textureDimensions(color_input));
+    let pixel = vec2<i32>(global_id.xy);
+    if pixel.x >= size.x || pixel.y >= size.y {
+        return;
+    }
+    let color = textureLoad(color_input, pixel, 0);
+    let ao = textureLoad(ao_input, pixel, 0).r;
+    textureStore(color_output, pixel, vec4<f32>(ao * color.rgb, color.a));
+}
@@ -5,26 +5,81 @@
 // storage textures of any format (r32uint should be supported as per the WebGPU spec).
 // Instead we use a regular texture as input.
 // Note 2: 32 bits uint is very wastefull for storing a cell's binary liveliness, but storage
-// textures are limited in their format. In a more performance minded setting we could pack
-// 32 cells in a single texel.
-// Note 3: there are 2 functionnaly equivalent entry points in this file, adapt the pipeline
-// creation to select the one you want to use
+// textures are limited in their format. The "step_packed" variant packs 32 cells in a single texel.
+// Note 3: there are 3 functionnaly equivalent entry points in this file, select the one you want
+// to use with the --entry-point argument (the packed one also needs packed textures, see main.rs)
+// Note 4: the neighbors beyond the borders of the grid depend on the boundary mode (see Params)
+// Note 5: the rule is not limited to Conway's game of life (B3/S23), any "Life-like" rule is
+// provided as a uniform, see main.rs for its "B/S" notation
+// Note 6: the grid size does not need to be a multiple of the workgroup size, the threads beyond
+// the grid (in the last workgroups of each row and column) don't write any cell
+
+// Width and height of the workgroups.
+// This would be a pipeline-overridable constant ("override"), but they are not supported yet by
+// the version of wgpu we use, so main.rs replaces this declaration in the source instead.
+const WORKGROUP_SIZE: u32 = 16u;
+
+const BOUNDARY_TORUS: u32 = 0u;
+const BOUNDARY_DEAD: u32 = 1u;
+const BOUNDARY_MIRROR: u32 = 2u;
+
+struct Params {
+    // Life-like rule, as bitmasks of the numbers of alive neighbors (0 to 8):
+    // a dead cell is born if the bit of its number of alive neighbors is set in "birth",
+    // an alive cell survives if the bit is set in "survive".
+    // E.g. Conway's game of life (B3/S23) is birth = 0b1000, survive = 0b1100.
+    birth: u32,
+    survive: u32,
+    // Boundary mode, for the neighbors beyond the borders of the grid:
+    // - BOUNDARY_TORUS: the grid wraps around its borders
+    // - BOUNDARY_DEAD: they are dead
+    // - BOUNDARY_MIRROR: the grid is mirrored, they are copies of the cells along the border
+    boundary: u32,
+}
 
 @group(0) @binding(0) var input_grid: texture_2d<u32>;
 @group(0) @binding(1) var output_grid: texture_storage_2d<r32uint, write>;
+@group(0) @binding(2) var<uniform> params: Params;
+
+/// Texel of the input grid at the given coordinates, which may be beyond the borders,
+/// according to the boundary mode.
+/// The texels beyond the borders are only meaningful for the neighbors of the texels along the borders.
+fn load_cell(coords: vec2i) -> u32 {
+    let size = vec2i(textureDimensions(input_grid));
+    // Both coordinates are computed and one is selected, rather than branching on the boundary mode,
+    // since branches are costly in the shaders (all the threads of a group may run both paths).
+    // Add the size before the modulo, since the remainder of a negative i32 is negative
+    // (and the modulo of an underflowing u32 only wraps for power of two sizes)
+    let wrapped = (coords + size) % size;
+    // -1 -> 0 and size -> size - 1
+    let mirrored = clamp(coords, vec2i(0), size - 1);
+    let cell = textureLoad(input_grid, select(wrapped, mirrored, params.boundary == BOUNDARY_MIRROR), 0).x;
+    let outside = any((coords < vec2i(0)) | (coords >= size));
+    return select(cell, 0u, outside && params.boundary == BOUNDARY_DEAD);
+}
+
+/// Next state of a cell, given its current state and its number of alive neighbors
+fn next_alive(alive: bool, num_alive: i32) -> bool {
+    let mask = select(params.birth, params.survive, alive);
+    return ((mask >> u32(num_alive)) & 1u) != 0u;
+}
 
 // This is a basic variant that reads directly into the input texture
 // Because each threads access its 3x3 neighbnorhood, this implies redundant accesses to
 // (comparatively slow) VRAM
 @compute
-@workgroup_size(16, 16)
+@workgroup_size(WORKGROUP_SIZE, WORKGROUP_SIZE)
 fn step(@builtin(global_invocation_id) cell_id: vec3u) {
+    // Skip the threads beyond the grid
+    if any(cell_id.xy >= textureDimensions(input_grid)) {
+        return;
+    }
 
     var num_alive = 0;
     var alive: bool = false;
     for (var i = -1; i <= 1; i++) {
         for (var j = -1; j <= 1; j++) {
-            let cell = textureLoad(input_grid, (vec2i(cell_id.xy) + vec2i(j, i)) % vec2i(textureDimensions(input_grid)), 0).x;
+            let cell = load_cell(vec2i(cell_id.xy) + vec2i(j, i));
             if (i == 0) && (j == 0) {
                 if cell > 0 {
                     alive = true;
@@ -37,15 +92,7 @@ fn step(@builtin(global_invocation_id) cell_id: vec3u) {
         }
     }
 
-    if alive {
-        if (num_alive < 2) || (num_alive > 3) {
-            alive = false;
-        } else {
-            alive = true;
-        } 
-    } else if num_alive == 3 {
-        alive = true;
-    }
+    alive = next_alive(alive, num_alive);
 
     textureStore(output_grid, cell_id.xy, vec4u(select(0, 1, alive)));
 }
@@ -57,55 +104,70 @@ fn step(@builtin(global_invocation_id) cell_id: vec3u) {
 // otherwise concurrent accesses should be taken care of (e.g. using atomic operations).
 
 // array<bool> would be better, but it does not seem to work
-var<workgroup> grid: array<u32, 324>; // (1+16+1)x(1+16+1) = 18x18
+const TILE_SIZE: u32 = WORKGROUP_SIZE + 2u; // 1 + WORKGROUP_SIZE + 1
+const TILE_CELLS: u32 = TILE_SIZE * TILE_SIZE; // (an array size can't be an expression in naga)
+var<workgroup> grid: array<u32, TILE_CELLS>; // e.g. 18x18 for 16x16 workgroups
 
 @compute
-@workgroup_size(16, 16)
+@workgroup_size(WORKGROUP_SIZE, WORKGROUP_SIZE)
 fn step_local_mem(@builtin(global_invocation_id) cell_id: vec3u,
         @builtin(local_invocation_id) local_id: vec3u,
 ) {
-    let width = textureDimensions(input_grid).x;
-    let height = textureDimensions(input_grid).y;
+    // The threads beyond the grid can't return early: all the threads of the workgroup
+    // must reach the barrier below. Their cells are loaded according to the boundary mode,
+    // since the cells just beyond the grid are the neighbors of the cells along its borders.
+
     // Every thread loads its cell
-    grid[18 * (local_id.y + 1) + (local_id.x + 1)] = textureLoad(input_grid, vec2i(cell_id.xy), 0).x;
+    let cell_coords = vec2i(cell_id.xy);
+    let last = WORKGROUP_SIZE - 1u;
+    grid[TILE_SIZE * (local_id.y + 1) + (local_id.x + 1)] = load_cell(cell_coords);
 
     // Top threads also load an extra line above
+    // (not "else if" below, for workgroups of a single thread)
     if (local_id.y == 0) {
-        grid[local_id.x + 1] = textureLoad(input_grid, vec2i(i32(cell_id.x), i32((cell_id.y - 1) % height)), 0).x;
+        grid[local_id.x + 1] = load_cell(cell_coords + vec2i(0, -1));
         if (local_id.x == 0) {
-            grid[0] = textureLoad(input_grid, vec2i(i32((cell_id.x - 1) % width), i32((cell_id.y - 1) % height)), 0).x;
-        } else if (local_id.x == 15) {
-            grid[17] = textureLoad(input_grid, vec2i(i32((cell_id.x + 1) % width), i32((cell_id.y - 1) % height)), 0).x;
+            grid[0] = load_cell(cell_coords + vec2i(-1, -1));
+        }
+        if (local_id.x == last) {
+            grid[TILE_SIZE - 1] = load_cell(cell_coords + vec2i(1, -1));
         }
     }
     // Bottom threads load an extra line below
-    else if (local_id.y == 15) {
-        grid[18 * 17 + local_id.x + 1] = textureLoad(input_grid, vec2i(i32(cell_id.x), i32((cell_id.y + 1) % height)), 0).x;
+    if (local_id.y == last) {
+        grid[TILE_SIZE * (TILE_SIZE - 1) + local_id.x + 1] = load_cell(cell_coords + vec2i(0, 1));
         if (local_id.x == 0) {
-            grid[18 * 17] = textureLoad(input_grid, vec2i(i32((cell_id.x - 1) % width), i32((cell_id.y + 1) % height)), 0).x;
-        } else if (local_id.x == 15) {
-            grid[18 * 17 + 17] = textureLoad(input_grid, vec2i(i32((cell_id.x + 1) % width), i32((cell_id.y + 1) % height)), 0).x;
+            grid[TILE_SIZE * (TILE_SIZE - 1)] = load_cell(cell_coords + vec2i(-1, 1));
+        }
+        if (local_id.x == last) {
+            grid[TILE_SIZE * TILE_SIZE - 1] = load_cell(cell_coords + vec2i(1, 1));
         }
     }
-    // Left side threads load an extra cell to the left
-    else if (local_id.x == 0) {
-        grid[18 * local_id.y] = textureLoad(input_grid, vec2i(i32((cell_id.x - 1) % width), i32(cell_id.y)), 0).x;
+    // Left side threads load an extra cell to the left, in the row of their cell (which is the row
+    // local_id.y + 1 of the array), including the top and bottom threads
+    if (local_id.x == 0) {
+        grid[TILE_SIZE * (local_id.y + 1)] = load_cell(cell_coords + vec2i(-1, 0));
     }
     // Right side threads load an extra cell to the right
-    else if (local_id.x == 15) {
-        grid[18 * local_id.y + 17] = textureLoad(input_grid, vec2i(i32((cell_id.x + 1) % width), i32(cell_id.y)), 0).x;
+    if (local_id.x == last) {
+        grid[TILE_SIZE * (local_id.y + 1) + TILE_SIZE - 1] = load_cell(cell_coords + vec2i(1, 0));
     }
 
     // Wait for each thread to have loaded its data
     workgroupBarrier();
 
+    // Now the threads beyond the grid can be skipped
+    if any(cell_id.xy >= textureDimensions(input_grid)) {
+        return;
+    }
+
     // The rest is similar to the other variant
     let cell_coord = vec2i(local_id.xy + vec2u(1, 1));
     var num_alive = 0;
     var alive: bool = false;
     for (var i = -1; i <= 1; i++) {
         for (var j = -1; j <= 1; j++) {
-            let cell = grid[18 * (cell_coord.y + i) + (cell_coord.x + j)];
+            let cell = grid[i32(TILE_SIZE) * (cell_coord.y + i) + (cell_coord.x + j)];
             if (i == 0) && (j == 0) {
                 if cell > 0 {
                     alive = true;
@@ -118,17 +180,89 @@ fn step_local_mem(@builtin(global_invocation_id) cell_id: vec3u,
         }
     }
 
-    if alive {
-        if (num_alive < 2) || (num_alive > 3) {
-            alive = false;
-        } else {
-            alive = true;
-        } 
-    } else if num_alive == 3 {
-        alive = true;
-    }
+    alive = next_alive(alive, num_alive);
 
     textureStore(output_grid, cell_id.xy, vec4u(select(0, 1, alive)));
 }
 
+// This variant packs 32 cells in each texel, the bit k of the texel (x, y) being the cell (32x + k, y).
+// Each thread computes the 32 cells of a texel at once with bitwise operations:
+// the neighbors in each of the 8 directions are gathered into a 32 bits mask (shifting the bits
+// across the words on the left and right), and the masks are summed into 32 counters "bit-sliced"
+// in 4 words, the bit k of the word i being the bit i of the counter of the cell k.
 
+/// Word of the packed grid at the given texel coordinates, according to the boundary mode
+fn load_word(coords: vec2i) -> u32 {
+    let word = load_cell(coords);
+    // When mirrored, the words beyond the left and right borders also have their cells reversed,
+    // e.g. the cell -1 being a copy of the cell 0, the last bit of the word -1 is the first bit of the word 0
+    let width = i32(textureDimensions(input_grid).x);
+    if params.boundary == BOUNDARY_MIRROR && (coords.x < 0 || coords.x >= width) {
+        return reverseBits(word);
+    }
+    return word;
+}
+
+@compute
+@workgroup_size(WORKGROUP_SIZE, WORKGROUP_SIZE)
+fn step_packed(@builtin(global_invocation_id) word_id: vec3u) {
+    // Skip the threads beyond the grid, the packed grid width being 32 times smaller than the grid width
+    let word_coords = vec2i(word_id.xy);
+    if any(word_id.xy >= textureDimensions(input_grid)) {
+        return;
+    }
+
+    // The 8 neighbor masks, from the 3 rows of 3 words around the current word
+    var neighbors: array<u32, 8>;
+    var num_neighbors = 0;
+    for (var i = -1; i <= 1; i++) {
+        let left = load_word(word_coords + vec2i(-1, i));
+        let center = load_word(word_coords + vec2i(0, i));
+        let right = load_word(word_coords + vec2i(1, i));
+        // Left neighbors: the bit k comes from the cell k - 1, the bit 0 from the last cell of the left word
+        neighbors[num_neighbors] = (center << 1u) | (left >> 31u);
+        // Right neighbors: the bit k comes from the cell k + 1, the bit 31 from the first cell of the right word
+        neighbors[num_neighbors + 1] = (center >> 1u) | (right << 31u);
+        num_neighbors += 2;
+        // The cells above and below, but not the current cells
+        if i != 0 {
+            neighbors[num_neighbors] = center;
+            num_neighbors++;
+        }
+    }
+
+    // Sum the neighbor masks into the bit-sliced counters (up to 8, so 4 bits),
+    // propagating the carry as in a binary addition
+    var count0 = 0u;
+    var count1 = 0u;
+    var count2 = 0u;
+    var count3 = 0u;
+    for (var n = 0; n < 8; n++) {
+        let carry0 = count0 & neighbors[n];
+        count0 ^= neighbors[n];
+        let carry1 = count1 & carry0;
+        count1 ^= carry0;
+        let carry2 = count2 & carry1;
+        count2 ^= carry1;
+        count3 |= carry2;
+    }
+
+    // For each possible number of alive neighbors, the mask of the cells having that number
+    // (all the bits of their counters match it) are born or survive according to the rule
+    let alive = load_word(word_coords);
+    var next = 0u;
+    for (var n = 0u; n <= 8u; n++) {
+        let matches = select(~count0, count0, (n & 1u) != 0u)
+            & select(~count1, count1, (n & 2u) != 0u)
+            & select(~count2, count2, (n & 4u) != 0u)
+            & select(~count3, count3, (n & 8u) != 0u);
+        if ((params.birth >> n) & 1u) != 0u {
+            next |= matches & ~alive;
+        }
+        if ((params.survive >> n) & 1u) != 0u {
+            next |= matches & alive;
+        }
+    }
+
+    textureStore(output_grid, word_id.xy, vec4u(next));
+}
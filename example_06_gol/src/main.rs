@@ -12,30 +12,83 @@
 //! * The use of compute shader that writes into a storage texture
 //! * The classic "ping-pong" technique between two textures for iterative computation
 //! * The use of local workgroup memory (optional: see the shader code for details)
+//! * Bit-packed grids storing 32 cells per texel (optional: see the shader code for details)
+//! * Pipelined readbacks, with a ring of staging buffers mapped asynchronously
+//!
+//! Reading back each iteration into a single staging buffer would serialize everything:
+//! the GPU waits for the CPU to unmap the buffer, and the CPU waits for the GPU to fill it.
+//! Instead, each iteration copies its grid into the next buffer of a ring of K staging buffers
+//! (`--staging-buffers K`, 3 by default, 1 giving back the serialized behavior),
+//! and a buffer is only waited for when it is about to be reused, K iterations later.
+//! Meanwhile, the gif frames are encoded on another thread.
+//! The throughput (iterations per second) is reported at the end, with the time spent blocked
+//! waiting for the readbacks: that's the time the ring can save, if the GPU works asynchronously.
+//!
+//! The shader entry point is selected with `--entry-point step|step_local_mem|step_packed`.
+//! With "step_packed", the grid textures are 32 times narrower, each texel holding 32 cells
+//! in its bits, so the initial state is packed before its upload, and the frames are unpacked
+//! after their readback. It produces the same frames as "step".
+//!
+//! The grid size is set with `--size WIDTHxHEIGHT` (256x256 by default, the width being
+//! a multiple of 32 with "step_packed"), and does not need to be a multiple of the workgroup size.
+//! The number of workgroups is rounded up, and the shaders skip the threads beyond the grid.
+//! The workgroups are 16x16 threads by default, which can be tuned with `--workgroup-size`
+//! (at most 16 with the default limit of 256 threads per workgroup).
+//!
+//! The rule of the automaton is given in the standard "B/S" notation with `--rule`,
+//! listing the numbers of alive neighbors for which a dead cell is born, and an alive cell survives.
+//! It defaults to Conway's game of life "B3/S23", other examples being HighLife "B36/S23",
+//! Seeds "B2/S" or Day & Night "B3678/S34678".
+//! It is provided to the shader as two bitmasks in a uniform buffer (see `Rule`).
+//!
+//! The grid wraps around its borders by default (it's a torus). With `--boundary dead`,
+//! the cells beyond the borders are dead instead, and with `--boundary mirror`,
+//! they are copies of the cells along the borders. The boundary mode is also in the uniform buffer.
+//!
+//! The random initial state is reproducible with `--seed N`, the same arguments always producing
+//! the same gif (without seed, a random one is picked, and printed to reproduce the run).
+//! The probability of each cell to be alive is set with `--density` (0.2 by default).
+//!
+//! Instead of random cells, the initial state can be loaded from a pattern file with `--pattern`,
+//! in the Golly RLE format (".rle") or in the plaintext format (".cells"), e.g. `--pattern glider_gun.rle`.
+//! The pattern is centered in the grid, unless placed with `--offset X,Y` (its top left corner).
+//! The rule of an RLE file header is ignored, use `--rule` if needed.
 //!
 //! 1. (async) Initialize the connection with the GPU device
 //! 2. Initialize 2 wgpu Texture objects that will serve as input and output data to a compute shader
 //!    To be able to write to these textures from a computer shader, their declaration includes
 //!    "storage binding" as usage (as well as "texture binding", for reading them)
-//! 3. Place cells at random locations into the first input grid (initial automaton state)
-//!    and upload this data int the respective texture.
-//! 4. Initialize a wgpu Buffer where one of the texture will be transferred to
-//! 5. Load the shader module, containing a compute shader
-//! 6. Define a compute pipeline
-//! 7. Create two similar bind groups:
+//! 3. Place cells at random locations into the first input grid (initial automaton state),
+//!    or load them from a pattern file,
+//!    and upload this data int the respective texture (packing the cells if needed).
+//! 4. Initialize a ring of wgpu Buffers where the current texture will be transferred to
+//! 5. Load the shader module, containing a compute shader, with the chosen workgroup size
+//! 6. Define a compute pipeline, and put the rule and boundary mode into a uniform buffer
+//! 7. Create two similar bind groups, both binding the uniform buffer:
 //!    - one that binds texture 0 as input and texture 1 as output
 //!    - the other, similar but with bindings swapped
-//! 8. Iterate multiple run of our compute shader
-//!    1. dispatch a grid of workgroups running the computer kernel to cover the whole grid
-//!    2. copy the current input grid into the staging buffer
-//!    3. submit the commands to the queue
-//!    4. read back the content of the staging buffer
-//!    5. postprocess the pixel data to encode a frame in an animated gif
-//!    6. unmap the staging buffer so that it's reusable for next iteration
+//! 8. Start a thread encoding the frames it receives into an animated gif
+//! 9. Iterate multiple run of our compute shader
+//!    1. if the next staging buffer of the ring is still in use, finish its readback (see 10.)
+//!    2. dispatch a grid of workgroups running the computer kernel to cover the whole grid
+//!    3. copy the current input grid into the staging buffer
+//!    4. submit the commands to the queue
+//!    5. request the mapping of the staging buffer, without waiting for it
+//! 10. Finish the readbacks still in flight. Each readback:
+//!    1. waits for the submission of its iteration, and for the mapping of its buffer
+//!    2. postprocesses the pixel data (unpacking the cells if needed),
+//!       and sends it to the gif thread as a frame
+//!    3. unmaps the staging buffer so that it's reusable for a next iteration
+//! 11. Wait for the gif thread to encode the last frames, and report the throughput
+//!     and the time spent waiting for the readbacks
 
 use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
 use std::borrow::Cow;
+use std::collections::VecDeque;
 use std::fs::File;
+use std::sync::mpsc;
+use wgpu::util::DeviceExt; // Utility trait to create and initialize buffers with device.create_buffer_init()
 
 fn main() {
     // Make the main async
@@ -45,14 +98,71 @@ fn main() {
 async fn run() {
     // (1) Initializing WebGPU
     println!("Initializing WebGPU ...");
-    let (device, queue) = init_wgpu_device().await.unwrap();
+    let (device, queue) = init_wgpu_device(false).await.unwrap();
+
+    let args: Vec<String> = std::env::args().collect();
+    let arg_value = |name: &str| {
+        let position = args.iter().position(|arg| arg == name)?;
+        Some(
+            args.get(position + 1)
+                .expect("Missing argument value")
+                .as_str(),
+        )
+    };
+    // Number of staging buffers in the ring (at least one)
+    let num_staging_buffers: usize = arg_value("--staging-buffers")
+        .map(|value| value.parse().unwrap())
+        .unwrap_or(3);
+    let entry_point = arg_value("--entry-point").unwrap_or("step");
+    let packed = match entry_point {
+        "step" | "step_local_mem" => false,
+        "step_packed" => true,
+        _ => panic!("Unknown entry point: {entry_point}"),
+    };
+    assert!(
+        num_staging_buffers > 0,
+        "At least one staging buffer is required"
+    );
+    let params = Params {
+        rule: Rule::parse(arg_value("--rule").unwrap_or("B3/S23")),
+        boundary: Boundary::from_name(arg_value("--boundary").unwrap_or("torus")) as u32,
+        _padding: 0,
+    };
+    let seed: u64 = arg_value("--seed").map_or_else(random, |value| value.parse().unwrap());
+    let density: f64 = arg_value("--density")
+        .map(|value| value.parse().unwrap())
+        .unwrap_or(0.2);
+    assert!(
+        (0.0..=1.0).contains(&density),
+        "The density must be between 0 and 1"
+    );
+    let (width, height) = arg_value("--size").map_or((256, 256), |value| {
+        let (width, height) = value
+            .split_once('x')
+            .expect("Expected a size as WIDTHxHEIGHT");
+        (width.parse().unwrap(), height.parse().unwrap())
+    });
+    let workgroup_size: u32 = arg_value("--workgroup-size")
+        .map(|value| value.parse().unwrap())
+        .unwrap_or(16);
+    assert!(
+        workgroup_size > 0
+            && workgroup_size * workgroup_size
+                <= device.limits().max_compute_invocations_per_workgroup,
+        "Invalid workgroup size: {workgroup_size}"
+    );
+    let pattern_path = arg_value("--pattern");
+    let pattern_offset = arg_value("--offset").map(|value| {
+        let (x, y) = value.split_once(',').expect("Expected an offset as X,Y");
+        [x.parse().unwrap(), y.parse().unwrap()]
+    });
 
-    // (2) Initialize two textures for the input (current state) and output (next state) grids
-    let width = 256;
-    let height = 256;
+    // (2) Initialize two textures for the input (current state) and output (next state) grids.
+    // The packed grids have one texel per 32 cells of a row.
+    let grid_width = if packed { width / 32 } else { width };
     let grids = [
-        init_grid_texture(&device, width, height),
-        init_grid_texture(&device, width, height),
+        init_grid_texture(&device, grid_width, height),
+        init_grid_texture(&device, grid_width, height),
     ];
     let desc = wgpu::TextureViewDescriptor {
         label: None,
@@ -66,142 +176,338 @@ async fn run() {
     };
     let grid_views = [grids[0].create_view(&desc), grids[1].create_view(&desc)];
 
-    // (3) Put some initial random cells into the input grid
-    let mut rng = rand::thread_rng();
-    let init_state: Vec<u8> = (0..4 * width * height)
-        .map(|i| {
-            if rng.gen::<u8>() > 200 && (i % 4 == 0) {
-                1
-            } else {
-                0
-            }
-        })
-        .collect();
-    queue.write_texture(
-        wgpu::ImageCopyTexture {
-            texture: &grids[0],
-            mip_level: 0,
-            origin: wgpu::Origin3d::ZERO,
-            aspect: wgpu::TextureAspect::All,
-        },
-        &init_state,
-        wgpu::ImageDataLayout {
-            offset: 0,
-            bytes_per_row: Some(4 * width), // texel size of r32uint texture is 4 bytes
-            rows_per_image: Some(height),
-        },
-        grids[0].size(),
-    );
+    // (3) Put some initial random cells into the input grid, or the cells of a pattern file
+    let cells: Vec<u32> = match pattern_path {
+        Some(path) => {
+            println!("Loading the pattern {path} ...");
+            Pattern::load(path).place(width, height, pattern_offset)
+        }
+        None => {
+            println!("Generating random cells with the seed {seed} ...");
+            random_cells(width, height, seed, density)
+        }
+    };
+    write_grid(&queue, &grids[0], &cells, width, packed);
 
-    // (4) Create a staging buffer for retrieving the content of the current state texture
-    let staging_buffer = init_buffer(&device, width, height);
+    // (4) Create a ring of staging buffers for retrieving the content of the current state texture
+    let staging_buffers: Vec<wgpu::Buffer> = (0..num_staging_buffers)
+        .map(|_| init_buffer(&device, grid_width, height))
+        .collect();
 
     // (5) Initialize the shader module, containing a single computer shader
-    let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-        label: Some("Game of Life"),
-        source: wgpu::ShaderSource::Wgsl(include_str!("game_of_life.wgsl").into()),
-    });
+    let shader_module = create_shader_module(&device, workgroup_size);
 
     // (6) Define a compute pipeline
-    // Use "step" or "step_local_mem" as the entry point of the shader.
-    let pipeline = build_pipeline(&device, &shader_module, "step");
+    // Use "step", "step_local_mem" or "step_packed" as the entry point of the shader.
+    let pipeline = build_pipeline(&device, &shader_module, entry_point);
+    let params_buffer = params.create_uniform_buffer(&device);
 
     // (7) create two bind groups alternating the role of the 2 textures
-    let desc = wgpu::BindGroupDescriptor {
-        label: None,
-        layout: &pipeline.get_bind_group_layout(0),
-        entries: &[
-            wgpu::BindGroupEntry {
-                binding: 0,
-                resource: wgpu::BindingResource::TextureView(&grid_views[0]),
-            },
-            wgpu::BindGroupEntry {
-                binding: 1,
-                resource: wgpu::BindingResource::TextureView(&grid_views[1]),
-            },
-        ],
-    };
-    let desc_alt = wgpu::BindGroupDescriptor {
-        entries: &[
-            wgpu::BindGroupEntry {
-                binding: 0,
-                resource: wgpu::BindingResource::TextureView(&grid_views[1]),
-            },
-            wgpu::BindGroupEntry {
-                binding: 1,
-                resource: wgpu::BindingResource::TextureView(&grid_views[0]),
-            },
-        ],
-        ..desc
-    };
-    let bind_groups = [
-        device.create_bind_group(&desc),
-        device.create_bind_group(&desc_alt),
-    ];
+    let bind_groups = create_bind_groups(&device, &pipeline, &grid_views, &params_buffer);
 
     // Show progress in the console
     const N_ITERS: usize = 1000;
     let bar = indicatif::ProgressBar::new(N_ITERS as u64);
 
-    // Animated gif encoder
-    let mut image = File::create("image.gif").unwrap();
-    let color_map = &[
-        0x11, 0x77, 0xaa, /* cell color */
-        0x33, 0x22, 0, /* background color*/
-    ];
-    let mut gif_enc =
-        gif::Encoder::new(&mut image, width as u16, height as u16, color_map).unwrap();
+    // (8) Encode the animated gif on another thread, receiving the frames through a channel
+    let (frame_sender, frame_receiver) = mpsc::channel::<Vec<u8>>();
+    let gif_thread = std::thread::spawn(move || {
+        let image = File::create("image.gif").unwrap();
+        let color_map = &[
+            0x11, 0x77, 0xaa, /* cell color */
+            0x33, 0x22, 0, /* background color*/
+        ];
+        let mut gif_enc = gif::Encoder::new(image, width as u16, height as u16, color_map).unwrap();
+        for pixels in frame_receiver {
+            let frame = gif::Frame {
+                delay: 2,
+                width: width as u16,
+                height: height as u16,
+                buffer: Cow::Borrowed(&*pixels),
+                ..Default::default()
+            };
+            gif_enc.write_frame(&frame).unwrap();
+        }
+    });
 
-    // (8) Evolve the automaton over some iterations
+    // (9) Evolve the automaton over some iterations.
+    // The readbacks in flight, from the oldest to the most recent.
+    let mut readbacks = VecDeque::with_capacity(num_staging_buffers);
     println!("Computing Game of Life's iterations ...");
+    let start = std::time::Instant::now();
+    let mut waiting = std::time::Duration::ZERO;
     for i in 0..N_ITERS {
         bar.inc(1);
+        let staging_buffer = &staging_buffers[i % num_staging_buffers];
+
+        // (9.1) If all the staging buffers are in use, the oldest one is the next of the ring,
+        // and we finish its readback to reuse it
+        if readbacks.len() == num_staging_buffers {
+            let readback = readbacks.pop_front().unwrap();
+            waiting += finish_readback(&device, readback, width, packed, &frame_sender);
+        }
 
         // Initialize a command encoder
         let mut encoder = device.create_command_encoder(&Default::default());
 
-        // (8.1) launch the compute kernel in blocks
+        // (9.2) launch the compute kernel in blocks
         launch_kernel(
             &mut encoder,
             &pipeline,
             &bind_groups[i % 2], // alternate input and output grids every other iteration
             &grids[0].size(),
+            workgroup_size,
         );
 
-        // (8.2) Copy the current input texture to the staging buffer
-        copy_texture_to_buffer(&mut encoder, &grids[i % 2], &staging_buffer);
+        // (9.3) Copy the current input texture to the staging buffer
+        copy_texture_to_buffer(&mut encoder, &grids[i % 2], staging_buffer);
 
-        // (8.3) Finalize the command encoder and send it to the queue
-        queue.submit(Some(encoder.finish()));
+        // (9.4) Finalize the command encoder and send it to the queue
+        let submission_index = queue.submit(Some(encoder.finish()));
 
-        // (8.4) read back the content of the staging buffer
-        let data = readback_buffer_data(&device, &staging_buffer).await;
+        // (9.5) Request the mapping of the staging buffer, completed during a later device poll
+        readbacks.push_back(request_readback(staging_buffer, submission_index));
 
-        // (8.5) Convert the returned data and encode it as frame of the result gif
-        // u32 texel -> u8 \in {0, 1} (gif is a paletted format)
-        let pixels: Vec<u8> = bytemuck::cast_slice::<u8, u32>(&data as &[u8])
+        // Process the callbacks of the work already finished, without blocking
+        device.poll(wgpu::Maintain::Poll);
+    }
+
+    // (10) Finish the readbacks of the last iterations
+    for readback in readbacks {
+        waiting += finish_readback(&device, readback, width, packed, &frame_sender);
+    }
+    bar.finish();
+
+    // (11) Closing the channel ends the gif thread, once it has encoded the remaining frames
+    drop(frame_sender);
+    gif_thread.join().unwrap();
+    let elapsed = start.elapsed().as_secs_f64();
+    println!(
+        "{N_ITERS} iterations with {num_staging_buffers} staging buffer(s) in {elapsed:.2}s ({:.1} iterations/s)",
+        N_ITERS as f64 / elapsed
+    );
+    println!(
+        "{:.2}s spent waiting for the readbacks",
+        waiting.as_secs_f64()
+    );
+
+    println!("Terminating the program ...")
+}
+
+/// Life-like rule of the automaton, in the uniform buffer of the parameters.
+/// The bit n of each mask is set if the rule applies with n alive neighbors (0 to 8).
+/// Bytemuck is used to enable easy casting to a &[u8].
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct Rule {
+    /// Numbers of alive neighbors for which a dead cell becomes alive
+    birth: u32,
+    /// Numbers of alive neighbors for which an alive cell stays alive
+    survive: u32,
+}
+
+impl Rule {
+    /// Parse a rule in the "B/S" notation, e.g. "B3/S23" (case insensitive)
+    fn parse(rule: &str) -> Self {
+        // Mask of the digits (0 to 8) following the prefix
+        let mask = |digits: &str, prefix: char| {
+            digits
+                .strip_prefix(prefix)?
+                .chars()
+                .try_fold(0, |mask, digit| {
+                    let neighbors = digit.to_digit(10).filter(|&neighbors| neighbors <= 8)?;
+                    Some(mask | (1 << neighbors))
+                })
+        };
+        let upper_rule = rule.to_ascii_uppercase();
+        let (birth, survive) = upper_rule.split_once('/').unwrap_or_default();
+        let parse_masks = || {
+            Some(Self {
+                birth: mask(birth, 'B')?,
+                survive: mask(survive, 'S')?,
+            })
+        };
+        parse_masks().unwrap_or_else(|| {
+            panic!("Invalid rule: {rule} (expected the B/S notation, e.g. B3/S23)")
+        })
+    }
+}
+
+/// Behavior of the cells beyond the borders of the grid, matching the shader constants
+#[derive(Clone, Copy, Debug)]
+enum Boundary {
+    /// The grid wraps around its borders
+    Torus = 0,
+    /// The cells beyond the borders are dead
+    Dead = 1,
+    /// The cells beyond the borders are copies of the cells along the borders
+    Mirror = 2,
+}
+
+impl Boundary {
+    fn from_name(name: &str) -> Self {
+        match name {
+            "torus" => Self::Torus,
+            "dead" => Self::Dead,
+            "mirror" => Self::Mirror,
+            _ => panic!("Unknown boundary: {name} (expected torus, dead or mirror)"),
+        }
+    }
+}
+
+/// Parameters of the automaton, in a uniform buffer
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct Params {
+    rule: Rule,
+    /// The `Boundary` mode
+    boundary: u32,
+    _padding: u32,
+}
+
+impl Params {
+    /// (6) Put the parameters into a uniform buffer
+    fn create_uniform_buffer(&self, device: &wgpu::Device) -> wgpu::Buffer {
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Params Buffer"),
+            contents: bytemuck::bytes_of(self),
+            usage: wgpu::BufferUsages::UNIFORM,
+        })
+    }
+}
+
+/// (3) Pattern of cells loaded from a file, e.g. a glider or a glider gun
+struct Pattern {
+    width: u32,
+    height: u32,
+    /// Coordinates [x, y] of the alive cells, from the top left corner of the pattern
+    alive_cells: Vec<[u32; 2]>,
+}
+
+impl Pattern {
+    /// Load a pattern from a Golly RLE (".rle") or plaintext (".cells") file
+    fn load(path: &str) -> Self {
+        let text = std::fs::read_to_string(path).unwrap();
+        match std::path::Path::new(path)
+            .extension()
+            .and_then(|extension| extension.to_str())
+        {
+            Some("rle") => Self::parse_rle(&text),
+            Some("cells") => Self::parse_plaintext(&text),
+            _ => panic!("Unknown pattern format: {path}"),
+        }
+    }
+
+    /// Parse the plaintext format: one line per row, with '.' for dead cells and 'O' for alive cells.
+    /// The lines starting with '!' are comments.
+    fn parse_plaintext(text: &str) -> Self {
+        let rows: Vec<&str> = text
+            .lines()
+            .map(|line| line.trim_end())
+            .filter(|line| !line.starts_with('!'))
+            .collect();
+        let alive_cells = rows
             .iter()
-            .map(|&x| if x > 0 { 0u8 } else { 1u8 })
+            .enumerate()
+            .flat_map(|(y, row)| {
+                row.chars()
+                    .enumerate()
+                    .filter_map(move |(x, cell)| match cell {
+                        '.' => None,
+                        'O' | '*' => Some([x as u32, y as u32]),
+                        _ => panic!("Invalid plaintext cell: {cell}"),
+                    })
+            })
             .collect();
+        Self {
+            width: rows.iter().map(|row| row.len()).max().unwrap_or(0) as u32,
+            height: rows.len() as u32,
+            alive_cells,
+        }
+    }
 
-        let mut frame = gif::Frame::default();
-        frame.delay = 2;
-        frame.width = width as u16;
-        frame.height = height as u16;
-        frame.buffer = Cow::Borrowed(&*pixels);
-        gif_enc.write_frame(&frame).unwrap();
+    /// Parse the run length encoded (RLE) format.
+    /// After the comments (starting with '#'), a header gives the size, e.g. "x = 3, y = 3".
+    /// Then the rows are encoded as runs of cells, each optionally preceded by its length:
+    /// 'b' for dead cells, 'o' for alive cells, '$' for the end of a row, and '!' for the end of the pattern.
+    fn parse_rle(text: &str) -> Self {
+        let mut lines = text.lines().filter(|line| !line.starts_with('#'));
+        let header = lines.next().expect("Missing RLE header");
+        let size = |name: &str| -> u32 {
+            header
+                .split(',')
+                .filter_map(|field| field.split_once('='))
+                .find(|(key, _)| key.trim() == name)
+                .unwrap_or_else(|| panic!("Invalid RLE header: {header}"))
+                .1
+                .trim()
+                .parse()
+                .unwrap()
+        };
+        let (width, height) = (size("x"), size("y"));
 
-        // (8.6) unmap the buffer to allow subsequent GPU writes to it
-        drop(data); // CPU pointer must not survive unmap (Rust memory safety guarantee)
-        staging_buffer.unmap();
+        let mut alive_cells = vec![];
+        let (mut x, mut y) = (0, 0);
+        let mut run_length = String::new();
+        for tag in lines.flat_map(|line| line.chars()) {
+            if tag.is_ascii_digit() {
+                run_length.push(tag);
+                continue;
+            }
+            let count = if run_length.is_empty() {
+                1
+            } else {
+                run_length.parse().unwrap()
+            };
+            run_length.clear();
+            match tag {
+                'b' | '.' => x += count,
+                '$' => {
+                    x = 0;
+                    y += count;
+                }
+                '!' => break,
+                // Whitespace is allowed between the runs
+                _ if tag.is_whitespace() => {}
+                // Any other state of a multi-state pattern is alive
+                _ if tag.is_ascii_alphabetic() => {
+                    alive_cells.extend((x..x + count).map(|x| [x, y]));
+                    x += count;
+                }
+                _ => panic!("Invalid RLE tag: {tag}"),
+            }
+        }
+        Self {
+            width,
+            height,
+            alive_cells,
+        }
     }
-    bar.finish();
 
-    println!("Terminating the program ...")
+    /// Place the pattern into an empty grid, at the given offset of its top left corner,
+    /// or centered by default. The pattern wraps around the borders of the grid.
+    fn place(&self, width: u32, height: u32, offset: Option<[u32; 2]>) -> Vec<u32> {
+        assert!(
+            self.width <= width && self.height <= height,
+            "The pattern ({}x{}) does not fit in the grid ({width}x{height})",
+            self.width,
+            self.height
+        );
+        let [offset_x, offset_y] =
+            offset.unwrap_or([(width - self.width) / 2, (height - self.height) / 2]);
+        let mut cells = vec![0; (width * height) as usize];
+        for &[x, y] in &self.alive_cells {
+            let (x, y) = ((x + offset_x) % width, (y + offset_y) % height);
+            cells[(y * width + x) as usize] = 1;
+        }
+        cells
+    }
 }
 
 /// (1) Initializing WebGPU
-async fn init_wgpu_device() -> Result<(wgpu::Device, wgpu::Queue), wgpu::RequestDeviceError> {
+async fn init_wgpu_device(
+    force_fallback_adapter: bool,
+) -> Result<(wgpu::Device, wgpu::Queue), wgpu::RequestDeviceError> {
     // Start an "Instance", which is the context for all things wgpu.
     let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
         backends: wgpu::Backends::all(),
@@ -213,7 +519,7 @@ async fn init_wgpu_device() -> Result<(wgpu::Device, wgpu::Queue), wgpu::Request
         .request_adapter(&wgpu::RequestAdapterOptions {
             power_preference: wgpu::PowerPreference::default(), // None | LowPower | HighPerformance
             compatible_surface: None,
-            force_fallback_adapter: false, // If needed to force CPU fallback?
+            force_fallback_adapter, // If needed to force CPU fallback (as in the tests)
         })
         .await
         .unwrap();
@@ -249,12 +555,52 @@ fn init_grid_texture(device: &wgpu::Device, width: u32, height: u32) -> wgpu::Te
     device.create_texture(&desc)
 }
 
+/// (3) Generate random cells (0 or 1), each one being alive with the probability `density`
+fn random_cells(width: u32, height: u32, seed: u64, density: f64) -> Vec<u32> {
+    // ChaCha8 is a seedable RNG whose output is guaranteed to stay the same
+    // across platforms and versions of the crate, contrary to StdRng
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    (0..width * height)
+        .map(|_| u32::from(rng.gen_bool(density)))
+        .collect()
+}
+
+/// (3) Upload the cells (0 or 1) of a grid into its texture, packing them if needed
+fn write_grid(
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+    cells: &[u32],
+    width: u32,
+    packed: bool,
+) {
+    let texels = if packed {
+        pack_cells(cells, width)
+    } else {
+        cells.to_vec()
+    };
+    queue.write_texture(
+        wgpu::ImageCopyTexture {
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        bytemuck::cast_slice(&texels),
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(4 * texture.width()), // texel size of r32uint texture is 4 bytes
+            rows_per_image: Some(texture.height()),
+        },
+        texture.size(),
+    );
+}
+
 /// (4) Initialize a staging buffer. Required because we can't retrieve the
 /// texture content directly when they're used as pipeline data.
 fn init_buffer(device: &wgpu::Device, width: u32, height: u32) -> wgpu::Buffer {
     let desc = wgpu::BufferDescriptor {
         label: None,
-        size: (width * height * 4).into(),
+        size: (padded_bytes_per_row(width) * height).into(),
         usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
         mapped_at_creation: false,
     };
@@ -262,6 +608,35 @@ fn init_buffer(device: &wgpu::Device, width: u32, height: u32) -> wgpu::Buffer {
     device.create_buffer(&desc)
 }
 
+/// (5) Initialize the shader module, with the given workgroup size (width and height).
+/// WGSL pipeline-overridable constants ("override") would let the pipeline set the workgroup size,
+/// but they are not supported by wgpu 0.19, so we replace the line declaring its constant in the source.
+/// Only that line is changed, the uses of the constant (or a comment quoting it) are left as is.
+fn create_shader_module(device: &wgpu::Device, workgroup_size: u32) -> wgpu::ShaderModule {
+    const DECLARATION: &str = "const WORKGROUP_SIZE: u32 = 16u;";
+    let source = include_str!("game_of_life.wgsl");
+    let mut declarations = 0;
+    let source: String = source
+        .split_inclusive('\n')
+        .map(|line| {
+            if line.trim_end() == DECLARATION {
+                declarations += 1;
+                format!("const WORKGROUP_SIZE: u32 = {workgroup_size}u;\n")
+            } else {
+                line.to_string()
+            }
+        })
+        .collect();
+    assert_eq!(
+        declarations, 1,
+        "The shader must declare the workgroup size exactly once as `{DECLARATION}`"
+    );
+    device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Game of Life"),
+        source: wgpu::ShaderSource::Wgsl(source.into()),
+    })
+}
+
 /// (6) Initialize a compute pipeline. Note how it requires a lot less settings compared
 /// to a render pipeline (less fixed functionalities to configure)
 fn build_pipeline(
@@ -279,13 +654,45 @@ fn build_pipeline(
     device.create_compute_pipeline(&desc)
 }
 
-/// (8.1) Dispatch our compute shader into thread groups (aka workgroups), as many as required to cover the whole
+/// (7) Create two bind groups alternating the role of the 2 textures,
+/// the first one reading the texture 0 and writing the texture 1, the other one the opposite
+fn create_bind_groups(
+    device: &wgpu::Device,
+    pipeline: &wgpu::ComputePipeline,
+    grid_views: &[wgpu::TextureView; 2],
+    params_buffer: &wgpu::Buffer,
+) -> [wgpu::BindGroup; 2] {
+    let layout = pipeline.get_bind_group_layout(0);
+    [[0, 1], [1, 0]].map(|[input, output]| {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&grid_views[input]),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&grid_views[output]),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: params_buffer.as_entire_binding(),
+                },
+            ],
+        })
+    })
+}
+
+/// (9.2) Dispatch our compute shader into thread groups (aka workgroups), as many as required to cover the whole
 /// grid (thread per cell/texel)
 fn launch_kernel(
     encoder: &mut wgpu::CommandEncoder,
     pipeline: &wgpu::ComputePipeline,
     grids_bind_group: &wgpu::BindGroup,
     grid_size: &wgpu::Extent3d,
+    workgroup_size: u32,
 ) {
     // Setup a compute pass
     let desc = wgpu::ComputePassDescriptor {
@@ -294,35 +701,39 @@ fn launch_kernel(
     };
     let mut pass = encoder.begin_compute_pass(&desc);
 
-    pass.set_pipeline(&pipeline);
+    pass.set_pipeline(pipeline);
     pass.set_bind_group(0, grids_bind_group, &[]);
 
-    // Workgroups are arranged in a 16x16 grid (as declared in the shader code),
-    // so we need to launch (width/16)x(height/16) of them
-    pass.dispatch_workgroups(grid_size.width / 16, grid_size.height / 16, 1);
+    // Workgroups are arranged in a NxN grid (as declared in the shader code, 16x16 by default),
+    // so we need to launch (width/N)x(height/N) of them, rounded up to cover the whole grid
+    // (the shaders skip the threads beyond the grid)
+    pass.dispatch_workgroups(
+        grid_size.width.div_ceil(workgroup_size),
+        grid_size.height.div_ceil(workgroup_size),
+        1,
+    );
 }
 
-/// (8.2) Copy the texture output into a buffer
+/// (9.3) Copy the texture output into a buffer
 fn copy_texture_to_buffer(
     encoder: &mut wgpu::CommandEncoder,
     texture: &wgpu::Texture,
     output_buffer: &wgpu::Buffer,
 ) {
-    let texel_size = texture.format().block_copy_size(None).unwrap();
     encoder.copy_texture_to_buffer(
         // source
         wgpu::ImageCopyTexture {
             aspect: wgpu::TextureAspect::All,
-            texture: &texture,
+            texture,
             mip_level: 0,
             origin: wgpu::Origin3d::ZERO,
         },
         // destination
         wgpu::ImageCopyBuffer {
-            buffer: &output_buffer,
+            buffer: output_buffer,
             layout: wgpu::ImageDataLayout {
                 offset: 0,
-                bytes_per_row: Some(texel_size * texture.width()),
+                bytes_per_row: Some(padded_bytes_per_row(texture.width())),
                 rows_per_image: Some(texture.height()),
             },
         },
@@ -331,19 +742,373 @@ fn copy_texture_to_buffer(
     );
 }
 
-/// (8.4) Retrieve the buffer data from the GPU
-async fn readback_buffer_data<'a>(
-    device: &wgpu::Device,
+/// A staging buffer being read back: its mapping was requested after the submission
+/// copying an iteration into it, and the callback reports the mapping result through a channel.
+struct Readback<'a> {
     buffer: &'a wgpu::Buffer,
-) -> wgpu::BufferView<'a> {
-    let buffer_slice = buffer.slice(..);
+    submission_index: wgpu::SubmissionIndex,
+    mapped: mpsc::Receiver<Result<(), wgpu::BufferAsyncError>>,
+}
 
-    // request the buffer to be mapped (made visible) to CPU memory
-    buffer_slice.map_async(wgpu::MapMode::Read, |_| {});
+/// (9.5) Request the staging buffer to be mapped (made visible) to CPU memory.
+/// This does not block: the callback is called by a later `device.poll()`,
+/// once the GPU has executed the copy into the buffer.
+fn request_readback(
+    buffer: &wgpu::Buffer,
+    submission_index: wgpu::SubmissionIndex,
+) -> Readback<'_> {
+    let (sender, mapped) = mpsc::channel();
+    buffer
+        .slice(..)
+        .map_async(wgpu::MapMode::Read, move |result| {
+            sender.send(result).unwrap();
+        });
+    Readback {
+        buffer,
+        submission_index,
+        mapped,
+    }
+}
 
-    // block on the device until the mapping callback above is called (indicating completion)
-    device.poll(wgpu::Maintain::Wait);
+/// (10) Retrieve the buffer data of a readback, and send it as a gif frame.
+/// Returns the time spent blocked until the buffer was mapped.
+fn finish_readback(
+    device: &wgpu::Device,
+    readback: Readback,
+    width: u32,
+    packed: bool,
+    frame_sender: &mpsc::Sender<Vec<u8>>,
+) -> std::time::Duration {
+    // (10.1) If the mapping callback was not already called during a previous poll,
+    // block on the device until the submission of this readback is done,
+    // rather than until all the submissions are done (as Maintain::Wait would).
+    let start = std::time::Instant::now();
+    let mapped = match readback.mapped.try_recv() {
+        Ok(mapped) => mapped,
+        Err(_) => {
+            device.poll(wgpu::Maintain::WaitForSubmissionIndex(
+                readback.submission_index,
+            ));
+            readback.mapped.recv().unwrap()
+        }
+    };
+    mapped.unwrap();
+    let waiting = start.elapsed();
 
-    // it's now safe to retrieve a pointer to the mapped memory
-    buffer_slice.get_mapped_range()
+    // (10.2) Convert the returned data, the frame of the result gif
+    // u32 texel -> u8 \in {0, 1} (gif is a paletted format)
+    let data = readback.buffer.slice(..).get_mapped_range();
+    let cells = read_cells(&data, width, packed);
+    let pixels: Vec<u8> = cells
+        .iter()
+        .map(|&x| if x > 0 { 0u8 } else { 1u8 })
+        .collect();
+    frame_sender.send(pixels).unwrap();
+
+    // (10.3) unmap the buffer to allow subsequent GPU writes to it
+    drop(data); // CPU pointer must not survive unmap (Rust memory safety guarantee)
+    readback.buffer.unmap();
+    waiting
+}
+
+/// Size in bytes of a row of a grid texture in a buffer.
+/// Texture to buffer copies require rows aligned to 256 bytes, which the packed grids are not.
+fn padded_bytes_per_row(grid_width: u32) -> u32 {
+    let bytes_per_row = 4 * grid_width; // texel size of r32uint texture is 4 bytes
+    bytes_per_row.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+}
+
+/// (3) Pack the cells (0 or 1) of a grid, 32 per u32:
+/// the bit k of the word x of a row is the cell 32x + k of that row.
+/// The width must be a multiple of 32.
+fn pack_cells(cells: &[u32], width: u32) -> Vec<u32> {
+    assert_eq!(
+        width % 32,
+        0,
+        "The packed grid width must be a multiple of 32"
+    );
+    cells
+        .chunks(32)
+        .map(|chunk| {
+            chunk
+                .iter()
+                .enumerate()
+                .fold(0, |word, (k, &cell)| word | (u32::from(cell > 0) << k))
+        })
+        .collect()
+}
+
+/// (10.2) Unpack the cells of a grid packed by `pack_cells()`, as 0 or 1
+fn unpack_cells(words: &[u32]) -> Vec<u32> {
+    words
+        .iter()
+        .flat_map(|&word| (0..32).map(move |k| (word >> k) & 1))
+        .collect()
+}
+
+/// (10.2) Retrieve the cells of a grid from a staging buffer, removing the row padding
+fn read_cells(data: &[u8], width: u32, packed: bool) -> Vec<u32> {
+    let grid_width = if packed { width / 32 } else { width };
+    let rows = data
+        .chunks(padded_bytes_per_row(grid_width) as usize)
+        .map(|row| &bytemuck::cast_slice::<u8, u32>(row)[..grid_width as usize]);
+    if packed {
+        rows.flat_map(unpack_cells).collect()
+    } else {
+        rows.flatten().copied().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// CPU reference of a generation of the automaton, with the same boundary modes as the shaders
+    fn step_cpu(cells: &[u32], width: u32, height: u32, params: &Params) -> Vec<u32> {
+        let (width, height) = (width as i32, height as i32);
+        let cell = |x: i32, y: i32| {
+            let inside = (0..width).contains(&x) && (0..height).contains(&y);
+            let (x, y) = match params.boundary {
+                b if b == Boundary::Dead as u32 && !inside => return 0,
+                b if b == Boundary::Mirror as u32 => {
+                    (x.clamp(0, width - 1), y.clamp(0, height - 1))
+                }
+                _ => (x.rem_euclid(width), y.rem_euclid(height)),
+            };
+            cells[(y * width + x) as usize]
+        };
+        let mut next = Vec::with_capacity(cells.len());
+        for y in 0..height {
+            for x in 0..width {
+                let mut num_alive = 0;
+                for (dx, dy) in [
+                    (-1, -1),
+                    (0, -1),
+                    (1, -1),
+                    (-1, 0),
+                    (1, 0),
+                    (-1, 1),
+                    (0, 1),
+                    (1, 1),
+                ] {
+                    num_alive += cell(x + dx, y + dy);
+                }
+                let mask = if cell(x, y) > 0 {
+                    params.rule.survive
+                } else {
+                    params.rule.birth
+                };
+                next.push((mask >> num_alive) & 1);
+            }
+        }
+        next
+    }
+
+    /// Compute generations of the automaton on the fallback adapter, with the given entry point
+    fn step_gpu(
+        entry_point: &str,
+        cells: &[u32],
+        width: u32,
+        height: u32,
+        params: &Params,
+        workgroup_size: u32,
+        generations: usize,
+    ) -> Vec<u32> {
+        let (device, queue) = pollster::block_on(init_wgpu_device(true)).unwrap();
+        let packed = entry_point == "step_packed";
+        let grid_width = if packed { width / 32 } else { width };
+        let grids = [
+            init_grid_texture(&device, grid_width, height),
+            init_grid_texture(&device, grid_width, height),
+        ];
+        let grid_views = grids
+            .each_ref()
+            .map(|grid| grid.create_view(&Default::default()));
+        write_grid(&queue, &grids[0], cells, width, packed);
+
+        let shader_module = create_shader_module(&device, workgroup_size);
+        let pipeline = build_pipeline(&device, &shader_module, entry_point);
+        let params_buffer = params.create_uniform_buffer(&device);
+        let bind_groups = create_bind_groups(&device, &pipeline, &grid_views, &params_buffer);
+
+        // All the generations in a single submission, one compute pass each
+        let mut encoder = device.create_command_encoder(&Default::default());
+        for i in 0..generations {
+            launch_kernel(
+                &mut encoder,
+                &pipeline,
+                &bind_groups[i % 2],
+                &grids[0].size(),
+                workgroup_size,
+            );
+        }
+        let buffer = init_buffer(&device, grid_width, height);
+        copy_texture_to_buffer(&mut encoder, &grids[generations % 2], &buffer);
+        queue.submit(Some(encoder.finish()));
+
+        let buffer_slice = buffer.slice(..);
+        buffer_slice.map_async(wgpu::MapMode::Read, |result| result.unwrap());
+        device.poll(wgpu::Maintain::Wait);
+        let data = buffer_slice.get_mapped_range();
+        read_cells(&data, width, packed)
+    }
+
+    /// Conway's game of life with the given boundary mode
+    fn conway_params(boundary: Boundary) -> Params {
+        Params {
+            rule: Rule::parse("B3/S23"),
+            boundary: boundary as u32,
+            _padding: 0,
+        }
+    }
+
+    /// Check that an entry point computes the same generations as the CPU reference,
+    /// from random cells covering the borders of the grid, with every boundary mode.
+    /// The sizes include grids whose size is not a power of two, nor a multiple of the workgroup size
+    /// (except the widths of the packed grids, which are multiples of 32).
+    fn check_entry_point(entry_point: &str) {
+        let packed = entry_point == "step_packed";
+        let sizes = [(64, 64), (96, 40), (40, 24)];
+        for (width, height) in sizes
+            .into_iter()
+            .filter(|(width, _)| !packed || width % 32 == 0)
+        {
+            for boundary in [Boundary::Torus, Boundary::Dead, Boundary::Mirror] {
+                for workgroup_size in [16, 6] {
+                    let params = conway_params(boundary);
+                    let cells = random_cells(width, height, 0, 0.3);
+
+                    let generations = 50;
+                    let expected = (0..generations).fold(cells.clone(), |cells, _| {
+                        step_cpu(&cells, width, height, &params)
+                    });
+                    let result = step_gpu(
+                        entry_point,
+                        &cells,
+                        width,
+                        height,
+                        &params,
+                        workgroup_size,
+                        generations,
+                    );
+                    let mismatches: Vec<(u32, u32)> = (0..width * height)
+                        .filter(|&i| result[i as usize] != expected[i as usize])
+                        .map(|i| (i % width, i / width))
+                        .collect();
+                    assert!(
+                        mismatches.is_empty(),
+                        "{} cells differ from the CPU after {generations} generations \
+                        ({width}x{height}, {boundary:?}, workgroups of {workgroup_size}x{workgroup_size}), \
+                        e.g. (x, y) = {:?}",
+                        mismatches.len(),
+                        &mismatches[..mismatches.len().min(8)]
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn parse_rules() {
+        let masks = |rule: Rule| (rule.birth, rule.survive);
+        // Conway's game of life
+        assert_eq!(masks(Rule::parse("B3/S23")), (0b1000, 0b1100));
+        // HighLife
+        assert_eq!(masks(Rule::parse("B36/S23")), (0b100_1000, 0b1100));
+        // Case insensitive
+        assert_eq!(masks(Rule::parse("b36/s23")), (0b100_1000, 0b1100));
+        // From 0 to 8 neighbors, or none at all
+        assert_eq!(masks(Rule::parse("B0/S8")), (0b1, 0b1_0000_0000));
+        assert_eq!(masks(Rule::parse("B/S")), (0, 0));
+    }
+
+    #[test]
+    fn parse_invalid_rules() {
+        for rule in ["", "B3S23", "S23/B3", "3/23", "B9/S23", "B3/S2a", "B3/S23/"] {
+            let result = std::panic::catch_unwind(|| Rule::parse(rule));
+            assert!(result.is_err(), "{rule:?} should be rejected");
+        }
+    }
+
+    #[test]
+    fn random_cells_depend_only_on_the_seed() {
+        let (width, height, density) = (64, 48, 0.2);
+        let cells = random_cells(width, height, 42, density);
+        assert_eq!(cells.len(), (width * height) as usize);
+        assert!(cells.iter().all(|&cell| cell <= 1));
+        assert_eq!(cells, random_cells(width, height, 42, density));
+        assert_ne!(cells, random_cells(width, height, 43, density));
+
+        // The density is the probability of each cell to be alive
+        let alive = cells.iter().sum::<u32>() as f64 / cells.len() as f64;
+        assert!((alive - density).abs() < 0.05, "{alive} alive cells");
+    }
+
+    #[test]
+    fn parse_rle_glider_and_blinker() {
+        // Comments, then the header with the size (and the rule, ignored here)
+        let glider = Pattern::parse_rle(
+            "#N Glider\n#C A comment\nx = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n",
+        );
+        assert_eq!((glider.width, glider.height), (3, 3));
+        assert_eq!(glider.alive_cells, [[1, 0], [2, 1], [0, 2], [1, 2], [2, 2]]);
+        // Same cells as the plaintext glider
+        let plaintext = Pattern::load("glider.cells");
+        assert_eq!(glider.alive_cells, plaintext.alive_cells);
+
+        let blinker = Pattern::parse_rle("x = 3, y = 1\n3o!");
+        assert_eq!((blinker.width, blinker.height), (3, 1));
+        assert_eq!(blinker.alive_cells, [[0, 0], [1, 0], [2, 0]]);
+    }
+
+    #[test]
+    fn parse_rle_runs() {
+        // Multi-digit runs, and a run of 2 rows ending the first one and skipping the second one
+        let pattern = Pattern::parse_rle("x = 14, y = 3\n12b2o2$o!");
+        assert_eq!(pattern.alive_cells, [[12, 0], [13, 0], [0, 2]]);
+
+        // Runs split over several lines, with whitespace in between
+        let pattern = Pattern::parse_rle("x = 4, y = 2\n2b\n2o $\n3o!");
+        assert_eq!(
+            pattern.alive_cells,
+            [[2, 0], [3, 0], [0, 1], [1, 1], [2, 1]]
+        );
+
+        // Nothing is read after the end of the pattern
+        let pattern = Pattern::parse_rle("x = 2, y = 2\no!\n$o\n");
+        assert_eq!(pattern.alive_cells, [[0, 0]]);
+    }
+
+    #[test]
+    fn load_glider_gun() {
+        let gun = Pattern::load("glider_gun.rle");
+        assert_eq!((gun.width, gun.height), (36, 9));
+        assert_eq!(gun.alive_cells.len(), 36);
+        assert!(gun.alive_cells.iter().all(|&[x, y]| x < 36 && y < 9));
+    }
+
+    #[test]
+    fn cpu_glider_moves_across_the_borders() {
+        let glider = Pattern::parse_plaintext(".O.\n..O\nOOO\n");
+        let params = conway_params(Boundary::Torus);
+        let (width, height) = (16, 16);
+        let cells = glider.place(width, height, Some([14, 14]));
+        let cells = (0..4).fold(cells, |cells, _| step_cpu(&cells, width, height, &params));
+        assert_eq!(cells, glider.place(width, height, Some([15, 15])));
+    }
+
+    #[test]
+    fn step_matches_cpu() {
+        check_entry_point("step");
+    }
+
+    #[test]
+    fn step_local_mem_matches_cpu() {
+        check_entry_point("step_local_mem");
+    }
+
+    #[test]
+    fn step_packed_matches_cpu() {
+        check_entry_point("step_packed");
+    }
 }
@@ -0,0 +1,24 @@
+// Readback of the depth texture array into a storage texture array, with a compute shader.
+//
+// Depth textures can only be copied into buffers on some devices (not on OpenGL, for instance).
+// But any depth format can be read by a shader, so we copy each layer into an R32Float
+// storage texture array, which can then be copied into a buffer like any color texture.
+//
+// The depth is declared as a regular float texture rather than a texture_depth_2d_array,
+// which is valid with an "unfilterable float" layout (see main.rs).
+
+@group(0) @binding(0) var depth_texture: texture_2d_array<f32>;
+@group(0) @binding(1) var depth_output: texture_storage_2d_array<r32float, write>;
+
+@compute
+@workgroup_size(8, 8)
+fn read_depth(@builtin(global_invocation_id) global_id: vec3u) {
+    // Skip the threads in excess, when the size is not a multiple of the workgroup size (8x8).
+    // There is one layer of workgroups per layer of the texture.
+    let pixel = global_id.xy;
+    let layer = global_id.z;
+    if any(pixel >= textureDimensions(depth_texture)) {
+        return;
+    }
+    textureStore(depth_output, pixel, layer, textureLoad(depth_texture, pixel, layer, 0));
+}
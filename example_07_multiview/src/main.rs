@@ -0,0 +1,779 @@
+//! This example shows how to render multiple views of the same mesh in a single submission.
+//!
+//! We reuse the bunny OBJ and perspective camera from the previous examples,
+//! except this time each view (a different rotation of the bunny) is rendered
+//! into one layer of 2D texture arrays, for both the color and the depth targets.
+//! When the device supports the multiview feature, all views are rendered in a single render pass,
+//! the vertex shader being invoked once per view with a "view_index" builtin input.
+//! Otherwise, we fall back to one render pass per layer, each with its own view index uniform.
+//! In both cases, all the layers are copied into a single buffer and read back at once.
+//! The number of views is set with `--views N` (4 by default, up to `MAX_VIEWS`).
+//! The steps of this minimal program are the following.
+//!
+//! 1. (async) Initialize the connection with the GPU device,
+//!    **(new)** requesting the multiview feature if the adapter supports it
+//! 2. **(new)** Initialize a wgpu Texture array that will serve as a write target for fragment shader
+//!    (one layer per view)
+//! 3. Initialize a wgpu Buffer where all the Texture layers will be transferred to
+//! 4. **(new)** Initialize a wgpu Texture array that will serve as a write target for the depth
+//!    and a wgpu Buffer where all its layers will be transferred to.
+//!    Depth textures cannot be copied into buffers on every device (e.g. on OpenGL),
+//!    so the depth layers are first copied into an R32Float storage texture array by a compute pass.
+//! 5. Load the OBJ bunny
+//!    1. Create and initialize a vertex buffer containing the triangle coordinates
+//!    2. Create and initialize an index buffer containing the vertex indices in the face
+//! 6. Load the shader module, containing both the vertex and fragment shaders.
+//!    **(new)** The vertex entry point differs depending on the multiview support.
+//! 7. Define our render pipeline, including:
+//!    - the vertex shader: include our vertex buffer layout
+//!    - the fragment shader
+//!    - the primitive type (triangle list)
+//!    - the depth_stencil is configured to compare depths on fragments
+//!      and only keep it when it's closer ("Less").
+//!      Also specifies to store that final depth into our depth texture
+//!    - **(new)** the number of views, when rendering with multiview
+//! 8. Create the camera
+//!    1. Put the perspective projection camera parameters into a uniform buffer
+//!    2. **(new)** Put the parameters of each view into a uniform buffer
+//!    3. Create a bind group and let WebGPU derive the layout implicitely
+//!    4. **(new)** Without multiview, create one bind group per layer containing the view index
+//! 9. Define our command encoder:
+//!    1. Start by defining our render pass(es):
+//!       - **(new)** Link to all the texture layers with multiview, or one layer per render pass otherwise
+//!       - Link to the pipeline
+//!       - Provide the camera bind group(s)
+//!       - Provide vertex buffer and index buffer
+//!       - Draw the primitive
+//!    2. **(new)** Copy the depth layers into their storage texture array with the readback compute pass
+//!    3. Add a command to copy all the layers of the fragment and depth textures
+//!       into their respective buffers
+//! 10. Submit our commands to the device queue
+//! 11. (async) Transfer the output buffers into images we can save to disk, one per layer
+
+use std::num::NonZeroU32;
+use wgpu::util::DeviceExt; // Utility trait to create and initialize buffers with device.create_buffer_init()
+
+/// Maximum number of views (must be the same as MAX_VIEWS in the shader)
+const MAX_VIEWS: usize = 16;
+
+fn main() {
+    // Make the main async
+    pollster::block_on(run());
+}
+
+async fn run() {
+    // Number of views to render (--views N, 4 by default)
+    let args: Vec<String> = std::env::args().collect();
+    let arg_value = |name: &str| {
+        let position = args.iter().position(|arg| arg == name)?;
+        Some(
+            args.get(position + 1)
+                .expect("Missing argument value")
+                .as_str(),
+        )
+    };
+    let num_views: usize = arg_value("--views").map_or(4, |value| value.parse().unwrap());
+    assert!(
+        (1..=MAX_VIEWS).contains(&num_views),
+        "The number of views must be between 1 and {MAX_VIEWS}, got {num_views}"
+    );
+
+    // (1) Initializing WebGPU
+    println!("Initializing WebGPU ...");
+    let (device, queue) = init_wgpu_device().await.unwrap();
+    let multiview = device.features().contains(wgpu::Features::MULTIVIEW);
+    if multiview {
+        println!("Multiview is supported, rendering all views in a single render pass");
+    } else {
+        println!("Multiview is not supported, rendering one view per render pass");
+    }
+
+    // Views to render, evenly rotating the bunny around its vertical axis
+    let views: Vec<ViewParams> = (0..num_views)
+        .map(|i| ViewParams::new(2.0 * std::f32::consts::PI * i as f32 / num_views as f32))
+        .collect();
+
+    // (2) Initialize the output texture array
+    let width = 256;
+    let height = 256;
+    let texture = init_output_texture(&device, width, height, num_views as u32);
+
+    // (3) Initialize a buffer for the texture output (all layers)
+    let output_buffer_desc = create_texture_buffer_descriptor(&texture);
+    let output_buffer = device.create_buffer(&output_buffer_desc);
+
+    // (4) Initialize the depth texture array, and a buffer for all its layers
+    let depth_texture = init_depth_texture(&device, width, height, num_views as u32);
+    let depth_readback = DepthReadback::new(&device, &depth_texture);
+    let depth_buffer_desc = create_texture_buffer_descriptor(&depth_readback.depth_output);
+    let depth_buffer = device.create_buffer(&depth_buffer_desc);
+
+    // (5) Load the OBJ bunny
+    let (models, _) = tobj::load_obj("bunny.obj", &tobj::GPU_LOAD_OPTIONS).unwrap();
+    let bunny = &models[0].mesh;
+
+    // (5.1) Create and initialize the vertex buffer for the vertices in the bunny mesh
+    // (needs the DeviceExt trait)
+    let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertex Buffer"),
+        contents: bytemuck::cast_slice(&bunny.positions),
+        usage: wgpu::BufferUsages::VERTEX,
+    });
+
+    // (5.2) Create and initialize the index buffer for the indices of the vertices in the bunny mesh
+    // (needs the DeviceExt trait)
+    let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Index Buffer"),
+        contents: bytemuck::cast_slice(&bunny.indices),
+        usage: wgpu::BufferUsages::INDEX,
+    });
+
+    // (6) Load the shader module, containing both the vertex and fragment shaders.
+    // The view_index builtin used by the multiview entry point requires the MULTIVIEW feature
+    // for the whole shader module to be valid, so we only append it if it is supported.
+    let vertex_entry = if multiview {
+        include_str!("vertex_multiview.wgsl")
+    } else {
+        include_str!("vertex_layer.wgsl")
+    };
+    let shader_source = format!("{}{}", include_str!("multiview.wgsl"), vertex_entry);
+    let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("multiview_shader"),
+        source: wgpu::ShaderSource::Wgsl(shader_source.into()),
+    });
+
+    // (7) Define our pipeline
+    let pipeline = build_simple_pipeline(
+        &device,
+        &shader_module,
+        texture.format(),
+        vtx_buffer_layout(),
+        // With multiview, the pipeline needs to know the number of array layers it renders to
+        if multiview {
+            NonZeroU32::new(num_views as u32)
+        } else {
+            None
+        },
+    );
+
+    // (8.1) Put the perspective projection camera into a uniform buffer
+    let camera = Camera {
+        focal_length: 5.0,
+        aspect_ratio: (width as f32) / (height as f32),
+        near_plane: 0.35,
+        far_plane: 0.65,
+    };
+    let camera_buffer = camera.create_uniform_buffer(&device);
+
+    // (8.2) Put the views parameters into a uniform buffer.
+    // Uniform arrays have a fixed size in the shader, so we fill it up to MAX_VIEWS.
+    let mut views_data = [ViewParams::new(0.0); MAX_VIEWS];
+    views_data[..num_views].copy_from_slice(&views);
+    let views_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Views Buffer"),
+        contents: bytemuck::cast_slice(&views_data),
+        usage: wgpu::BufferUsages::UNIFORM,
+    });
+
+    // (8.3) For the bind group layout, we let WebGPU derive it implicitely.
+    // We just tell it that it's the first one at index 0.
+    let camera_bind_group = Camera::create_bind_group(
+        &device,
+        &camera_buffer,
+        &views_buffer,
+        &pipeline.get_bind_group_layout(0),
+    );
+
+    // (8.4) Without multiview, each render pass gets its view index from its own bind group
+    let layer_bind_groups: Vec<wgpu::BindGroup> = if multiview {
+        Vec::new()
+    } else {
+        (0..num_views as u32)
+            .map(|layer| {
+                create_layer_bind_group(&device, layer, &pipeline.get_bind_group_layout(1))
+            })
+            .collect()
+    };
+
+    // Initialize a command encoder
+    let mut encoder = device.create_command_encoder(&Default::default());
+
+    // (9.1) Draw our pipeline (add render pass(es) to the command encoder)
+    let mesh = Mesh {
+        vertex_buffer: &vertex_buffer,
+        index_buffer: &index_buffer,
+        num_indices: bunny.indices.len() as u32,
+    };
+    if multiview {
+        // A single render pass, targetting all the layers of the textures at once
+        let texture_view = create_layers_view(&texture, 0, num_views as u32);
+        let depth_texture_view = create_layers_view(&depth_texture, 0, num_views as u32);
+        draw_pipeline(
+            &mut encoder,
+            &pipeline,
+            &texture_view,
+            &depth_texture_view,
+            &[&camera_bind_group],
+            &mesh,
+        );
+    } else {
+        // One render pass per layer of the textures
+        for (layer, layer_bind_group) in layer_bind_groups.iter().enumerate() {
+            let texture_view = create_layers_view(&texture, layer as u32, 1);
+            let depth_texture_view = create_layers_view(&depth_texture, layer as u32, 1);
+            draw_pipeline(
+                &mut encoder,
+                &pipeline,
+                &texture_view,
+                &depth_texture_view,
+                &[&camera_bind_group, layer_bind_group],
+                &mesh,
+            );
+        }
+    }
+
+    // (9.2) Copy the depth layers into a storage texture array, which can be copied into a buffer
+    depth_readback.compute(&mut encoder);
+
+    // (9.3) Add commands to copy all the texture layers into their respective buffers
+    copy_texture_to_buffer(&mut encoder, &texture, &output_buffer);
+    copy_texture_to_buffer(&mut encoder, &depth_readback.depth_output, &depth_buffer);
+
+    // (10) Finalize the command encoder and send it to the queue
+    println!("Submitting commands to the queue ...");
+    queue.submit(Some(encoder.finish()));
+
+    // (11) Transfer texture buffers into image buffers.
+    // New scope to encapsulate img_data BufferView and drop it before unmapping.
+    {
+        // Map both buffers at once, they contain all the layers one after the other
+        println!("Saving the GPU outputs into images ...");
+        let img_data = retrieve_texture_buffer_data(&device, &output_buffer).await;
+        let depth_data = retrieve_texture_buffer_data(&device, &depth_buffer).await;
+        let depth_data_f32: &[f32] = bytemuck::cast_slice(&depth_data);
+
+        let layer_size = (width * height) as usize;
+        for layer in 0..num_views {
+            let layer_data = &img_data[4 * layer * layer_size..4 * (layer + 1) * layer_size];
+            let img = image::RgbaImage::from_raw(width, height, Vec::from(layer_data)).unwrap();
+            img.save(format!("image_{layer}.png")).unwrap();
+
+            let img_data_u16: Vec<u16> = depth_data_f32
+                [layer * layer_size..(layer + 1) * layer_size]
+                .iter()
+                .map(|p| (p.clamp(0.0, 1.0) * 65535.0) as u16)
+                .collect();
+            let img_u16 =
+                image::ImageBuffer::<image::Luma<u16>, _>::from_raw(width, height, img_data_u16)
+                    .unwrap();
+            img_u16.save(format!("depth_{layer}.png")).unwrap();
+        }
+    }
+
+    // Flushes any pending write operations and unmaps the buffer from host memory
+    output_buffer.unmap();
+    depth_buffer.unmap();
+
+    println!("Terminating the program ...")
+}
+
+/// (1) Initializing WebGPU
+async fn init_wgpu_device() -> Result<(wgpu::Device, wgpu::Queue), wgpu::RequestDeviceError> {
+    // Start an "Instance", which is the context for all things wgpu.
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: wgpu::Backends::all(),
+        ..Default::default()
+    });
+
+    // An "Adapter" is a handle to a physical graphics/compute device.
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(), // None | LowPower | HighPerformance
+            compatible_surface: None,
+            force_fallback_adapter: false, // If needed to force CPU fallback?
+        })
+        .await
+        .unwrap();
+
+    // Request a connection to a physical device,
+    // and also access to the queue for its command buffers.
+    // We require the multiview feature, but only if the adapter supports it.
+    let device_desc = wgpu::DeviceDescriptor {
+        label: None,
+        required_features: adapter.features() & wgpu::Features::MULTIVIEW,
+        required_limits: Default::default(),
+    };
+    adapter.request_device(&device_desc, None).await
+}
+
+/// (2) Initialize the output texture array
+fn init_output_texture(
+    device: &wgpu::Device,
+    width: u32,
+    height: u32,
+    layers: u32,
+) -> wgpu::Texture {
+    let texture_desc = wgpu::TextureDescriptor {
+        label: Some("output_texture"),
+        // The texture size, with one layer per view
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: layers,
+        },
+        dimension: wgpu::TextureDimension::D2,
+        mip_level_count: 1, // the number of mip levels the texture will contain
+        sample_count: 1,    // sample_count > 1 would indicate a multisampled texture
+        // Use RGBA format for the output
+        format: wgpu::TextureFormat::Rgba8Unorm,
+        // RENDER_ATTACHMENT -> so that the GPU can render to the texture
+        // COPY_SRC -> so that we can pull data out of the texture
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        // Specify the allowed formats when calling "texture.create_view()"
+        view_formats: &[],
+    };
+    device.create_texture(&texture_desc)
+}
+
+/// (3 & 4) Create a buffer descriptor of the correct size for all the texture layers
+fn create_texture_buffer_descriptor(texture: &wgpu::Texture) -> wgpu::BufferDescriptor<'_> {
+    let texel_size = texture.format().block_copy_size(None).unwrap();
+    let layers = texture.depth_or_array_layers();
+    wgpu::BufferDescriptor {
+        size: (texel_size * texture.width() * texture.height() * layers).into(),
+        usage: wgpu::BufferUsages::COPY_DST
+            // this tells wpgu that we want to read this buffer from the cpu
+            | wgpu::BufferUsages::MAP_READ,
+        label: None,
+        mapped_at_creation: false,
+    }
+}
+
+/// (4) Initialize a depth texture array
+fn init_depth_texture(
+    device: &wgpu::Device,
+    width: u32,
+    height: u32,
+    layers: u32,
+) -> wgpu::Texture {
+    let texture_desc = wgpu::TextureDescriptor {
+        label: Some("depth_texture"),
+        // The texture size, with one layer per view
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: layers,
+        },
+        dimension: wgpu::TextureDimension::D2,
+        mip_level_count: 1, // the number of mip levels the texture will contain
+        sample_count: 1,    // sample_count > 1 would indicate a multisampled texture
+        // Use a 32 bits float format for the depth
+        format: wgpu::TextureFormat::Depth32Float,
+        // RENDER_ATTACHMENT -> so that the GPU can render to the texture
+        // TEXTURE_BINDING -> so that the readback compute pass can read the depth
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        // Specify the allowed formats when calling "texture.create_view()"
+        view_formats: &[],
+    };
+    device.create_texture(&texture_desc)
+}
+
+/// (4) Compute pass copying the depth texture array into an R32Float storage texture array,
+/// since depth textures cannot be copied into buffers on every device (e.g. on OpenGL)
+struct DepthReadback {
+    /// Copy of all the depth layers, which can be copied into a buffer
+    depth_output: wgpu::Texture,
+    pipeline: wgpu::ComputePipeline,
+    bind_group: wgpu::BindGroup,
+}
+
+impl DepthReadback {
+    fn new(device: &wgpu::Device, depth_texture: &wgpu::Texture) -> Self {
+        let depth_output = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("depth_output"),
+            size: depth_texture.size(),
+            dimension: wgpu::TextureDimension::D2,
+            mip_level_count: 1,
+            sample_count: 1,
+            format: wgpu::TextureFormat::R32Float,
+            // STORAGE_BINDING -> so that the compute pass can write the depth
+            // COPY_SRC -> so that we can pull data out of the texture
+            usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("depth_readback_shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("depth_readback.wgsl").into()),
+        });
+
+        // The automatic layout would expect a filterable float texture for the depth,
+        // which depth textures are not, so we define the layout ourselves.
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("depth_readback_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2Array,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::StorageTexture {
+                        access: wgpu::StorageTextureAccess::WriteOnly,
+                        format: wgpu::TextureFormat::R32Float,
+                        view_dimension: wgpu::TextureViewDimension::D2Array,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("depth_readback_pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("depth_readback_pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader_module,
+            entry_point: "read_depth",
+        });
+
+        // View all the layers as arrays, even when there is a single one
+        let array_view = |texture: &wgpu::Texture| {
+            texture.create_view(&wgpu::TextureViewDescriptor {
+                dimension: Some(wgpu::TextureViewDimension::D2Array),
+                ..Default::default()
+            })
+        };
+        let depth_texture_view = array_view(depth_texture);
+        let output_view = array_view(&depth_output);
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("depth_readback_bind_group"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&depth_texture_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&output_view),
+                },
+            ],
+        });
+
+        Self {
+            depth_output,
+            pipeline,
+            bind_group,
+        }
+    }
+
+    /// (9.2) Add the compute pass copying the depth layers into the storage texture array
+    fn compute(&self, encoder: &mut wgpu::CommandEncoder) {
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("depth_readback_pass"),
+            timestamp_writes: None,
+        });
+        // One thread per pixel, with workgroups of 8x8 threads, and one layer of workgroups per layer
+        let size = self.depth_output.size();
+        compute_pass.set_pipeline(&self.pipeline);
+        compute_pass.set_bind_group(0, &self.bind_group, &[]);
+        compute_pass.dispatch_workgroups(
+            size.width.div_ceil(8),
+            size.height.div_ceil(8),
+            size.depth_or_array_layers,
+        );
+    }
+}
+
+/// (9.1) Create a 2D array view of some layers of a texture,
+/// to be used as a render pass attachment
+fn create_layers_view(texture: &wgpu::Texture, base_layer: u32, layers: u32) -> wgpu::TextureView {
+    texture.create_view(&wgpu::TextureViewDescriptor {
+        // A single layer is viewed as a regular 2D texture
+        dimension: Some(if layers == 1 {
+            wgpu::TextureViewDimension::D2
+        } else {
+            wgpu::TextureViewDimension::D2Array
+        }),
+        base_array_layer: base_layer,
+        array_layer_count: Some(layers),
+        ..Default::default()
+    })
+}
+
+/// Define the layout of Vertex buffers
+fn vtx_buffer_layout() -> wgpu::VertexBufferLayout<'static> {
+    wgpu::VertexBufferLayout {
+        // array_stride is the bytes count between two vertices
+        array_stride: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &[
+            // position
+            wgpu::VertexAttribute {
+                offset: 0,
+                shader_location: 0,
+                format: wgpu::VertexFormat::Float32x3,
+            },
+        ],
+    }
+}
+
+/// Perspective camera
+/// Bytemuck is used to enable easy casting to a &[u8].
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct Camera {
+    focal_length: f32,
+    aspect_ratio: f32,
+    near_plane: f32,
+    far_plane: f32,
+}
+
+impl Camera {
+    fn create_uniform_buffer(&self, device: &wgpu::Device) -> wgpu::Buffer {
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera Buffer"),
+            contents: bytemuck::bytes_of(self),
+            usage: wgpu::BufferUsages::UNIFORM, // | wgpu::BufferUsages::COPY_DST,
+        })
+    }
+    fn create_bind_group(
+        device: &wgpu::Device,
+        buffer: &wgpu::Buffer,
+        views_buffer: &wgpu::Buffer,
+        layout: &wgpu::BindGroupLayout,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: views_buffer.as_entire_binding(),
+                },
+            ],
+            label: Some("camera_bind_group"),
+        })
+    }
+}
+
+/// Parameters of one view: the rotation of the bunny around its vertical axis.
+/// Elements of uniform arrays must be aligned to 16 bytes, hence the padding.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct ViewParams {
+    yaw: f32,
+    _padding: [f32; 3],
+}
+
+impl ViewParams {
+    fn new(yaw: f32) -> Self {
+        Self {
+            yaw,
+            _padding: [0.0; 3],
+        }
+    }
+}
+
+/// (8.4) Create a bind group containing the index of the layer (view) to render
+/// in a given render pass, when multiview is not available
+fn create_layer_bind_group(
+    device: &wgpu::Device,
+    layer: u32,
+    layout: &wgpu::BindGroupLayout,
+) -> wgpu::BindGroup {
+    let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Layer Index Buffer"),
+        contents: bytemuck::bytes_of(&layer),
+        usage: wgpu::BufferUsages::UNIFORM,
+    });
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: buffer.as_entire_binding(),
+        }],
+        label: Some("layer_bind_group"),
+    })
+}
+
+/// (7) Define our simple render pipeline
+fn build_simple_pipeline(
+    device: &wgpu::Device,
+    shader_module: &wgpu::ShaderModule,
+    texture_format: wgpu::TextureFormat,
+    vertex_buffer_layout: wgpu::VertexBufferLayout,
+    multiview: Option<NonZeroU32>,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Render Pipeline"),
+        layout: None, // "auto"
+        vertex: wgpu::VertexState {
+            module: shader_module,
+            entry_point: "vertex_main",
+            buffers: &[vertex_buffer_layout],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader_module,
+            entry_point: "fragment_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format: texture_format,
+                blend: Some(wgpu::BlendState {
+                    alpha: wgpu::BlendComponent::REPLACE,
+                    color: wgpu::BlendComponent::REPLACE,
+                }),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: Some(wgpu::Face::Front),
+            // Setting this to anything other than Fill requires Features::NON_FILL_POLYGON_MODE
+            polygon_mode: wgpu::PolygonMode::Fill,
+            // Requires Features::DEPTH_CLIP_CONTROL
+            unclipped_depth: false,
+            // Requires Features::CONSERVATIVE_RASTERIZATION
+            conservative: false,
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: wgpu::TextureFormat::Depth32Float,
+            // Enable WebGPU to write the depth (position.z) to the provided texture
+            depth_write_enabled: true,
+            // Keep the depth value closest to us (lower values)
+            depth_compare: wgpu::CompareFunction::Less,
+            // Not using stencil stuff
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        // If the pipeline will be used with a multiview render pass, this
+        // indicates how many array layers the attachments will have.
+        multiview,
+    })
+}
+
+/// The buffers of the mesh to draw
+struct Mesh<'a> {
+    vertex_buffer: &'a wgpu::Buffer,
+    index_buffer: &'a wgpu::Buffer,
+    num_indices: u32,
+}
+
+/// (9.1) Draw our pipeline (add a render pass to the command encoder).
+/// With multiview, the texture views contain all the layers, rendered in this single pass.
+fn draw_pipeline(
+    encoder: &mut wgpu::CommandEncoder,
+    pipeline: &wgpu::RenderPipeline,
+    texture_view: &wgpu::TextureView,
+    depth_texture_view: &wgpu::TextureView,
+    bind_groups: &[&wgpu::BindGroup],
+    mesh: &Mesh,
+) {
+    // Setup the pass that will render into our texture
+    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("Render Pass"),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: texture_view,   // the output texture
+            resolve_target: None, // only useful for multisampling
+            ops: wgpu::Operations {
+                // "load" specifies how data is read.
+                // "Clear" is the lightest way to initialize the texture.
+                load: wgpu::LoadOp::Clear(wgpu::Color {
+                    r: 0.1,
+                    g: 0.2,
+                    b: 0.3,
+                    a: 1.0,
+                }),
+                // whether data is written to or not. Store | Discard
+                store: wgpu::StoreOp::Store,
+            },
+        })],
+        depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+            view: depth_texture_view,
+            depth_ops: Some(wgpu::Operations {
+                load: wgpu::LoadOp::Clear(1.0),
+                store: wgpu::StoreOp::Store,
+            }),
+            stencil_ops: None,
+        }),
+        ..Default::default()
+    });
+
+    // Draw the render pass for our pipeline
+    render_pass.set_pipeline(pipeline);
+    for (index, bind_group) in bind_groups.iter().enumerate() {
+        render_pass.set_bind_group(index as u32, bind_group, &[]);
+    }
+    render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+    render_pass.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+    render_pass.draw_indexed(0..mesh.num_indices, 0, 0..1);
+}
+
+/// (9.2) Copy all the layers of a texture into a buffer, one after the other
+fn copy_texture_to_buffer(
+    encoder: &mut wgpu::CommandEncoder,
+    texture: &wgpu::Texture,
+    output_buffer: &wgpu::Buffer,
+) {
+    let texel_size = texture.format().block_copy_size(None).unwrap();
+    encoder.copy_texture_to_buffer(
+        // source
+        wgpu::ImageCopyTexture {
+            aspect: wgpu::TextureAspect::All,
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+        },
+        // destination
+        wgpu::ImageCopyBuffer {
+            buffer: output_buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(texel_size * texture.width()),
+                // the number of rows between two layers in the buffer
+                rows_per_image: Some(texture.height()),
+            },
+        },
+        // copy_size, including all the layers
+        texture.size(),
+    );
+}
+
+/// (11) Retrieve the texture buffer data from the GPU
+async fn retrieve_texture_buffer_data<'a>(
+    device: &wgpu::Device,
+    texture_buffer: &'a wgpu::Buffer,
+) -> wgpu::BufferView<'a> {
+    let buffer_slice = texture_buffer.slice(..);
+
+    // NOTE: We have to create the mapping THEN device.poll() before await the future.
+    // Otherwise the application will freeze.
+    let (tx, rx) = oneshot::channel();
+    buffer_slice.map_async(wgpu::MapMode::Read, move |result| {
+        tx.send(result).unwrap();
+    });
+    device.poll(wgpu::Maintain::Wait);
+    rx.await.unwrap().unwrap();
+
+    // Synchronously and immediately map a buffer for reading.
+    // Will panic if buffer_slice.map_async() did not finish yet.
+    buffer_slice.get_mapped_range()
+}
@@ -0,0 +1,77 @@
+// Vertex shader
+//
+// Same perspective camera projection as in the camera example,
+// except that the bunny is rotated around its vertical axis differently for each view,
+// which is equivalent to a camera orbiting around the bunny.
+//
+// This file only contains the parts that are shared by the two rendering strategies.
+// The actual vertex entry point is appended by the Rust program,
+// depending on whether the device supports multiview rendering or not:
+// - "vertex_multiview.wgsl" gets the view index from the @builtin(view_index) input
+// - "vertex_layer.wgsl" gets it from a uniform, bound differently for each render pass
+
+/// Perspective projection parameters
+struct ProjCamParams {
+    focal: f32,
+    ratio: f32,
+    near: f32,
+    far: f32,
+}
+
+/// Per-view parameters.
+/// Elements of uniform arrays must be aligned to 16 bytes, hence the explicit size.
+/// (a vec3 padding field would not work, since vec3 are themselves aligned to 16 bytes)
+struct ViewParams {
+    @size(16) yaw: f32,
+}
+
+// Must be the same as MAX_VIEWS in the Rust program
+const MAX_VIEWS: u32 = 16u;
+
+@group(0) @binding(0) var<uniform> proj_cam_params: ProjCamParams;
+@group(0) @binding(1) var<uniform> views: array<ViewParams, MAX_VIEWS>;
+
+fn project(position: vec3<f32>, view_index: u32) -> vec4<f32> {
+    let focal = proj_cam_params.focal;
+    let ratio = proj_cam_params.ratio;
+    let near = proj_cam_params.near;
+    let far = proj_cam_params.far;
+
+    // Perspective projection matrix (intrinsics), see the camera example for details
+    let proj_mat = transpose(mat4x4f(
+        focal, 0.0          , 0.0               , 0.0                       ,
+        0.0  , focal * ratio, 0.0               , 0.0                       ,
+        0.0  , 0.0          , far / (far - near), -far * near / (far - near),
+        0.0  , 0.0          , 1.0               , 0.0                       ,
+    ));
+
+    // Build the view matrix (extrinsics)
+    let view_mat = transpose(mat4x4f(
+        1.0, 0.0, 0.0, 0.0,
+        0.0, 1.0, 0.0, 0.0,
+        0.0, 0.0, 1.0, 0.0,
+        0.0, 0.0, 0.0, 1.0,
+    ));
+
+    // Build the model matrix, rotating the bunny around the vertical axis
+    // by the view yaw angle, then applying a small translation.
+    let c = cos(views[view_index].yaw);
+    let s = sin(views[view_index].yaw);
+    let model_mat = transpose(mat4x4f(
+        c  , 0.0, s  ,  0.0,
+        0.0, 1.0, 0.0, -0.1, // y - 0.1
+        -s , 0.0, c  ,  0.5, // z + 0.5
+        0.0, 0.0, 0.0,  1.0,
+    ));
+
+    let homogeneous_pos = vec4<f32>(position, 1.0);
+    return proj_mat * view_mat * model_mat * homogeneous_pos;
+}
+
+// Fragment shader
+// Output the normalized Z clip coordinate
+
+@fragment
+fn fragment_main(@builtin(position) clip_pos: vec4<f32>) -> @location(0) vec4<f32> {
+    return vec4<f32>(vec3<f32>(clip_pos.z), 1.0);
+}
@@ -0,0 +1,12 @@
+
+// Vertex entry point for the fallback, one render pass per view.
+//
+// The index of the current view is provided by a uniform,
+// with a different bind group set for each render pass (layer of the render targets).
+
+@group(1) @binding(0) var<uniform> layer_index: u32;
+
+@vertex
+fn vertex_main(@location(0) position: vec3<f32>) -> @builtin(position) vec4<f32> {
+    return project(position, layer_index);
+}
@@ -0,0 +1,15 @@
+
+// Vertex entry point for multiview rendering.
+//
+// The vertex shader is invoked once per view (layer of the render targets),
+// with the index of the current view provided by the view_index builtin.
+// This builtin requires the MULTIVIEW feature, even for the shader module to be valid,
+// which is why this entry point lives in its own file.
+
+@vertex
+fn vertex_main(
+    @location(0) position: vec3<f32>,
+    @builtin(view_index) view_index: i32,
+) -> @builtin(position) vec4<f32> {
+    return project(position, u32(view_index));
+}
@@ -0,0 +1,104 @@
+// Vertex shader
+//
+// Each vertex of the bunny mesh is provided together with the data of the current instance:
+// its model matrix (local to world coordinates) and its color.
+// The perspective projection is the same as in the camera example,
+// except that the camera is now placed above the ground, looking down at the bunnies.
+
+/// Perspective projection parameters, and camera placement
+struct CamParams {
+    focal: f32,
+    ratio: f32,
+    near: f32,
+    far: f32,
+    height: f32,
+    distance: f32,
+}
+
+@group(0) @binding(0) var<uniform> cam_params: CamParams;
+
+/// Per-vertex data (from the vertex buffer)
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+}
+
+/// Per-instance data (from the instance buffer).
+/// Vertex attributes cannot be matrices, so the model matrix is provided as its 4 columns.
+struct InstanceInput {
+    @location(1) model_col_0: vec4<f32>,
+    @location(2) model_col_1: vec4<f32>,
+    @location(3) model_col_2: vec4<f32>,
+    @location(4) model_col_3: vec4<f32>,
+    @location(5) color: vec3<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) world_position: vec3<f32>,
+    @location(1) color: vec3<f32>,
+}
+
+@vertex
+fn vertex_main(vertex: VertexInput, instance: InstanceInput) -> VertexOutput {
+    let focal = cam_params.focal;
+    let ratio = cam_params.ratio;
+    let near = cam_params.near;
+    let far = cam_params.far;
+
+    // Perspective projection matrix (intrinsics), see the camera example for details
+    let proj_mat = transpose(mat4x4f(
+        focal, 0.0          , 0.0               , 0.0                       ,
+        0.0  , focal * ratio, 0.0               , 0.0                       ,
+        0.0  , 0.0          , far / (far - near), -far * near / (far - near),
+        0.0  , 0.0          , 1.0               , 0.0                       ,
+    ));
+
+    // Build the view matrix (extrinsics).
+    // The camera is at (0, height, -distance), looking down at the world origin.
+    // So we translate the world by the opposite of the camera position,
+    // and rotate it around the X axis by the pitch angle of the camera.
+    let height = cam_params.height;
+    let distance = cam_params.distance;
+    let hypot = sqrt(height * height + distance * distance);
+    let c = distance / hypot;
+    let s = height / hypot;
+    let view_mat = transpose(mat4x4f(
+        1.0, 0.0, 0.0, 0.0,
+        0.0, c  , s  , -c * height + s * distance,
+        0.0, -s , c  , s * height + c * distance,
+        0.0, 0.0, 0.0, 1.0,
+    ));
+
+    // The model matrix comes from the instance buffer
+    let model_mat = mat4x4f(
+        instance.model_col_0,
+        instance.model_col_1,
+        instance.model_col_2,
+        instance.model_col_3,
+    );
+
+    let world_position = model_mat * vec4<f32>(vertex.position, 1.0);
+    return VertexOutput(
+        // clip_position
+        proj_mat * view_mat * world_position,
+        // world_position
+        world_position.xyz,
+        // color
+        instance.color,
+    );
+}
+
+// Fragment shader
+//
+// The bunny mesh has no normals, so we compute flat shading normals
+// from the screen-space derivatives of the world position (the triangle plane),
+// and shade the instance color with a simple directional light.
+
+@fragment
+fn fragment_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let normal = normalize(cross(dpdy(in.world_position), dpdx(in.world_position)));
+    let light_dir = normalize(vec3<f32>(0.5, 1.0, -0.5));
+    let diffuse = abs(dot(normal, light_dir));
+    let ambient = 0.2;
+    return vec4<f32>((ambient + (1.0 - ambient) * diffuse) * in.color, 1.0);
+}
@@ -0,0 +1,676 @@
+//! This example shows how to cast shadows from a directional light with shadow mapping.
+//!
+//! We reuse the bunny OBJ and the depth texture setup of the previous examples,
+//! and place the bunny on a ground plane lit by a directional light.
+//! The scene is rendered in two passes:
+//! 1. a shadow pass, rendering the depth of the scene from the light point of view
+//!    into a "shadow map" texture, with a configurable depth bias,
+//! 2. the main pass, rendering the scene from the camera point of view,
+//!    and sampling the shadow map with a comparison sampler to know which fragments are lit.
+//!
+//! The shadow map resolution and the depth bias can be configured from the command line,
+//! for example `cargo run -- --shadow-resolution 256 --depth-bias 0 --slope-bias 0`
+//! shows both blocky shadow edges and "shadow acne" (self-shadowing artifacts).
+//! The steps of this minimal program are the following.
+//!
+//! 1. (async) Initialize the connection with the GPU device
+//! 2. Initialize a wgpu Texture object that will serve as a write target for fragment shader
+//! 3. Initialize a wgpu Buffer where the Texture output will be transferred to
+//! 4. Initialize a wgpu Texture object that will serve as a write target for the depth
+//! 5. **(new)** Initialize the shadow map, a depth texture that can also be sampled in shaders,
+//!    and its comparison sampler
+//! 6. Load the OBJ bunny and **(new)** create a ground plane
+//!    1. Create and initialize vertex buffers containing the triangle coordinates
+//!    2. Create and initialize index buffers containing the vertex indices in the faces
+//! 7. Load the shader module, containing the vertex and fragment shaders of both passes
+//! 8. **(new)** Define our two render pipelines:
+//!    - the shadow pipeline, with no fragment shader (only the depth is needed)
+//!      and a depth bias to avoid self-shadowing
+//!    - the main pipeline, similar to the one of the previous examples
+//! 9. Create the camera and the light
+//!    1. Put the camera and light parameters into uniform buffers
+//!    2. Create a bind group for each pipeline, and let WebGPU derive the layouts implicitely.
+//!       **(new)** The main pipeline bind group also contains the shadow map and its sampler.
+//! 10. Define our command encoder:
+//!     1. **(new)** Start by defining the shadow render pass:
+//!        - Link to the shadow map as depth target (no color target)
+//!        - Draw the bunny and the ground
+//!     2. Then define the main render pass:
+//!        - Link to the texture output
+//!        - Draw the bunny and the ground
+//!     3. Add a command to copy the fragment texture into the output buffer
+//! 11. Submit our commands to the device queue
+//! 12. (async) Transfer the output buffer into an image we can save to disk
+
+use wgpu::util::DeviceExt; // Utility trait to create and initialize buffers with device.create_buffer_init()
+
+fn main() {
+    // Make the main async
+    pollster::block_on(run());
+}
+
+async fn run() {
+    // Shadow settings, provided as command line arguments
+    let args: Vec<String> = std::env::args().collect();
+    let arg_value = |name: &str| {
+        let position = args.iter().position(|arg| arg == name)?;
+        Some(
+            args.get(position + 1)
+                .expect("Missing argument value")
+                .clone(),
+        )
+    };
+    let shadow_resolution: u32 =
+        arg_value("--shadow-resolution").map_or(1024, |value| value.parse().unwrap());
+    let depth_bias = wgpu::DepthBiasState {
+        // Constant depth bias, in units of the smallest depth difference
+        constant: arg_value("--depth-bias").map_or(2, |value| value.parse().unwrap()),
+        // Depth bias proportional to the slope of the triangle (relative to the light)
+        slope_scale: arg_value("--slope-bias").map_or(2.0, |value| value.parse().unwrap()),
+        // Maximum depth bias (0.0 for no limit)
+        clamp: 0.0,
+    };
+
+    // (1) Initializing WebGPU
+    println!("Initializing WebGPU ...");
+    let (device, queue) = init_wgpu_device().await.unwrap();
+
+    // (2) Initialize the output texture
+    let width = 512;
+    let height = 512;
+    let texture = init_output_texture(&device, width, height);
+    let texture_view = texture.create_view(&Default::default());
+
+    // (3) Initialize a buffer for the texture output
+    let output_buffer_desc = create_texture_buffer_descriptor(&texture);
+    let output_buffer = device.create_buffer(&output_buffer_desc);
+
+    // (4) Initialize the depth texture
+    let depth_texture = init_depth_texture(&device, width, height, false);
+    let depth_texture_view = depth_texture.create_view(&Default::default());
+
+    // (5) Initialize the shadow map and its comparison sampler
+    let shadow_map = init_depth_texture(&device, shadow_resolution, shadow_resolution, true);
+    let shadow_map_view = shadow_map.create_view(&Default::default());
+    let shadow_sampler = init_shadow_sampler(&device);
+
+    // (6) Load the OBJ bunny
+    let (models, _) = tobj::load_obj("bunny.obj", &tobj::GPU_LOAD_OPTIONS).unwrap();
+    let bunny = &models[0].mesh;
+    // Ground square under the bunny, at the height of its lowest point
+    let ground_positions: &[f32] = &[
+        -0.3, 0.033, -0.3, //
+        0.3, 0.033, -0.3, //
+        0.3, 0.033, 0.3, //
+        -0.3, 0.033, 0.3, //
+    ];
+    let ground_indices: &[u32] = &[0, 1, 2, 0, 2, 3];
+
+    // (6.1 & 6.2) Create and initialize the vertex and index buffers of both meshes
+    // (needs the DeviceExt trait)
+    let meshes = [
+        Mesh::new(&device, &bunny.positions, &bunny.indices),
+        Mesh::new(&device, ground_positions, ground_indices),
+    ];
+
+    // (7) Load the shader module, containing the shaders of both passes
+    let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("shadow_shader"),
+        source: wgpu::ShaderSource::Wgsl(include_str!("shadow.wgsl").into()),
+    });
+
+    // (8) Define our two pipelines
+    let shadow_pipeline =
+        build_shadow_pipeline(&device, &shader_module, vtx_buffer_layout(), depth_bias);
+    let pipeline = build_simple_pipeline(
+        &device,
+        &shader_module,
+        texture.format(),
+        vtx_buffer_layout(),
+    );
+
+    // (9.1) Put the camera and light parameters into uniform buffers
+    let camera = Camera {
+        eye: [0.0, 0.25, -0.35],
+        focal_length: 2.0,
+        center: [-0.02, 0.09, 0.0],
+        aspect_ratio: (width as f32) / (height as f32),
+        near_plane: 0.1,
+        far_plane: 2.0,
+        _padding: [0.0; 2],
+    };
+    let camera_buffer = create_uniform_buffer(&device, bytemuck::bytes_of(&camera));
+    let light = Light {
+        position: [0.3, 0.5, -0.1],
+        half_size: 0.3,
+        center: [-0.02, 0.09, 0.0],
+        near_plane: 0.1,
+        far_plane: 1.5,
+        _padding: [0.0; 3],
+    };
+    let light_buffer = create_uniform_buffer(&device, bytemuck::bytes_of(&light));
+
+    // (9.2) For the bind group layouts, we let WebGPU derive them implicitely.
+    // The shadow pipeline only uses the light parameters.
+    let shadow_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout: &shadow_pipeline.get_bind_group_layout(0),
+        entries: &[wgpu::BindGroupEntry {
+            binding: 1,
+            resource: light_buffer.as_entire_binding(),
+        }],
+        label: Some("shadow_bind_group"),
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: light_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::TextureView(&shadow_map_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::Sampler(&shadow_sampler),
+            },
+        ],
+        label: Some("main_bind_group"),
+    });
+
+    // Initialize a command encoder
+    let mut encoder = device.create_command_encoder(&Default::default());
+
+    // (10.1) Render the shadow map, from the light point of view
+    draw_shadow_pipeline(
+        &mut encoder,
+        &shadow_pipeline,
+        &shadow_map_view,
+        &shadow_bind_group,
+        &meshes,
+    );
+
+    // (10.2) Render the scene, from the camera point of view
+    draw_pipeline(
+        &mut encoder,
+        &pipeline,
+        &texture_view,
+        &depth_texture_view,
+        &bind_group,
+        &meshes,
+    );
+
+    // (10.3) Add commands to copy the texture into the output buffer
+    copy_texture_to_buffer(&mut encoder, &texture, &output_buffer);
+
+    // (11) Finalize the command encoder and send it to the queue
+    println!("Submitting commands to the queue ...");
+    queue.submit(Some(encoder.finish()));
+
+    // (12) Transfer texture buffers into image buffers.
+    // New scope to encapsulate img_data BufferView and drop it before unmapping.
+    {
+        // Transfer the texture output buffer into an image buffer
+        println!("Saving the GPU output into an image ...");
+        let img_data = retrieve_texture_buffer_data(&device, &output_buffer).await;
+        let img = image::RgbaImage::from_raw(width, height, Vec::from(&img_data as &[u8])).unwrap();
+
+        println!("Saving the image to disk ...");
+        img.save("image.png").unwrap();
+    }
+
+    // Flushes any pending write operations and unmaps the buffer from host memory
+    output_buffer.unmap();
+
+    println!("Terminating the program ...")
+}
+
+/// (1) Initializing WebGPU
+async fn init_wgpu_device() -> Result<(wgpu::Device, wgpu::Queue), wgpu::RequestDeviceError> {
+    // Start an "Instance", which is the context for all things wgpu.
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: wgpu::Backends::all(),
+        ..Default::default()
+    });
+
+    // An "Adapter" is a handle to a physical graphics/compute device.
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(), // None | LowPower | HighPerformance
+            compatible_surface: None,
+            force_fallback_adapter: false, // If needed to force CPU fallback?
+        })
+        .await
+        .unwrap();
+
+    // Request a connection to a physical device,
+    // and also access to the queue for its command buffers.
+    // It is possible, if necessary, to add a description of required features.
+    adapter.request_device(&Default::default(), None).await
+}
+
+/// (2) Initialize the output texture
+fn init_output_texture(device: &wgpu::Device, width: u32, height: u32) -> wgpu::Texture {
+    let texture_desc = wgpu::TextureDescriptor {
+        label: Some("output_texture"),
+        // The texture size. (layers is set to 1)
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        dimension: wgpu::TextureDimension::D2,
+        mip_level_count: 1, // the number of mip levels the texture will contain
+        sample_count: 1,    // sample_count > 1 would indicate a multisampled texture
+        // Use RGBA format for the output
+        format: wgpu::TextureFormat::Rgba8Unorm,
+        // RENDER_ATTACHMENT -> so that the GPU can render to the texture
+        // COPY_SRC -> so that we can pull data out of the texture
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        // Specify the allowed formats when calling "texture.create_view()"
+        view_formats: &[],
+    };
+    device.create_texture(&texture_desc)
+}
+
+/// (3) Create a buffer descriptor of the correct size for the texture
+fn create_texture_buffer_descriptor(texture: &wgpu::Texture) -> wgpu::BufferDescriptor<'_> {
+    let texel_size = texture.format().block_copy_size(None).unwrap();
+    wgpu::BufferDescriptor {
+        size: (texel_size * texture.width() * texture.height()).into(),
+        usage: wgpu::BufferUsages::COPY_DST
+            // this tells wpgu that we want to read this buffer from the cpu
+            | wgpu::BufferUsages::MAP_READ,
+        label: None,
+        mapped_at_creation: false,
+    }
+}
+
+/// (4 & 5) Initialize a depth texture.
+/// The shadow map also needs to be sampled in the main pass.
+fn init_depth_texture(
+    device: &wgpu::Device,
+    width: u32,
+    height: u32,
+    sampled: bool,
+) -> wgpu::Texture {
+    let texture_desc = wgpu::TextureDescriptor {
+        label: Some(if sampled {
+            "shadow_map"
+        } else {
+            "depth_texture"
+        }),
+        // The texture size. (layers is set to 1)
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        dimension: wgpu::TextureDimension::D2,
+        mip_level_count: 1, // the number of mip levels the texture will contain
+        sample_count: 1,    // sample_count > 1 would indicate a multisampled texture
+        // Use a 32 bits float format for the depth
+        format: wgpu::TextureFormat::Depth32Float,
+        // RENDER_ATTACHMENT -> so that the GPU can render to the texture
+        // TEXTURE_BINDING -> so that the texture can be sampled in shaders
+        usage: if sampled {
+            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING
+        } else {
+            wgpu::TextureUsages::RENDER_ATTACHMENT
+        },
+        // Specify the allowed formats when calling "texture.create_view()"
+        view_formats: &[],
+    };
+    device.create_texture(&texture_desc)
+}
+
+/// (5) Initialize the comparison sampler of the shadow map.
+/// Instead of returning the depth stored in the shadow map,
+/// sampling returns the result of the comparison with a provided depth (0.0 or 1.0).
+fn init_shadow_sampler(device: &wgpu::Device) -> wgpu::Sampler {
+    device.create_sampler(&wgpu::SamplerDescriptor {
+        label: Some("shadow_sampler"),
+        // Outside of the shadow map, reuse the values of its edges
+        address_mode_u: wgpu::AddressMode::ClampToEdge,
+        address_mode_v: wgpu::AddressMode::ClampToEdge,
+        address_mode_w: wgpu::AddressMode::ClampToEdge,
+        // Linear filtering interpolates the comparison results of the 4 closest texels
+        mag_filter: wgpu::FilterMode::Linear,
+        min_filter: wgpu::FilterMode::Linear,
+        mipmap_filter: wgpu::FilterMode::Nearest,
+        // Lit if the fragment depth is less or equal to the depth stored in the shadow map
+        compare: Some(wgpu::CompareFunction::LessEqual),
+        ..Default::default()
+    })
+}
+
+/// Define the layout of Vertex buffers
+fn vtx_buffer_layout() -> wgpu::VertexBufferLayout<'static> {
+    wgpu::VertexBufferLayout {
+        // array_stride is the bytes count between two vertices
+        array_stride: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &[
+            // position
+            wgpu::VertexAttribute {
+                offset: 0,
+                shader_location: 0,
+                format: wgpu::VertexFormat::Float32x3,
+            },
+        ],
+    }
+}
+
+/// (6) The buffers of a mesh to draw
+struct Mesh {
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    num_indices: u32,
+}
+
+impl Mesh {
+    /// (6.1 & 6.2) Create and initialize the vertex and index buffers of a mesh
+    fn new(device: &wgpu::Device, positions: &[f32], indices: &[u32]) -> Self {
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertex Buffer"),
+            contents: bytemuck::cast_slice(positions),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Index Buffer"),
+            contents: bytemuck::cast_slice(indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        Self {
+            vertex_buffer,
+            index_buffer,
+            num_indices: indices.len() as u32,
+        }
+    }
+
+    fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.draw_indexed(0..self.num_indices, 0, 0..1);
+    }
+}
+
+/// Perspective camera, looking from eye to center.
+/// Bytemuck is used to enable easy casting to a &[u8].
+/// The fields order and padding follow the alignment rules of the shader struct
+/// (vec3 are aligned to 16 bytes, and the struct size is a multiple of 16 bytes).
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct Camera {
+    eye: [f32; 3],
+    focal_length: f32,
+    center: [f32; 3],
+    aspect_ratio: f32,
+    near_plane: f32,
+    far_plane: f32,
+    _padding: [f32; 2],
+}
+
+/// Directional light, with rays going from position to center.
+/// Its orthographic projection covers a square of half_size around the center.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct Light {
+    position: [f32; 3],
+    half_size: f32,
+    center: [f32; 3],
+    near_plane: f32,
+    far_plane: f32,
+    _padding: [f32; 3],
+}
+
+/// (9.1) Create a uniform buffer initialized with the given data
+fn create_uniform_buffer(device: &wgpu::Device, contents: &[u8]) -> wgpu::Buffer {
+    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Uniform Buffer"),
+        contents,
+        usage: wgpu::BufferUsages::UNIFORM,
+    })
+}
+
+/// (8) Define the shadow render pipeline, only writing the depth from the light point of view
+fn build_shadow_pipeline(
+    device: &wgpu::Device,
+    shader_module: &wgpu::ShaderModule,
+    vertex_buffer_layout: wgpu::VertexBufferLayout,
+    depth_bias: wgpu::DepthBiasState,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Shadow Pipeline"),
+        layout: None, // "auto"
+        vertex: wgpu::VertexState {
+            module: shader_module,
+            entry_point: "vertex_shadow",
+            buffers: &[vertex_buffer_layout],
+        },
+        // No fragment shader, the depth is written without it
+        fragment: None,
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            // The ground is a single sided square, so no culling
+            cull_mode: None,
+            // Setting this to anything other than Fill requires Features::NON_FILL_POLYGON_MODE
+            polygon_mode: wgpu::PolygonMode::Fill,
+            // Requires Features::DEPTH_CLIP_CONTROL
+            unclipped_depth: false,
+            // Requires Features::CONSERVATIVE_RASTERIZATION
+            conservative: false,
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: wgpu::TextureFormat::Depth32Float,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            // Push the depth written in the shadow map slightly away from the light.
+            // Otherwise, due to the limited shadow map resolution and depth precision,
+            // lit surfaces would randomly shadow themselves ("shadow acne").
+            bias: depth_bias,
+        }),
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+    })
+}
+
+/// (8) Define our simple render pipeline
+fn build_simple_pipeline(
+    device: &wgpu::Device,
+    shader_module: &wgpu::ShaderModule,
+    texture_format: wgpu::TextureFormat,
+    vertex_buffer_layout: wgpu::VertexBufferLayout,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Render Pipeline"),
+        layout: None, // "auto"
+        vertex: wgpu::VertexState {
+            module: shader_module,
+            entry_point: "vertex_main",
+            buffers: &[vertex_buffer_layout],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader_module,
+            entry_point: "fragment_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format: texture_format,
+                blend: Some(wgpu::BlendState {
+                    alpha: wgpu::BlendComponent::REPLACE,
+                    color: wgpu::BlendComponent::REPLACE,
+                }),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            // The ground is a single sided square, so no culling
+            cull_mode: None,
+            // Setting this to anything other than Fill requires Features::NON_FILL_POLYGON_MODE
+            polygon_mode: wgpu::PolygonMode::Fill,
+            // Requires Features::DEPTH_CLIP_CONTROL
+            unclipped_depth: false,
+            // Requires Features::CONSERVATIVE_RASTERIZATION
+            conservative: false,
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: wgpu::TextureFormat::Depth32Float,
+            // Enable WebGPU to write the depth (position.z) to the provided texture
+            depth_write_enabled: true,
+            // Keep the depth value closest to us (lower values)
+            depth_compare: wgpu::CompareFunction::Less,
+            // Not using stencil stuff
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        // If the pipeline will be used with a multiview render pass, this
+        // indicates how many array layers the attachments will have.
+        multiview: None,
+    })
+}
+
+/// (10.1) Draw the shadow pipeline (add the shadow render pass to the command encoder).
+fn draw_shadow_pipeline(
+    encoder: &mut wgpu::CommandEncoder,
+    pipeline: &wgpu::RenderPipeline,
+    shadow_map_view: &wgpu::TextureView,
+    bind_group: &wgpu::BindGroup,
+    meshes: &[Mesh],
+) {
+    // No color attachment, only the depth
+    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("Shadow Pass"),
+        color_attachments: &[],
+        depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+            view: shadow_map_view,
+            depth_ops: Some(wgpu::Operations {
+                load: wgpu::LoadOp::Clear(1.0),
+                store: wgpu::StoreOp::Store,
+            }),
+            stencil_ops: None,
+        }),
+        ..Default::default()
+    });
+
+    render_pass.set_pipeline(pipeline);
+    render_pass.set_bind_group(0, bind_group, &[]);
+    for mesh in meshes {
+        mesh.draw(&mut render_pass);
+    }
+}
+
+/// (10.2) Draw our pipeline (add render pass to the command encoder).
+fn draw_pipeline(
+    encoder: &mut wgpu::CommandEncoder,
+    pipeline: &wgpu::RenderPipeline,
+    texture_view: &wgpu::TextureView,
+    depth_texture_view: &wgpu::TextureView,
+    bind_group: &wgpu::BindGroup,
+    meshes: &[Mesh],
+) {
+    // Setup the pass that will render into our texture
+    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("Render Pass"),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: texture_view,   // the output texture
+            resolve_target: None, // only useful for multisampling
+            ops: wgpu::Operations {
+                // "load" specifies how data is read.
+                // "Clear" is the lightest way to initialize the texture.
+                load: wgpu::LoadOp::Clear(wgpu::Color {
+                    r: 0.1,
+                    g: 0.2,
+                    b: 0.3,
+                    a: 1.0,
+                }),
+                // whether data is written to or not. Store | Discard
+                store: wgpu::StoreOp::Store,
+            },
+        })],
+        depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+            view: depth_texture_view,
+            depth_ops: Some(wgpu::Operations {
+                load: wgpu::LoadOp::Clear(1.0),
+                store: wgpu::StoreOp::Store,
+            }),
+            stencil_ops: None,
+        }),
+        ..Default::default()
+    });
+
+    // Draw the render pass for our pipeline
+    render_pass.set_pipeline(pipeline);
+    render_pass.set_bind_group(0, bind_group, &[]);
+    for mesh in meshes {
+        mesh.draw(&mut render_pass);
+    }
+}
+
+/// (10.3) Copy the texture output into a buffer
+fn copy_texture_to_buffer(
+    encoder: &mut wgpu::CommandEncoder,
+    texture: &wgpu::Texture,
+    output_buffer: &wgpu::Buffer,
+) {
+    let texel_size = texture.format().block_copy_size(None).unwrap();
+    encoder.copy_texture_to_buffer(
+        // source
+        wgpu::ImageCopyTexture {
+            aspect: wgpu::TextureAspect::All,
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+        },
+        // destination
+        wgpu::ImageCopyBuffer {
+            buffer: output_buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(texel_size * texture.width()),
+                rows_per_image: Some(texture.height()),
+            },
+        },
+        // copy_size
+        texture.size(),
+    );
+}
+
+/// (12) Retrieve the texture buffer data from the GPU
+async fn retrieve_texture_buffer_data<'a>(
+    device: &wgpu::Device,
+    texture_buffer: &'a wgpu::Buffer,
+) -> wgpu::BufferView<'a> {
+    let buffer_slice = texture_buffer.slice(..);
+
+    // NOTE: We have to create the mapping THEN device.poll() before await the future.
+    // Otherwise the application will freeze.
+    let (tx, rx) = oneshot::channel();
+    buffer_slice.map_async(wgpu::MapMode::Read, move |result| {
+        tx.send(result).unwrap();
+    });
+    device.poll(wgpu::Maintain::Wait);
+    rx.await.unwrap().unwrap();
+
+    // Synchronously and immediately map a buffer for reading.
+    // Will panic if buffer_slice.map_async() did not finish yet.
+    buffer_slice.get_mapped_range()
+}
@@ -0,0 +1,138 @@
+// Shadow mapping from a directional light, in two render passes.
+//
+// 1. The shadow pass renders the scene from the point of view of the light,
+//    with an orthographic projection (the light rays are parallel).
+//    Only the depth is kept, in the shadow map texture.
+// 2. The main pass renders the scene from the point of view of the camera.
+//    Each fragment is projected into the light space, and its depth is compared
+//    to the one stored in the shadow map: if it is further away from the light,
+//    something else is in front of it, and the fragment is in the shadow.
+
+/// Perspective camera parameters.
+/// The vec3 are aligned to 16 bytes, so we fill the gaps with the scalar parameters.
+struct CameraParams {
+    eye: vec3<f32>,
+    focal: f32,
+    center: vec3<f32>,
+    ratio: f32,
+    near: f32,
+    far: f32,
+}
+
+/// Directional light parameters.
+/// The light rays go from the position to the center,
+/// and the orthographic projection covers a square of half_size around the center.
+struct LightParams {
+    position: vec3<f32>,
+    half_size: f32,
+    center: vec3<f32>,
+    near: f32,
+    far: f32,
+}
+
+@group(0) @binding(0) var<uniform> camera: CameraParams;
+@group(0) @binding(1) var<uniform> light: LightParams;
+@group(0) @binding(2) var shadow_map: texture_depth_2d;
+@group(0) @binding(3) var shadow_sampler: sampler_comparison;
+
+/// Build a view matrix (extrinsics) looking from eye to center,
+/// with the same conventions as the camera example:
+/// X to the right, Y up, and Z forward (in front of the camera).
+fn look_at(eye: vec3<f32>, center: vec3<f32>) -> mat4x4<f32> {
+    let forward = normalize(center - eye);
+    let right = normalize(cross(vec3<f32>(0.0, 1.0, 0.0), forward));
+    let up = cross(forward, right);
+    return transpose(mat4x4f(
+        right.x  , right.y  , right.z  , -dot(right, eye)  ,
+        up.x     , up.y     , up.z     , -dot(up, eye)     ,
+        forward.x, forward.y, forward.z, -dot(forward, eye),
+        0.0      , 0.0      , 0.0      , 1.0               ,
+    ));
+}
+
+/// Projection from world coordinates to the clip coordinates of the camera
+fn camera_view_proj() -> mat4x4<f32> {
+    let focal = camera.focal;
+    let ratio = camera.ratio;
+    let near = camera.near;
+    let far = camera.far;
+    // Perspective projection matrix, see the camera example for details
+    let proj_mat = transpose(mat4x4f(
+        focal, 0.0          , 0.0               , 0.0                       ,
+        0.0  , focal * ratio, 0.0               , 0.0                       ,
+        0.0  , 0.0          , far / (far - near), -far * near / (far - near),
+        0.0  , 0.0          , 1.0               , 0.0                       ,
+    ));
+    return proj_mat * look_at(camera.eye, camera.center);
+}
+
+/// Projection from world coordinates to the clip coordinates of the light.
+/// Contrary to the camera, it is an orthographic projection, with no perspective division (W = 1).
+fn light_view_proj() -> mat4x4<f32> {
+    let scale = 1.0 / light.half_size;
+    let near = light.near;
+    let far = light.far;
+    let proj_mat = transpose(mat4x4f(
+        scale, 0.0  , 0.0               , 0.0                  ,
+        0.0  , scale, 0.0               , 0.0                  ,
+        0.0  , 0.0  , 1.0 / (far - near), -near / (far - near) ,
+        0.0  , 0.0  , 0.0               , 1.0                  ,
+    ));
+    return proj_mat * look_at(light.position, light.center);
+}
+
+// Shadow pass: vertex shader only, the depth is written automatically
+
+@vertex
+fn vertex_shadow(@location(0) position: vec3<f32>) -> @builtin(position) vec4<f32> {
+    return light_view_proj() * vec4<f32>(position, 1.0);
+}
+
+// Main pass
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) world_position: vec3<f32>,
+}
+
+@vertex
+fn vertex_main(@location(0) position: vec3<f32>) -> VertexOutput {
+    return VertexOutput(
+        // clip_position
+        camera_view_proj() * vec4<f32>(position, 1.0),
+        // world_position
+        position,
+    );
+}
+
+@fragment
+fn fragment_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Project the fragment into the shadow map.
+    // Clip coordinates are in [-1, 1] with Y up, texture coordinates in [0, 1] with Y down.
+    let light_clip = light_view_proj() * vec4<f32>(in.world_position, 1.0);
+    let shadow_uv = vec2<f32>(0.5 * light_clip.x + 0.5, -0.5 * light_clip.y + 0.5);
+
+    // The comparison sampler returns 1.0 where the fragment depth is less or equal
+    // to the shadow map depth (lit), and 0.0 otherwise (in the shadow).
+    // With linear filtering, the 4 closest texels are compared and interpolated,
+    // which smoothes a little the shadow edges.
+    var visibility = textureSampleCompare(shadow_map, shadow_sampler, shadow_uv, light_clip.z);
+    // Everything beyond the far plane of the light is considered lit
+    if light_clip.z > 1.0 {
+        visibility = 1.0;
+    }
+
+    // The bunny mesh has no normals, so we compute flat shading normals
+    // from the screen-space derivatives of the world position,
+    // turned to face the camera.
+    var normal = normalize(cross(dpdx(in.world_position), dpdy(in.world_position)));
+    if dot(normal, camera.eye - in.world_position) < 0.0 {
+        normal = -normal;
+    }
+
+    // Simple diffuse shading, only where the light is not blocked
+    let to_light = normalize(light.position - light.center);
+    let diffuse = max(dot(normal, to_light), 0.0) * visibility;
+    let ambient = 0.2;
+    return vec4<f32>(vec3<f32>(ambient + (1.0 - ambient) * diffuse), 1.0);
+}
@@ -0,0 +1,648 @@
+//! This example shows how to render a G-buffer with multiple render targets (MRT).
+//!
+//! A G-buffer is the first step of deferred rendering:
+//! instead of shading the scene directly, a single "geometry" render pass writes
+//! all the surface attributes needed for lighting into several color attachments:
+//! - the albedo (base color) of the surface, in a `Rgba8Unorm` texture,
+//! - the world-space normal, in a `Rgba16Float` texture,
+//! - the world-space position, in a `Rgba32Float` texture,
+//! - the linear depth (distance to the camera along its viewing axis), in a `R32Float` texture.
+//!
+//! The fragment shader has one output per color attachment, identified by its location.
+//! Remark that the total size of the attachments formats is limited per pixel
+//! (32 bytes with the default limits), which drives the choice of formats above.
+//! Each target is then read back and saved according to its format:
+//! a PNG image for the albedo, and PFM images (portable float maps) for the float targets.
+//!
+//! The scene is the bunny OBJ on a checkerboard ground plane,
+//! with vertex normals computed from the mesh triangles.
+//! The steps of this minimal program are the following.
+//!
+//! 1. (async) Initialize the connection with the GPU device
+//! 2. **(new)** Initialize one wgpu Texture per G-buffer target, each with its own format
+//! 3. Initialize a wgpu Buffer per Texture, where their data will be transferred to
+//! 4. Initialize a wgpu Texture object that will serve as a write target for the depth
+//! 5. Load the OBJ bunny and create a ground plane
+//!    1. **(new)** Compute the vertex normals of the bunny, and assign an albedo to each vertex
+//!    2. Create and initialize the vertex and index buffers of each mesh
+//! 6. Load the shader module, containing both the vertex and fragment shaders
+//! 7. Define our render pipeline, including:
+//!    - the vertex shader: include our vertex buffer layout (position, normal and albedo)
+//!    - the fragment shader, **(new)** with one color target per G-buffer texture
+//!    - the primitive type (triangle list)
+//!    - the depth_stencil, only keeping the closest fragments ("Less")
+//! 8. Create the camera
+//!    1. Put the perspective projection camera parameters into a uniform buffer
+//!    2. Create a bind group and let WebGPU derive the layout implicitely
+//! 9. Define our command encoder:
+//!    1. Start by defining our render pass:
+//!       - **(new)** Link to all the G-buffer textures
+//!       - Link to the pipeline
+//!       - Provide the camera bind group
+//!       - Draw the bunny and the ground
+//!    2. Add a command to copy each G-buffer texture into its buffer
+//! 10. Submit our commands to the device queue
+//! 11. (async) **(new)** Transfer the output buffers into images,
+//!     depending on their texture formats, and save them to disk.
+
+use texture_image::{padded_bytes_per_row, DecodeError, Texels, TextureImage}; // Decoding of the readbacks
+use wgpu::util::DeviceExt; // Utility trait to create and initialize buffers with device.create_buffer_init()
+
+/// The G-buffer targets, with the name of the file they are saved to, and their format
+const GBUFFER_TARGETS: [(&str, wgpu::TextureFormat); 4] = [
+    ("albedo", wgpu::TextureFormat::Rgba8Unorm),
+    ("normal", wgpu::TextureFormat::Rgba16Float),
+    ("position", wgpu::TextureFormat::Rgba32Float),
+    ("depth", wgpu::TextureFormat::R32Float),
+];
+
+fn main() {
+    // Make the main async
+    pollster::block_on(run());
+}
+
+async fn run() {
+    // (1) Initializing WebGPU
+    println!("Initializing WebGPU ...");
+    let (device, queue) = init_wgpu_device().await.unwrap();
+
+    // (2) Initialize the G-buffer textures
+    let width = 512;
+    let height = 512;
+    let textures: Vec<wgpu::Texture> = GBUFFER_TARGETS
+        .iter()
+        .map(|&(name, format)| init_output_texture(&device, name, width, height, format))
+        .collect();
+    let texture_views: Vec<wgpu::TextureView> = textures
+        .iter()
+        .map(|texture| texture.create_view(&Default::default()))
+        .collect();
+
+    // (3) Initialize a buffer for each texture output
+    let output_buffers: Vec<wgpu::Buffer> = textures
+        .iter()
+        .map(|texture| device.create_buffer(&create_texture_buffer_descriptor(texture)))
+        .collect();
+
+    // (4) Initialize the depth texture
+    let depth_texture = init_depth_texture(&device, width, height);
+    let depth_texture_view = depth_texture.create_view(&Default::default());
+
+    // (5) Load the OBJ bunny
+    let (models, _) = tobj::load_obj("bunny.obj", &tobj::GPU_LOAD_OPTIONS).unwrap();
+    let bunny = &models[0].mesh;
+
+    // (5.1) The OBJ has no normals, so we compute them from its triangles
+    let bunny_normals = compute_vertex_normals(&bunny.positions, &bunny.indices);
+    let bunny_vertices: Vec<Vertex> = bunny
+        .positions
+        .chunks_exact(3)
+        .zip(&bunny_normals)
+        .map(|(position, &normal)| Vertex {
+            position: [position[0], position[1], position[2]],
+            normal,
+            albedo: [0.9, 0.75, 0.55],
+        })
+        .collect();
+    // Checkerboard ground under the bunny, at the height of its lowest point
+    let (ground_vertices, ground_indices) = ground_mesh(8, 0.3, 0.033);
+
+    // (5.2) Create and initialize the vertex and index buffers of both meshes
+    // (needs the DeviceExt trait)
+    let meshes = [
+        Mesh::new(&device, &bunny_vertices, &bunny.indices),
+        Mesh::new(&device, &ground_vertices, &ground_indices),
+    ];
+
+    // (6) Load the shader module, containing both the vertex and fragment shaders
+    let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("gbuffer_shader"),
+        source: wgpu::ShaderSource::Wgsl(include_str!("gbuffer.wgsl").into()),
+    });
+
+    // (7) Define our pipeline, with one color target per G-buffer texture
+    let texture_formats: Vec<wgpu::TextureFormat> =
+        textures.iter().map(|texture| texture.format()).collect();
+    let pipeline = build_simple_pipeline(
+        &device,
+        &shader_module,
+        &texture_formats,
+        vtx_buffer_layout(),
+    );
+
+    // (8.1) Put the perspective projection camera into a uniform buffer
+    let camera = Camera {
+        eye: [0.0, 0.25, -0.35],
+        focal_length: 2.0,
+        center: [-0.02, 0.09, 0.0],
+        aspect_ratio: (width as f32) / (height as f32),
+        near_plane: 0.1,
+        far_plane: 2.0,
+        _padding: [0.0; 2],
+    };
+    let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Camera Buffer"),
+        contents: bytemuck::bytes_of(&camera),
+        usage: wgpu::BufferUsages::UNIFORM,
+    });
+
+    // (8.2) For the bind group layout, we let WebGPU derive it implicitely.
+    let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: camera_buffer.as_entire_binding(),
+        }],
+        label: Some("camera_bind_group"),
+    });
+
+    // Initialize a command encoder
+    let mut encoder = device.create_command_encoder(&Default::default());
+
+    // (9.1) Draw our pipeline (add render pass to the command encoder)
+    let texture_view_refs: Vec<&wgpu::TextureView> = texture_views.iter().collect();
+    draw_pipeline(
+        &mut encoder,
+        &pipeline,
+        &texture_view_refs,
+        &depth_texture_view,
+        &camera_bind_group,
+        &meshes,
+    );
+
+    // (9.2) Add commands to copy the textures into their respective buffers
+    for (texture, output_buffer) in textures.iter().zip(&output_buffers) {
+        copy_texture_to_buffer(&mut encoder, texture, output_buffer);
+    }
+
+    // (10) Finalize the command encoder and send it to the queue
+    println!("Submitting commands to the queue ...");
+    queue.submit(Some(encoder.finish()));
+
+    // (11) Transfer texture buffers into images, according to their formats.
+    // New scope to encapsulate the BufferView and drop it before unmapping.
+    for ((name, format), output_buffer) in GBUFFER_TARGETS.iter().zip(&output_buffers) {
+        {
+            println!("Saving the {name} target to disk ...");
+            let data = retrieve_texture_buffer_data(&device, output_buffer).await;
+            save_texture_data(name, *format, &data, width, height)
+                .unwrap_or_else(|error| panic!("Cannot save the {name} target: {error}"));
+        }
+        // Flushes any pending write operations and unmaps the buffer from host memory
+        output_buffer.unmap();
+    }
+
+    println!("Terminating the program ...")
+}
+
+/// (1) Initializing WebGPU
+async fn init_wgpu_device() -> Result<(wgpu::Device, wgpu::Queue), wgpu::RequestDeviceError> {
+    // Start an "Instance", which is the context for all things wgpu.
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: wgpu::Backends::all(),
+        ..Default::default()
+    });
+
+    // An "Adapter" is a handle to a physical graphics/compute device.
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(), // None | LowPower | HighPerformance
+            compatible_surface: None,
+            force_fallback_adapter: false, // If needed to force CPU fallback?
+        })
+        .await
+        .unwrap();
+
+    // Request a connection to a physical device,
+    // and also access to the queue for its command buffers.
+    // It is possible, if necessary, to add a description of required features.
+    adapter.request_device(&Default::default(), None).await
+}
+
+/// (2) Initialize an output texture of the G-buffer
+fn init_output_texture(
+    device: &wgpu::Device,
+    name: &str,
+    width: u32,
+    height: u32,
+    format: wgpu::TextureFormat,
+) -> wgpu::Texture {
+    let texture_desc = wgpu::TextureDescriptor {
+        label: Some(name),
+        // The texture size. (layers is set to 1)
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        dimension: wgpu::TextureDimension::D2,
+        mip_level_count: 1, // the number of mip levels the texture will contain
+        sample_count: 1,    // sample_count > 1 would indicate a multisampled texture
+        // Each G-buffer target has its own format
+        format,
+        // RENDER_ATTACHMENT -> so that the GPU can render to the texture
+        // COPY_SRC -> so that we can pull data out of the texture
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        // Specify the allowed formats when calling "texture.create_view()"
+        view_formats: &[],
+    };
+    device.create_texture(&texture_desc)
+}
+
+/// (3) Create a buffer descriptor of the correct size for the texture,
+/// with its rows padded to the alignment required by texture copies
+fn create_texture_buffer_descriptor(texture: &wgpu::Texture) -> wgpu::BufferDescriptor<'_> {
+    let bytes_per_row = padded_bytes_per_row(texture.format(), texture.width());
+    wgpu::BufferDescriptor {
+        size: (bytes_per_row * texture.height()).into(),
+        usage: wgpu::BufferUsages::COPY_DST
+            // this tells wpgu that we want to read this buffer from the cpu
+            | wgpu::BufferUsages::MAP_READ,
+        label: None,
+        mapped_at_creation: false,
+    }
+}
+
+/// (4) Initialize a depth texture
+fn init_depth_texture(device: &wgpu::Device, width: u32, height: u32) -> wgpu::Texture {
+    let texture_desc = wgpu::TextureDescriptor {
+        label: Some("depth_texture"),
+        // The texture size. (layers is set to 1)
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        dimension: wgpu::TextureDimension::D2,
+        mip_level_count: 1, // the number of mip levels the texture will contain
+        sample_count: 1,    // sample_count > 1 would indicate a multisampled texture
+        // Use a 32 bits float format for the depth
+        format: wgpu::TextureFormat::Depth32Float,
+        // RENDER_ATTACHMENT -> so that the GPU can render to the texture
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        // Specify the allowed formats when calling "texture.create_view()"
+        view_formats: &[],
+    };
+    device.create_texture(&texture_desc)
+}
+
+/// A vertex of our meshes.
+/// Bytemuck is used to enable easy casting to a &[u8].
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct Vertex {
+    position: [f32; 3],
+    normal: [f32; 3],
+    albedo: [f32; 3],
+}
+
+/// Define the layout of Vertex buffers
+fn vtx_buffer_layout() -> wgpu::VertexBufferLayout<'static> {
+    // position, normal and albedo, at shader locations 0, 1 and 2
+    const ATTRIBUTES: [wgpu::VertexAttribute; 3] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x3];
+    wgpu::VertexBufferLayout {
+        // array_stride is the bytes count between two vertices
+        array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &ATTRIBUTES,
+    }
+}
+
+/// (5.1) Compute vertex normals as the sum of the normals of their adjacent triangles.
+/// The cross product of two edges is proportional to the triangle area,
+/// so larger triangles have more weight.
+fn compute_vertex_normals(positions: &[f32], indices: &[u32]) -> Vec<[f32; 3]> {
+    let position = |i: u32| {
+        let i = 3 * i as usize;
+        [positions[i], positions[i + 1], positions[i + 2]]
+    };
+    let sub = |a: [f32; 3], b: [f32; 3]| [a[0] - b[0], a[1] - b[1], a[2] - b[2]];
+    let mut normals = vec![[0.0; 3]; positions.len() / 3];
+    for triangle in indices.chunks_exact(3) {
+        let p0 = position(triangle[0]);
+        let e1 = sub(position(triangle[1]), p0);
+        let e2 = sub(position(triangle[2]), p0);
+        let face_normal = [
+            e1[1] * e2[2] - e1[2] * e2[1],
+            e1[2] * e2[0] - e1[0] * e2[2],
+            e1[0] * e2[1] - e1[1] * e2[0],
+        ];
+        for &i in triangle {
+            let normal: &mut [f32; 3] = &mut normals[i as usize];
+            for k in 0..3 {
+                normal[k] += face_normal[k];
+            }
+        }
+    }
+    for normal in normals.iter_mut() {
+        let length = normal.iter().map(|x| x * x).sum::<f32>().sqrt();
+        if length > 0.0 {
+            normal.iter_mut().for_each(|x| *x /= length);
+        }
+    }
+    normals
+}
+
+/// (5.1) Create a horizontal square ground, made of tiles x tiles squares with alternating albedos.
+/// Each tile has its own 4 vertices, so that their albedo is not interpolated with their neighbours.
+fn ground_mesh(tiles: u32, half_size: f32, height: f32) -> (Vec<Vertex>, Vec<u32>) {
+    let tile_size = 2.0 * half_size / tiles as f32;
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    for i in 0..tiles {
+        for j in 0..tiles {
+            let albedo = if (i + j) % 2 == 0 {
+                [0.8, 0.8, 0.8]
+            } else {
+                [0.3, 0.3, 0.3]
+            };
+            let x = -half_size + i as f32 * tile_size;
+            let z = -half_size + j as f32 * tile_size;
+            let first = vertices.len() as u32;
+            for (dx, dz) in [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)] {
+                vertices.push(Vertex {
+                    position: [x + dx * tile_size, height, z + dz * tile_size],
+                    normal: [0.0, 1.0, 0.0],
+                    albedo,
+                });
+            }
+            indices.extend([first, first + 1, first + 2, first, first + 2, first + 3]);
+        }
+    }
+    (vertices, indices)
+}
+
+/// (5.2) The buffers of a mesh to draw
+struct Mesh {
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    num_indices: u32,
+}
+
+impl Mesh {
+    /// (5.2) Create and initialize the vertex and index buffers of a mesh
+    fn new(device: &wgpu::Device, vertices: &[Vertex], indices: &[u32]) -> Self {
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertex Buffer"),
+            contents: bytemuck::cast_slice(vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Index Buffer"),
+            contents: bytemuck::cast_slice(indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        Self {
+            vertex_buffer,
+            index_buffer,
+            num_indices: indices.len() as u32,
+        }
+    }
+
+    fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.draw_indexed(0..self.num_indices, 0, 0..1);
+    }
+}
+
+/// Perspective camera, looking from eye to center.
+/// Bytemuck is used to enable easy casting to a &[u8].
+/// The fields order and padding follow the alignment rules of the shader struct
+/// (vec3 are aligned to 16 bytes, and the struct size is a multiple of 16 bytes).
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct Camera {
+    eye: [f32; 3],
+    focal_length: f32,
+    center: [f32; 3],
+    aspect_ratio: f32,
+    near_plane: f32,
+    far_plane: f32,
+    _padding: [f32; 2],
+}
+
+/// (7) Define our simple render pipeline, with one color target per texture format
+fn build_simple_pipeline(
+    device: &wgpu::Device,
+    shader_module: &wgpu::ShaderModule,
+    texture_formats: &[wgpu::TextureFormat],
+    vertex_buffer_layout: wgpu::VertexBufferLayout,
+) -> wgpu::RenderPipeline {
+    // The order of the targets corresponds to the locations of the fragment shader outputs.
+    // Float32 formats do not support blending,
+    // but replacing the previous value is exactly what "no blending" does anyway.
+    let targets: Vec<_> = texture_formats
+        .iter()
+        .map(|&format| {
+            Some(wgpu::ColorTargetState {
+                format,
+                blend: None,
+                write_mask: wgpu::ColorWrites::ALL,
+            })
+        })
+        .collect();
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Render Pipeline"),
+        layout: None, // "auto"
+        vertex: wgpu::VertexState {
+            module: shader_module,
+            entry_point: "vertex_main",
+            buffers: &[vertex_buffer_layout],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader_module,
+            entry_point: "fragment_main",
+            targets: &targets,
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            // The ground is a single sided square, so no culling
+            cull_mode: None,
+            // Setting this to Line requires Features::POLYGON_MODE_LINE
+            polygon_mode: wgpu::PolygonMode::Fill,
+            // Requires Features::DEPTH_CLIP_CONTROL
+            unclipped_depth: false,
+            // Requires Features::CONSERVATIVE_RASTERIZATION
+            conservative: false,
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: wgpu::TextureFormat::Depth32Float,
+            // Enable WebGPU to write the depth (position.z) to the provided texture
+            depth_write_enabled: true,
+            // Keep the depth value closest to us (lower values)
+            depth_compare: wgpu::CompareFunction::Less,
+            // Not using stencil stuff
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        // If the pipeline will be used with a multiview render pass, this
+        // indicates how many array layers the attachments will have.
+        multiview: None,
+    })
+}
+
+/// (9.1) Draw our pipeline (add render pass to the command encoder).
+fn draw_pipeline(
+    encoder: &mut wgpu::CommandEncoder,
+    pipeline: &wgpu::RenderPipeline,
+    texture_views: &[&wgpu::TextureView],
+    depth_texture_view: &wgpu::TextureView,
+    camera_bind_group: &wgpu::BindGroup,
+    meshes: &[Mesh],
+) {
+    // One color attachment per G-buffer texture, all cleared to 0.
+    // So the background has an albedo alpha and a position W of 0.
+    let color_attachments: Vec<_> = texture_views
+        .iter()
+        .map(|&view| {
+            Some(wgpu::RenderPassColorAttachment {
+                view,                 // the output texture
+                resolve_target: None, // only useful for multisampling
+                ops: wgpu::Operations {
+                    // "load" specifies how data is read.
+                    // "Clear" is the lightest way to initialize the texture.
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    // whether data is written to or not. Store | Discard
+                    store: wgpu::StoreOp::Store,
+                },
+            })
+        })
+        .collect();
+
+    // Setup the pass that will render into our textures
+    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("Geometry Pass"),
+        color_attachments: &color_attachments,
+        depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+            view: depth_texture_view,
+            depth_ops: Some(wgpu::Operations {
+                load: wgpu::LoadOp::Clear(1.0),
+                store: wgpu::StoreOp::Store,
+            }),
+            stencil_ops: None,
+        }),
+        ..Default::default()
+    });
+
+    // Draw the render pass for our pipeline
+    render_pass.set_pipeline(pipeline);
+    render_pass.set_bind_group(0, camera_bind_group, &[]);
+    for mesh in meshes {
+        mesh.draw(&mut render_pass);
+    }
+}
+
+/// (9.2) Copy the texture output into a buffer
+fn copy_texture_to_buffer(
+    encoder: &mut wgpu::CommandEncoder,
+    texture: &wgpu::Texture,
+    output_buffer: &wgpu::Buffer,
+) {
+    encoder.copy_texture_to_buffer(
+        // source
+        wgpu::ImageCopyTexture {
+            aspect: wgpu::TextureAspect::All,
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+        },
+        // destination
+        wgpu::ImageCopyBuffer {
+            buffer: output_buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_bytes_per_row(texture.format(), texture.width())),
+                rows_per_image: Some(texture.height()),
+            },
+        },
+        // copy_size
+        texture.size(),
+    );
+}
+
+/// (11) Retrieve the texture buffer data from the GPU
+async fn retrieve_texture_buffer_data<'a>(
+    device: &wgpu::Device,
+    texture_buffer: &'a wgpu::Buffer,
+) -> wgpu::BufferView<'a> {
+    let buffer_slice = texture_buffer.slice(..);
+
+    // NOTE: We have to create the mapping THEN device.poll() before await the future.
+    // Otherwise the application will freeze.
+    let (tx, rx) = oneshot::channel();
+    buffer_slice.map_async(wgpu::MapMode::Read, move |result| {
+        tx.send(result).unwrap();
+    });
+    device.poll(wgpu::Maintain::Wait);
+    rx.await.unwrap().unwrap();
+
+    // Synchronously and immediately map a buffer for reading.
+    // Will panic if buffer_slice.map_async() did not finish yet.
+    buffer_slice.get_mapped_range()
+}
+
+/// (11) Save the texture data to disk, in a file format suited to its texture format:
+/// PNG for 8 bits formats, PFM (portable float map) for float formats.
+/// The alpha channel of float formats is dropped, since PFM only supports 1 or 3 channels.
+/// The data (with padded rows) is decoded by the texture_image crate shared with example 4,
+/// which also converts the half floats.
+fn save_texture_data(
+    name: &str,
+    format: wgpu::TextureFormat,
+    data: &[u8],
+    width: u32,
+    height: u32,
+) -> Result<(), DecodeError> {
+    let bytes_per_row = padded_bytes_per_row(format, width);
+    let img = TextureImage::decode(
+        format,
+        wgpu::TextureAspect::All,
+        data,
+        width,
+        height,
+        bytes_per_row,
+    )?;
+    match (format, &img.texels) {
+        (wgpu::TextureFormat::Rgba8Unorm, _) => {
+            img.to_dynamic_image().save(format!("{name}.png")).unwrap();
+        }
+        (
+            wgpu::TextureFormat::Rgba16Float | wgpu::TextureFormat::Rgba32Float,
+            Texels::F32(rgba),
+        ) => {
+            let rgb: Vec<f32> = rgba
+                .chunks_exact(4)
+                .flat_map(|rgba| [rgba[0], rgba[1], rgba[2]])
+                .collect();
+            save_pfm(&format!("{name}.pfm"), &rgb, 3, width, height);
+        }
+        (wgpu::TextureFormat::R32Float, Texels::F32(values)) => {
+            save_pfm(&format!("{name}.pfm"), values, 1, width, height);
+        }
+        // Not one of the G-buffer formats
+        _ => return Err(DecodeError::UnsupportedFormat(format)),
+    }
+    Ok(())
+}
+
+/// Save float data (1 or 3 channels) as a PFM image (portable float map).
+/// The header is followed by the raw floats, in little-endian (negative scale),
+/// and with the rows ordered from bottom to top.
+fn save_pfm(path: &str, data: &[f32], channels: usize, width: u32, height: u32) {
+    let magic = if channels == 3 { "PF" } else { "Pf" };
+    let mut bytes = format!("{magic}\n{width} {height}\n-1.0\n").into_bytes();
+    let row_length = channels * width as usize;
+    for row in data.chunks_exact(row_length).rev() {
+        bytes.extend(row.iter().flat_map(|x| x.to_le_bytes()));
+    }
+    std::fs::write(path, bytes).unwrap();
+}
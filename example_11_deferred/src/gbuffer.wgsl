@@ -0,0 +1,104 @@
+// Geometry pass of a deferred renderer.
+//
+// Instead of computing the final color of each fragment,
+// we store in several render targets (the G-buffer) all the surface attributes
+// that the lighting pass needs: albedo, normal and position.
+// So the lighting cost only depends on the number of pixels,
+// not on the number of triangles drawn (or overdrawn) in the scene.
+
+/// Perspective camera parameters.
+/// The vec3 are aligned to 16 bytes, so we fill the gaps with the scalar parameters.
+struct CameraParams {
+    eye: vec3<f32>,
+    focal: f32,
+    center: vec3<f32>,
+    ratio: f32,
+    near: f32,
+    far: f32,
+}
+
+@group(0) @binding(0) var<uniform> camera: CameraParams;
+
+/// Build a view matrix (extrinsics) looking from eye to center,
+/// with the same conventions as the camera example:
+/// X to the right, Y up, and Z forward (in front of the camera).
+fn look_at(eye: vec3<f32>, center: vec3<f32>) -> mat4x4<f32> {
+    let forward = normalize(center - eye);
+    let right = normalize(cross(vec3<f32>(0.0, 1.0, 0.0), forward));
+    let up = cross(forward, right);
+    return transpose(mat4x4f(
+        right.x  , right.y  , right.z  , -dot(right, eye)  ,
+        up.x     , up.y     , up.z     , -dot(up, eye)     ,
+        forward.x, forward.y, forward.z, -dot(forward, eye),
+        0.0      , 0.0      , 0.0      , 1.0               ,
+    ));
+}
+
+/// Projection from world coordinates to the clip coordinates of the camera
+fn camera_view_proj() -> mat4x4<f32> {
+    let focal = camera.focal;
+    let ratio = camera.ratio;
+    let near = camera.near;
+    let far = camera.far;
+    // Perspective projection matrix, see the camera example for details
+    let proj_mat = transpose(mat4x4f(
+        focal, 0.0          , 0.0               , 0.0                       ,
+        0.0  , focal * ratio, 0.0               , 0.0                       ,
+        0.0  , 0.0          , far / (far - near), -far * near / (far - near),
+        0.0  , 0.0          , 1.0               , 0.0                       ,
+    ));
+    return proj_mat * look_at(camera.eye, camera.center);
+}
+
+// Vertex shader
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) albedo: vec3<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) world_position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) albedo: vec3<f32>,
+}
+
+@vertex
+fn vertex_main(in: VertexInput) -> VertexOutput {
+    return VertexOutput(
+        // clip_position
+        camera_view_proj() * vec4<f32>(in.position, 1.0),
+        // world_position
+        in.position,
+        // normal
+        in.normal,
+        // albedo
+        in.albedo,
+    );
+}
+
+// Fragment shader
+//
+// Each output location corresponds to one color attachment of the render pass.
+// The 4th component of the albedo and position is 1.0 for the geometry,
+// and stays at its cleared value (0.0) for the background.
+
+struct GBufferOutput {
+    @location(0) albedo: vec4<f32>,
+    @location(1) normal: vec4<f32>,
+    @location(2) position: vec4<f32>,
+}
+
+@fragment
+fn fragment_main(in: VertexOutput) -> GBufferOutput {
+    return GBufferOutput(
+        // albedo
+        vec4<f32>(in.albedo, 1.0),
+        // normal, re-normalized after the interpolation
+        vec4<f32>(normalize(in.normal), 0.0),
+        // position
+        vec4<f32>(in.world_position, 1.0),
+    );
+}
@@ -0,0 +1,86 @@
+// Lighting pass of a deferred renderer.
+//
+// A single triangle covering the whole screen is drawn,
+// so that the fragment shader runs exactly once per pixel.
+// Each fragment reads the surface attributes stored in the G-buffer at its pixel,
+// and accumulates the contribution of every point light of the storage buffer.
+
+/// Perspective camera parameters.
+/// The vec3 are aligned to 16 bytes, so we fill the gaps with the scalar parameters.
+struct CameraParams {
+    eye: vec3<f32>,
+    focal: f32,
+    center: vec3<f32>,
+    ratio: f32,
+    near: f32,
+    far: f32,
+}
+
+/// Point light, lighting everything closer than its radius.
+/// Same layout trick as the camera: a scalar fills the gap after each vec3.
+struct PointLight {
+    position: vec3<f32>,
+    radius: f32,
+    color: vec3<f32>,
+    intensity: f32,
+}
+
+@group(0) @binding(0) var<uniform> camera: CameraParams;
+// A storage buffer can hold a runtime-sized array, whose length is known with arrayLength().
+@group(0) @binding(1) var<storage, read> lights: array<PointLight>;
+
+@group(1) @binding(0) var albedo_texture: texture_2d<f32>;
+@group(1) @binding(1) var normal_texture: texture_2d<f32>;
+@group(1) @binding(2) var position_texture: texture_2d<f32>;
+
+// Vertex shader
+
+@vertex
+fn vertex_main(@builtin(vertex_index) in_vertex_index: u32) -> @builtin(position) vec4<f32> {
+    // One triangle large enough to cover the [-1, 1] clip square,
+    // the parts outside of the screen being clipped.
+    let x = f32(i32(in_vertex_index & 1u) * 4 - 1);
+    let y = f32(i32(in_vertex_index >> 1u) * 4 - 1);
+    return vec4<f32>(x, y, 0.0, 1.0);
+}
+
+// Fragment shader
+
+@fragment
+fn fragment_main(@builtin(position) frag_position: vec4<f32>) -> @location(0) vec4<f32> {
+    // The fragment position is at the pixel center, such as (0.5, 0.5) for the first pixel.
+    // The G-buffer has the same size as the output, so we read exactly one texel,
+    // without sampler nor filtering.
+    let pixel = vec2<i32>(floor(frag_position.xy));
+    let albedo = textureLoad(albedo_texture, pixel, 0);
+
+    // The background was cleared with an alpha of 0.0
+    if albedo.a == 0.0 {
+        return vec4<f32>(0.1, 0.2, 0.3, 1.0);
+    }
+    let normal = textureLoad(normal_texture, pixel, 0).xyz;
+    let position = textureLoad(position_texture, pixel, 0).xyz;
+    let to_eye = normalize(camera.eye - position);
+
+    // Accumulate the diffuse and specular (Blinn-Phong) reflections of all the lights
+    let ambient = 0.05;
+    var color = ambient * albedo.rgb;
+    for (var i = 0u; i < arrayLength(&lights); i++) {
+        let light = lights[i];
+        let to_light = light.position - position;
+        let distance = length(to_light);
+        if distance >= light.radius {
+            continue;
+        }
+        // Smooth attenuation, reaching 0.0 at the light radius
+        let falloff = 1.0 - (distance * distance) / (light.radius * light.radius);
+        let radiance = light.color * light.intensity * falloff * falloff;
+
+        let light_dir = to_light / distance;
+        let diffuse = max(dot(normal, light_dir), 0.0);
+        let half_dir = normalize(light_dir + to_eye);
+        let specular = select(0.0, pow(max(dot(normal, half_dir), 0.0), 32.0), diffuse > 0.0);
+        color += radiance * (diffuse * albedo.rgb + 0.3 * specular);
+    }
+    return vec4<f32>(color, 1.0);
+}
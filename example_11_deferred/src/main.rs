@@ -0,0 +1,1134 @@
+//! This example shows how to light a scene with many point lights, with deferred rendering.
+//!
+//! Deferred rendering splits the rendering into two passes:
+//! 1. A "geometry" pass, writing the surface attributes of the scene into a G-buffer
+//!    (albedo, world-space normal and world-space position), as in the G-buffer example.
+//! 2. A "lighting" pass, drawing a single triangle covering the whole screen.
+//!    Its fragment shader reads the G-buffer textures at the current pixel,
+//!    and accumulates the contribution of all the point lights, stored in a storage buffer.
+//!
+//! So each light is only evaluated once per pixel of the output,
+//! whatever the number of triangles drawn (or overdrawn) by the geometry pass.
+//! The number of lights is configurable with `cargo run -- --lights 256` (64 by default),
+//! and their intensity with `--light-intensity 4.0` (1.0 by default).
+//!
+//! The accumulated light easily exceeds 1.0, which the 8 bits output clamps.
+//! Run with `--hdr rgba16float` (or `rgba32float`) to render the lighting into a float texture,
+//! saved as a Radiance "image.hdr", and tone mapped into "image.png" by a last pass.
+//! The tone mapping operator is chosen with `--tone-mapping aces|reinhard|clamp` (ACES by default),
+//! and the exposure with `--exposure 2.0` (1.0 by default).
+//! The steps of this minimal program are the following.
+//!
+//! 1. (async) Initialize the connection with the GPU device
+//! 2. Initialize the textures
+//!    1. **(new)** The G-buffer textures, which can also be bound to the lighting pass shaders
+//!    2. The output texture, that will serve as a write target for the lighting pass
+//!    3. **(new)** In HDR mode, a float texture that will serve as a write target
+//!       for the lighting pass instead
+//! 3. Initialize a wgpu Buffer where the output Texture (**(new)** and the HDR texture)
+//!    will be transferred to
+//! 4. Initialize a wgpu Texture object that will serve as a write target for the depth
+//! 5. Load the OBJ bunny and create a ground plane
+//!    1. Compute the vertex normals of the bunny, and assign an albedo to each vertex
+//!    2. Create and initialize the vertex and index buffers of each mesh
+//! 6. Load the shader modules of the geometry and lighting passes
+//! 7. Define our geometry pipeline, with one color target per G-buffer texture
+//! 8. Create the camera
+//!    1. Put the perspective projection camera parameters into a uniform buffer
+//!    2. Create a bind group and let WebGPU derive the layout implicitely
+//! 9. **(new)** Generate the point lights, and put them into a storage buffer
+//! 10. **(new)** Define our lighting pipeline:
+//!     1. Explicitly create the bind group layouts, since the G-buffer float textures are not filterable
+//!     2. Create the pipeline, with no vertex buffer and a single color target
+//!     3. Create the bind groups of the camera and lights, and of the G-buffer textures
+//!     4. **(new)** In HDR mode, define the tone mapping pipeline and its bind group
+//! 11. Define our command encoder:
+//!     1. Start by defining the geometry pass, rendering into the G-buffer textures
+//!     2. **(new)** Then define the lighting pass:
+//!        - Link to the output texture
+//!        - Link to the lighting pipeline
+//!        - Provide the camera and lights, and G-buffer bind groups
+//!        - Draw a single triangle covering the whole screen
+//!     3. **(new)** In HDR mode, draw the tone mapping pass from the HDR texture into the output texture
+//!     4. Add a command to copy the output texture (**(new)** and the HDR texture) into the buffers
+//! 12. Submit our commands to the device queue
+//! 13. (async) Transfer the output buffer into an image we can save to disk,
+//!     **(new)** and the HDR buffer into a Radiance HDR image
+
+use texture_image::{padded_bytes_per_row, Texels, TextureImage}; // Decoding of the readbacks
+use wgpu::util::DeviceExt; // Utility trait to create and initialize buffers with device.create_buffer_init()
+
+/// The G-buffer targets, with their name and format
+const GBUFFER_TARGETS: [(&str, wgpu::TextureFormat); 3] = [
+    ("albedo", wgpu::TextureFormat::Rgba8Unorm),
+    ("normal", wgpu::TextureFormat::Rgba16Float),
+    ("position", wgpu::TextureFormat::Rgba32Float),
+];
+
+fn main() {
+    // Make the main async
+    pollster::block_on(run());
+}
+
+async fn run() {
+    // Lighting settings, provided as command line arguments
+    let args: Vec<String> = std::env::args().collect();
+    let arg_value = |name: &str| {
+        let position = args.iter().position(|arg| arg == name)?;
+        Some(
+            args.get(position + 1)
+                .expect("Missing argument value")
+                .clone(),
+        )
+    };
+    let num_lights: u32 = arg_value("--lights").map_or(64, |value| value.parse().unwrap());
+    assert!(num_lights > 0, "At least one light is needed");
+    let light_intensity: f32 =
+        arg_value("--light-intensity").map_or(1.0, |value| value.parse().unwrap());
+    let hdr_format = arg_value("--hdr").map(|value| match value.as_str() {
+        "rgba16float" => wgpu::TextureFormat::Rgba16Float,
+        "rgba32float" => wgpu::TextureFormat::Rgba32Float,
+        _ => panic!("Unknown HDR format: {value}"),
+    });
+    let tone_mapping = arg_value("--tone-mapping").unwrap_or("aces".to_string());
+    assert!(
+        ["aces", "reinhard", "clamp"].contains(&tone_mapping.as_str()),
+        "Unknown tone mapping: {tone_mapping}"
+    );
+    let exposure: f32 = arg_value("--exposure").map_or(1.0, |value| value.parse().unwrap());
+
+    // (1) Initializing WebGPU
+    println!("Initializing WebGPU ...");
+    let (device, queue) = init_wgpu_device().await.unwrap();
+
+    // (2.1) Initialize the G-buffer textures
+    let width = 512;
+    let height = 512;
+    let gbuffer_textures: Vec<wgpu::Texture> = GBUFFER_TARGETS
+        .iter()
+        .map(|&(name, format)| init_gbuffer_texture(&device, name, width, height, format))
+        .collect();
+    let gbuffer_views: Vec<wgpu::TextureView> = gbuffer_textures
+        .iter()
+        .map(|texture| texture.create_view(&Default::default()))
+        .collect();
+
+    // (2.2) Initialize the output texture of the lighting pass
+    let texture = init_output_texture(&device, width, height);
+    let texture_view = texture.create_view(&Default::default());
+
+    // (2.3) In HDR mode, initialize the float texture of the lighting pass
+    let hdr_texture = hdr_format.map(|format| init_hdr_texture(&device, width, height, format));
+    let hdr_texture_view = hdr_texture
+        .as_ref()
+        .map(|hdr_texture| hdr_texture.create_view(&Default::default()));
+
+    // (3) Initialize the output buffers
+    let output_buffer = device.create_buffer(&create_texture_buffer_descriptor(&texture));
+    let hdr_buffer = hdr_texture
+        .as_ref()
+        .map(|hdr_texture| device.create_buffer(&create_texture_buffer_descriptor(hdr_texture)));
+
+    // (4) Initialize the depth texture
+    let depth_texture = init_depth_texture(&device, width, height);
+    let depth_texture_view = depth_texture.create_view(&Default::default());
+
+    // (5) Load the OBJ bunny
+    let (models, _) = tobj::load_obj("bunny.obj", &tobj::GPU_LOAD_OPTIONS).unwrap();
+    let bunny = &models[0].mesh;
+
+    // (5.1) The OBJ has no normals, so we compute them from its triangles
+    let bunny_normals = compute_vertex_normals(&bunny.positions, &bunny.indices);
+    let bunny_vertices: Vec<Vertex> = bunny
+        .positions
+        .chunks_exact(3)
+        .zip(&bunny_normals)
+        .map(|(position, &normal)| Vertex {
+            position: [position[0], position[1], position[2]],
+            normal,
+            albedo: [0.9, 0.9, 0.9],
+        })
+        .collect();
+    // Checkerboard ground under the bunny, at the height of its lowest point
+    let (ground_vertices, ground_indices) = ground_mesh(8, 0.3, 0.033);
+
+    // (5.2) Create and initialize the vertex and index buffers of both meshes
+    // (needs the DeviceExt trait)
+    let meshes = [
+        Mesh::new(&device, &bunny_vertices, &bunny.indices),
+        Mesh::new(&device, &ground_vertices, &ground_indices),
+    ];
+
+    // (6) Load the shader modules of both passes
+    let gbuffer_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("gbuffer_shader"),
+        source: wgpu::ShaderSource::Wgsl(include_str!("gbuffer.wgsl").into()),
+    });
+    let lighting_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("lighting_shader"),
+        source: wgpu::ShaderSource::Wgsl(include_str!("lighting.wgsl").into()),
+    });
+
+    // (7) Define the geometry pipeline, with one color target per G-buffer texture
+    let gbuffer_formats: Vec<wgpu::TextureFormat> = gbuffer_textures
+        .iter()
+        .map(|texture| texture.format())
+        .collect();
+    let gbuffer_pipeline = build_gbuffer_pipeline(
+        &device,
+        &gbuffer_shader,
+        &gbuffer_formats,
+        vtx_buffer_layout(),
+    );
+
+    // (8.1) Put the perspective projection camera into a uniform buffer
+    let camera = Camera {
+        eye: [0.0, 0.25, -0.35],
+        focal_length: 2.0,
+        center: [-0.02, 0.09, 0.0],
+        aspect_ratio: (width as f32) / (height as f32),
+        near_plane: 0.1,
+        far_plane: 2.0,
+        _padding: [0.0; 2],
+    };
+    let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Camera Buffer"),
+        contents: bytemuck::bytes_of(&camera),
+        usage: wgpu::BufferUsages::UNIFORM,
+    });
+
+    // (8.2) For the geometry pass, we let WebGPU derive the bind group layout implicitely.
+    let gbuffer_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout: &gbuffer_pipeline.get_bind_group_layout(0),
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: camera_buffer.as_entire_binding(),
+        }],
+        label: Some("gbuffer_bind_group"),
+    });
+
+    // (9) Generate the point lights, and put them into a storage buffer.
+    // Contrary to uniform buffers, storage buffers can be large,
+    // and their size does not need to be known by the shader.
+    let lights = point_lights(num_lights, light_intensity);
+    let lights_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Lights Buffer"),
+        contents: bytemuck::cast_slice(&lights),
+        usage: wgpu::BufferUsages::STORAGE,
+    });
+
+    // (10.1) Create the bind group layouts of the lighting pass
+    let (scene_layout, gbuffer_layout) = lighting_bind_group_layouts(&device);
+
+    // (10.2) Define the lighting pipeline, rendering into the HDR texture in HDR mode
+    let lighting_format = hdr_format.unwrap_or(texture.format());
+    let lighting_pipeline = build_lighting_pipeline(
+        &device,
+        &lighting_shader,
+        lighting_format,
+        &[&scene_layout, &gbuffer_layout],
+    );
+
+    // (10.3) Create the bind groups of the lighting pass
+    let scene_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout: &scene_layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: lights_buffer.as_entire_binding(),
+            },
+        ],
+        label: Some("scene_bind_group"),
+    });
+    let gbuffer_entries: Vec<wgpu::BindGroupEntry> = gbuffer_views
+        .iter()
+        .enumerate()
+        .map(|(binding, view)| wgpu::BindGroupEntry {
+            binding: binding as u32,
+            resource: wgpu::BindingResource::TextureView(view),
+        })
+        .collect();
+    let gbuffer_textures_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout: &gbuffer_layout,
+        entries: &gbuffer_entries,
+        label: Some("gbuffer_textures_bind_group"),
+    });
+
+    // (10.4) In HDR mode, define the tone mapping pass
+    let tone_mapping = hdr_texture_view.as_ref().map(|hdr_texture_view| {
+        ToneMapping::new(
+            &device,
+            hdr_texture_view,
+            texture.format(),
+            &tone_mapping,
+            exposure,
+        )
+    });
+
+    // Initialize a command encoder
+    let mut encoder = device.create_command_encoder(&Default::default());
+
+    // (11.1) Draw the geometry pass into the G-buffer
+    let gbuffer_view_refs: Vec<&wgpu::TextureView> = gbuffer_views.iter().collect();
+    draw_gbuffer_pipeline(
+        &mut encoder,
+        &gbuffer_pipeline,
+        &gbuffer_view_refs,
+        &depth_texture_view,
+        &gbuffer_bind_group,
+        &meshes,
+    );
+
+    // (11.2) Draw the lighting pass into the output texture.
+    // The G-buffer textures are written by the previous pass and read by this one,
+    // WebGPU takes care of the synchronization between both.
+    draw_lighting_pipeline(
+        &mut encoder,
+        &lighting_pipeline,
+        hdr_texture_view.as_ref().unwrap_or(&texture_view),
+        &scene_bind_group,
+        &gbuffer_textures_bind_group,
+    );
+
+    // (11.3) In HDR mode, tone map the HDR texture into the output texture
+    if let Some(tone_mapping) = &tone_mapping {
+        tone_mapping.draw(&mut encoder, &texture_view);
+    }
+
+    // (11.4) Add commands to copy the output (and HDR) textures into the buffers
+    copy_texture_to_buffer(&mut encoder, &texture, &output_buffer);
+    if let (Some(hdr_texture), Some(hdr_buffer)) = (&hdr_texture, &hdr_buffer) {
+        copy_texture_to_buffer(&mut encoder, hdr_texture, hdr_buffer);
+    }
+
+    // (12) Finalize the command encoder and send it to the queue
+    println!("Submitting commands to the queue ...");
+    queue.submit(Some(encoder.finish()));
+
+    // (13) Transfer texture buffer into a CPU image and save to disk.
+    // New scope to encapsulate the BufferView and drop it before unmapping.
+    {
+        println!("Saving the image to disk ...");
+        let data = retrieve_texture_buffer_data(&device, &output_buffer).await;
+        let img = decode_texture_data(&texture, &data).unwrap();
+        img.to_dynamic_image().save("image.png").unwrap();
+
+        // In HDR mode, also save the raw HDR values
+        if let (Some(hdr_texture), Some(hdr_buffer)) = (&hdr_texture, &hdr_buffer) {
+            println!("Saving the HDR image to disk ...");
+            let data = retrieve_texture_buffer_data(&device, hdr_buffer).await;
+            save_hdr_image("image.hdr", hdr_texture, &data)
+                .unwrap_or_else(|error| panic!("Cannot save the HDR image: {error}"));
+        }
+    }
+    // Flushes any pending write operations and unmaps the buffers from host memory
+    output_buffer.unmap();
+    if let Some(hdr_buffer) = &hdr_buffer {
+        hdr_buffer.unmap();
+    }
+
+    println!("Terminating the program ...")
+}
+
+/// (1) Initializing WebGPU
+async fn init_wgpu_device() -> Result<(wgpu::Device, wgpu::Queue), wgpu::RequestDeviceError> {
+    // Start an "Instance", which is the context for all things wgpu.
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: wgpu::Backends::all(),
+        ..Default::default()
+    });
+
+    // An "Adapter" is a handle to a physical graphics/compute device.
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(), // None | LowPower | HighPerformance
+            compatible_surface: None,
+            force_fallback_adapter: false, // If needed to force CPU fallback?
+        })
+        .await
+        .unwrap();
+
+    // Request a connection to a physical device,
+    // and also access to the queue for its command buffers.
+    // It is possible, if necessary, to add a description of required features.
+    adapter.request_device(&Default::default(), None).await
+}
+
+/// (2.1) Initialize a texture of the G-buffer
+fn init_gbuffer_texture(
+    device: &wgpu::Device,
+    name: &str,
+    width: u32,
+    height: u32,
+    format: wgpu::TextureFormat,
+) -> wgpu::Texture {
+    let texture_desc = wgpu::TextureDescriptor {
+        label: Some(name),
+        // The texture size. (layers is set to 1)
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        dimension: wgpu::TextureDimension::D2,
+        mip_level_count: 1, // the number of mip levels the texture will contain
+        sample_count: 1,    // sample_count > 1 would indicate a multisampled texture
+        // Each G-buffer target has its own format
+        format,
+        // RENDER_ATTACHMENT -> so that the GPU can render to the texture
+        // TEXTURE_BINDING -> so that the lighting pass shaders can read the texture
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        // Specify the allowed formats when calling "texture.create_view()"
+        view_formats: &[],
+    };
+    device.create_texture(&texture_desc)
+}
+
+/// (2.2) Initialize the output texture
+fn init_output_texture(device: &wgpu::Device, width: u32, height: u32) -> wgpu::Texture {
+    let texture_desc = wgpu::TextureDescriptor {
+        label: Some("output_texture"),
+        // The texture size. (layers is set to 1)
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        dimension: wgpu::TextureDimension::D2,
+        mip_level_count: 1, // the number of mip levels the texture will contain
+        sample_count: 1,    // sample_count > 1 would indicate a multisampled texture
+        // Use RGBA format for the output
+        format: wgpu::TextureFormat::Rgba8Unorm,
+        // RENDER_ATTACHMENT -> so that the GPU can render to the texture
+        // COPY_SRC -> so that we can pull data out of the texture
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        // Specify the allowed formats when calling "texture.create_view()"
+        view_formats: &[],
+    };
+    device.create_texture(&texture_desc)
+}
+
+/// (2.3) Initialize the HDR texture, written by the lighting pass and read by the tone mapping pass
+fn init_hdr_texture(
+    device: &wgpu::Device,
+    width: u32,
+    height: u32,
+    format: wgpu::TextureFormat,
+) -> wgpu::Texture {
+    let texture_desc = wgpu::TextureDescriptor {
+        label: Some("hdr_texture"),
+        // The texture size. (layers is set to 1)
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        dimension: wgpu::TextureDimension::D2,
+        mip_level_count: 1, // the number of mip levels the texture will contain
+        sample_count: 1,    // sample_count > 1 would indicate a multisampled texture
+        // Rgba16Float or Rgba32Float, holding values above 1.0
+        format,
+        // RENDER_ATTACHMENT -> so that the GPU can render to the texture
+        // TEXTURE_BINDING -> so that the tone mapping shaders can read the texture
+        // COPY_SRC -> so that we can pull data out of the texture
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+            | wgpu::TextureUsages::TEXTURE_BINDING
+            | wgpu::TextureUsages::COPY_SRC,
+        // Specify the allowed formats when calling "texture.create_view()"
+        view_formats: &[],
+    };
+    device.create_texture(&texture_desc)
+}
+
+/// (3) Create a buffer descriptor of the correct size for the texture,
+/// with its rows padded to the alignment required by texture copies
+fn create_texture_buffer_descriptor(texture: &wgpu::Texture) -> wgpu::BufferDescriptor<'_> {
+    let bytes_per_row = padded_bytes_per_row(texture.format(), texture.width());
+    wgpu::BufferDescriptor {
+        size: (bytes_per_row * texture.height()).into(),
+        usage: wgpu::BufferUsages::COPY_DST
+            // this tells wpgu that we want to read this buffer from the cpu
+            | wgpu::BufferUsages::MAP_READ,
+        label: None,
+        mapped_at_creation: false,
+    }
+}
+
+/// (4) Initialize a depth texture
+fn init_depth_texture(device: &wgpu::Device, width: u32, height: u32) -> wgpu::Texture {
+    let texture_desc = wgpu::TextureDescriptor {
+        label: Some("depth_texture"),
+        // The texture size. (layers is set to 1)
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        dimension: wgpu::TextureDimension::D2,
+        mip_level_count: 1, // the number of mip levels the texture will contain
+        sample_count: 1,    // sample_count > 1 would indicate a multisampled texture
+        // Use a 32 bits float format for the depth
+        format: wgpu::TextureFormat::Depth32Float,
+        // RENDER_ATTACHMENT -> so that the GPU can render to the texture
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        // Specify the allowed formats when calling "texture.create_view()"
+        view_formats: &[],
+    };
+    device.create_texture(&texture_desc)
+}
+
+/// A vertex of our meshes.
+/// Bytemuck is used to enable easy casting to a &[u8].
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct Vertex {
+    position: [f32; 3],
+    normal: [f32; 3],
+    albedo: [f32; 3],
+}
+
+/// Define the layout of Vertex buffers
+fn vtx_buffer_layout() -> wgpu::VertexBufferLayout<'static> {
+    // position, normal and albedo, at shader locations 0, 1 and 2
+    const ATTRIBUTES: [wgpu::VertexAttribute; 3] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x3];
+    wgpu::VertexBufferLayout {
+        // array_stride is the bytes count between two vertices
+        array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &ATTRIBUTES,
+    }
+}
+
+/// (5.1) Compute vertex normals as the sum of the normals of their adjacent triangles.
+/// The cross product of two edges is proportional to the triangle area,
+/// so larger triangles have more weight.
+fn compute_vertex_normals(positions: &[f32], indices: &[u32]) -> Vec<[f32; 3]> {
+    let position = |i: u32| {
+        let i = 3 * i as usize;
+        [positions[i], positions[i + 1], positions[i + 2]]
+    };
+    let sub = |a: [f32; 3], b: [f32; 3]| [a[0] - b[0], a[1] - b[1], a[2] - b[2]];
+    let mut normals = vec![[0.0; 3]; positions.len() / 3];
+    for triangle in indices.chunks_exact(3) {
+        let p0 = position(triangle[0]);
+        let e1 = sub(position(triangle[1]), p0);
+        let e2 = sub(position(triangle[2]), p0);
+        let face_normal = [
+            e1[1] * e2[2] - e1[2] * e2[1],
+            e1[2] * e2[0] - e1[0] * e2[2],
+            e1[0] * e2[1] - e1[1] * e2[0],
+        ];
+        for &i in triangle {
+            let normal: &mut [f32; 3] = &mut normals[i as usize];
+            for k in 0..3 {
+                normal[k] += face_normal[k];
+            }
+        }
+    }
+    for normal in normals.iter_mut() {
+        let length = normal.iter().map(|x| x * x).sum::<f32>().sqrt();
+        if length > 0.0 {
+            normal.iter_mut().for_each(|x| *x /= length);
+        }
+    }
+    normals
+}
+
+/// (5.1) Create a horizontal square ground, made of tiles x tiles squares with alternating albedos.
+/// Each tile has its own 4 vertices, so that their albedo is not interpolated with their neighbours.
+fn ground_mesh(tiles: u32, half_size: f32, height: f32) -> (Vec<Vertex>, Vec<u32>) {
+    let tile_size = 2.0 * half_size / tiles as f32;
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    for i in 0..tiles {
+        for j in 0..tiles {
+            let albedo = if (i + j) % 2 == 0 {
+                [0.8, 0.8, 0.8]
+            } else {
+                [0.3, 0.3, 0.3]
+            };
+            let x = -half_size + i as f32 * tile_size;
+            let z = -half_size + j as f32 * tile_size;
+            let first = vertices.len() as u32;
+            for (dx, dz) in [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)] {
+                vertices.push(Vertex {
+                    position: [x + dx * tile_size, height, z + dz * tile_size],
+                    normal: [0.0, 1.0, 0.0],
+                    albedo,
+                });
+            }
+            indices.extend([first, first + 1, first + 2, first, first + 2, first + 3]);
+        }
+    }
+    (vertices, indices)
+}
+
+/// (5.2) The buffers of a mesh to draw
+struct Mesh {
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    num_indices: u32,
+}
+
+impl Mesh {
+    /// (5.2) Create and initialize the vertex and index buffers of a mesh
+    fn new(device: &wgpu::Device, vertices: &[Vertex], indices: &[u32]) -> Self {
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertex Buffer"),
+            contents: bytemuck::cast_slice(vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Index Buffer"),
+            contents: bytemuck::cast_slice(indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        Self {
+            vertex_buffer,
+            index_buffer,
+            num_indices: indices.len() as u32,
+        }
+    }
+
+    fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.draw_indexed(0..self.num_indices, 0, 0..1);
+    }
+}
+
+/// Perspective camera, looking from eye to center.
+/// Bytemuck is used to enable easy casting to a &[u8].
+/// The fields order and padding follow the alignment rules of the shader struct
+/// (vec3 are aligned to 16 bytes, and the struct size is a multiple of 16 bytes).
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct Camera {
+    eye: [f32; 3],
+    focal_length: f32,
+    center: [f32; 3],
+    aspect_ratio: f32,
+    near_plane: f32,
+    far_plane: f32,
+    _padding: [f32; 2],
+}
+
+/// (7) Define the geometry pipeline, with one color target per G-buffer texture format
+fn build_gbuffer_pipeline(
+    device: &wgpu::Device,
+    shader_module: &wgpu::ShaderModule,
+    texture_formats: &[wgpu::TextureFormat],
+    vertex_buffer_layout: wgpu::VertexBufferLayout,
+) -> wgpu::RenderPipeline {
+    // The order of the targets corresponds to the locations of the fragment shader outputs.
+    // Float32 formats do not support blending,
+    // but replacing the previous value is exactly what "no blending" does anyway.
+    let targets: Vec<_> = texture_formats
+        .iter()
+        .map(|&format| {
+            Some(wgpu::ColorTargetState {
+                format,
+                blend: None,
+                write_mask: wgpu::ColorWrites::ALL,
+            })
+        })
+        .collect();
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Geometry Pipeline"),
+        layout: None, // "auto"
+        vertex: wgpu::VertexState {
+            module: shader_module,
+            entry_point: "vertex_main",
+            buffers: &[vertex_buffer_layout],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader_module,
+            entry_point: "fragment_main",
+            targets: &targets,
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            // The ground is a single sided square, so no culling
+            cull_mode: None,
+            // Setting this to Line requires Features::POLYGON_MODE_LINE
+            polygon_mode: wgpu::PolygonMode::Fill,
+            // Requires Features::DEPTH_CLIP_CONTROL
+            unclipped_depth: false,
+            // Requires Features::CONSERVATIVE_RASTERIZATION
+            conservative: false,
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: wgpu::TextureFormat::Depth32Float,
+            // Enable WebGPU to write the depth (position.z) to the provided texture
+            depth_write_enabled: true,
+            // Keep the depth value closest to us (lower values)
+            depth_compare: wgpu::CompareFunction::Less,
+            // Not using stencil stuff
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        // If the pipeline will be used with a multiview render pass, this
+        // indicates how many array layers the attachments will have.
+        multiview: None,
+    })
+}
+
+/// A point light, lighting everything closer than its radius.
+/// Bytemuck is used to enable easy casting to a &[u8].
+/// As for the camera, the fields order follows the alignment rules of the shader struct.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct PointLight {
+    position: [f32; 3],
+    radius: f32,
+    color: [f32; 3],
+    intensity: f32,
+}
+
+/// (9) Spread the lights with various colors over the ground, along a spiral.
+/// The radius of the lights decreases with their number,
+/// so that each pixel is roughly lit by the same number of lights.
+fn point_lights(count: u32, intensity: f32) -> Vec<PointLight> {
+    let golden_angle = std::f32::consts::PI * (3.0 - 5f32.sqrt());
+    let radius = 0.6 / (count as f32).sqrt();
+    (0..count)
+        .map(|i| {
+            // t goes from 0 to 1, from the center to the border of the ground
+            let t = (i as f32 + 0.5) / count as f32;
+            let angle = i as f32 * golden_angle;
+            let distance = 0.3 * t.sqrt();
+            // Rainbow colors, with a hue depending on t
+            let hue = 2.0 * std::f32::consts::PI * t;
+            let channel = |offset: f32| 0.5 + 0.5 * (hue - offset).cos();
+            PointLight {
+                position: [
+                    distance * angle.cos(),
+                    0.033 + 0.5 * radius,
+                    distance * angle.sin(),
+                ],
+                radius,
+                color: [channel(0.0), channel(2.094), channel(4.189)],
+                intensity,
+            }
+        })
+        .collect()
+}
+
+/// (10.1) Explicitly create the bind group layouts of the lighting pass.
+///
+/// With an implicit ("auto") layout, WebGPU would consider the float textures as filterable.
+/// But 32 bits float formats, like the position, are not filterable without
+/// `Features::FLOAT32_FILTERABLE`, so they could not be bound.
+/// We only read exact texels with textureLoad() anyway, so we declare them as non-filterable.
+fn lighting_bind_group_layouts(
+    device: &wgpu::Device,
+) -> (wgpu::BindGroupLayout, wgpu::BindGroupLayout) {
+    // Group 0: the camera uniform buffer, and the lights storage buffer
+    let scene_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("scene_bind_group_layout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: true },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+        ],
+    });
+
+    // Group 1: one texture per G-buffer target
+    let gbuffer_entries: Vec<wgpu::BindGroupLayoutEntry> = (0..GBUFFER_TARGETS.len())
+        .map(|binding| wgpu::BindGroupLayoutEntry {
+            binding: binding as u32,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        })
+        .collect();
+    let gbuffer_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("gbuffer_bind_group_layout"),
+        entries: &gbuffer_entries,
+    });
+
+    (scene_layout, gbuffer_layout)
+}
+
+/// (10.2) Define the lighting pipeline.
+/// There is no vertex buffer nor depth, the full screen triangle is hardcoded in the vertex shader.
+fn build_lighting_pipeline(
+    device: &wgpu::Device,
+    shader_module: &wgpu::ShaderModule,
+    texture_format: wgpu::TextureFormat,
+    bind_group_layouts: &[&wgpu::BindGroupLayout],
+) -> wgpu::RenderPipeline {
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Lighting Pipeline Layout"),
+        bind_group_layouts,
+        push_constant_ranges: &[],
+    });
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Lighting Pipeline"),
+        layout: Some(&pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: shader_module,
+            entry_point: "vertex_main",
+            buffers: &[],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader_module,
+            entry_point: "fragment_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format: texture_format,
+                // No blending, since 32 bits float formats are not blendable
+                // without Features::FLOAT32_BLENDABLE
+                blend: None,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
+
+/// Tone mapping parameters.
+/// Bytemuck is used to enable easy casting to a &[u8].
+/// Uniform buffers are padded to 16 bytes.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct ToneMappingParams {
+    exposure: f32,
+    _padding: [f32; 3],
+}
+
+/// (10.4) The tone mapping pass, from the HDR texture to the output texture
+struct ToneMapping {
+    pipeline: wgpu::RenderPipeline,
+    bind_group: wgpu::BindGroup,
+}
+
+impl ToneMapping {
+    /// Define the tone mapping pass, with the operator "aces", "reinhard" or "clamp"
+    fn new(
+        device: &wgpu::Device,
+        hdr_texture_view: &wgpu::TextureView,
+        output_format: wgpu::TextureFormat,
+        operator: &str,
+        exposure: f32,
+    ) -> Self {
+        let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("tone_mapping_shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("tone_mapping.wgsl").into()),
+        });
+
+        // As for the G-buffer, Rgba32Float textures are not filterable,
+        // so we create the layout explicitly
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("tone_mapping_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Tone Mapping Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Tone Mapping Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader_module,
+                entry_point: "vertex_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader_module,
+                // One entry point per operator
+                entry_point: &format!("fragment_{operator}"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: output_format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let params = ToneMappingParams {
+            exposure,
+            _padding: [0.0; 3],
+        };
+        let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Tone Mapping Buffer"),
+            contents: bytemuck::bytes_of(&params),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(hdr_texture_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: params_buffer.as_entire_binding(),
+                },
+            ],
+            label: Some("tone_mapping_bind_group"),
+        });
+
+        Self {
+            pipeline,
+            bind_group,
+        }
+    }
+
+    /// (11.3) Draw the tone mapping pass into the output texture,
+    /// with the full screen triangle covering every pixel
+    fn draw(&self, encoder: &mut wgpu::CommandEncoder, texture_view: &wgpu::TextureView) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Tone Mapping Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: texture_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            ..Default::default()
+        });
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+/// (11.1) Draw the geometry pipeline into the G-buffer textures.
+fn draw_gbuffer_pipeline(
+    encoder: &mut wgpu::CommandEncoder,
+    pipeline: &wgpu::RenderPipeline,
+    texture_views: &[&wgpu::TextureView],
+    depth_texture_view: &wgpu::TextureView,
+    camera_bind_group: &wgpu::BindGroup,
+    meshes: &[Mesh],
+) {
+    // One color attachment per G-buffer texture, all cleared to 0.
+    // So the background has an albedo alpha and a position W of 0.
+    let color_attachments: Vec<_> = texture_views
+        .iter()
+        .map(|&view| {
+            Some(wgpu::RenderPassColorAttachment {
+                view,                 // the output texture
+                resolve_target: None, // only useful for multisampling
+                ops: wgpu::Operations {
+                    // "load" specifies how data is read.
+                    // "Clear" is the lightest way to initialize the texture.
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    // whether data is written to or not. Store | Discard
+                    store: wgpu::StoreOp::Store,
+                },
+            })
+        })
+        .collect();
+
+    // Setup the pass that will render into our textures
+    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("Geometry Pass"),
+        color_attachments: &color_attachments,
+        depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+            view: depth_texture_view,
+            depth_ops: Some(wgpu::Operations {
+                load: wgpu::LoadOp::Clear(1.0),
+                store: wgpu::StoreOp::Store,
+            }),
+            stencil_ops: None,
+        }),
+        ..Default::default()
+    });
+
+    // Draw the render pass for our pipeline
+    render_pass.set_pipeline(pipeline);
+    render_pass.set_bind_group(0, camera_bind_group, &[]);
+    for mesh in meshes {
+        mesh.draw(&mut render_pass);
+    }
+}
+
+/// (11.2) Draw the lighting pipeline into the output texture.
+fn draw_lighting_pipeline(
+    encoder: &mut wgpu::CommandEncoder,
+    pipeline: &wgpu::RenderPipeline,
+    texture_view: &wgpu::TextureView,
+    scene_bind_group: &wgpu::BindGroup,
+    gbuffer_bind_group: &wgpu::BindGroup,
+) {
+    // Setup the pass that will render into our output texture.
+    // Every pixel is covered by the full screen triangle, so there is no need to clear it.
+    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("Lighting Pass"),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: texture_view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Load,
+                store: wgpu::StoreOp::Store,
+            },
+        })],
+        ..Default::default()
+    });
+
+    // Draw the 3 vertices of the full screen triangle
+    render_pass.set_pipeline(pipeline);
+    render_pass.set_bind_group(0, scene_bind_group, &[]);
+    render_pass.set_bind_group(1, gbuffer_bind_group, &[]);
+    render_pass.draw(0..3, 0..1);
+}
+
+/// (11.4) Copy the texture output into a buffer
+fn copy_texture_to_buffer(
+    encoder: &mut wgpu::CommandEncoder,
+    texture: &wgpu::Texture,
+    output_buffer: &wgpu::Buffer,
+) {
+    encoder.copy_texture_to_buffer(
+        // source
+        wgpu::ImageCopyTexture {
+            aspect: wgpu::TextureAspect::All,
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+        },
+        // destination
+        wgpu::ImageCopyBuffer {
+            buffer: output_buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_bytes_per_row(texture.format(), texture.width())),
+                rows_per_image: Some(texture.height()),
+            },
+        },
+        // copy_size
+        texture.size(),
+    );
+}
+
+/// (13) Retrieve the texture buffer data from the GPU
+async fn retrieve_texture_buffer_data<'a>(
+    device: &wgpu::Device,
+    texture_buffer: &'a wgpu::Buffer,
+) -> wgpu::BufferView<'a> {
+    let buffer_slice = texture_buffer.slice(..);
+
+    // NOTE: We have to create the mapping THEN device.poll() before await the future.
+    // Otherwise the application will freeze.
+    let (tx, rx) = oneshot::channel();
+    buffer_slice.map_async(wgpu::MapMode::Read, move |result| {
+        tx.send(result).unwrap();
+    });
+    device.poll(wgpu::Maintain::Wait);
+    rx.await.unwrap().unwrap();
+
+    // Synchronously and immediately map a buffer for reading.
+    // Will panic if buffer_slice.map_async() did not finish yet.
+    buffer_slice.get_mapped_range()
+}
+
+/// (13) Decode the data copied from a texture, with its padded rows, according to its format.
+/// The decoding (including the half floats) is done by the texture_image crate shared with example 4.
+fn decode_texture_data(
+    texture: &wgpu::Texture,
+    data: &[u8],
+) -> Result<TextureImage, texture_image::DecodeError> {
+    let (format, width, height) = (texture.format(), texture.width(), texture.height());
+    let bytes_per_row = padded_bytes_per_row(format, width);
+    TextureImage::decode(
+        format,
+        wgpu::TextureAspect::All,
+        data,
+        width,
+        height,
+        bytes_per_row,
+    )
+}
+
+/// (13) Save the data of a HDR texture (Rgba16Float or Rgba32Float) as a Radiance HDR image,
+/// which stores RGB floats (without alpha) with a shared exponent
+fn save_hdr_image(
+    path: &str,
+    texture: &wgpu::Texture,
+    data: &[u8],
+) -> Result<(), texture_image::DecodeError> {
+    let img = decode_texture_data(texture, data)?;
+    let rgb: Vec<image::Rgb<f32>> = match (texture.format(), &img.texels) {
+        (
+            wgpu::TextureFormat::Rgba16Float | wgpu::TextureFormat::Rgba32Float,
+            Texels::F32(rgba),
+        ) => rgba
+            .chunks_exact(4)
+            .map(|rgba| image::Rgb([rgba[0], rgba[1], rgba[2]]))
+            .collect(),
+        // Not one of the HDR formats
+        (format, _) => return Err(texture_image::DecodeError::UnsupportedFormat(format)),
+    };
+    let file = std::io::BufWriter::new(std::fs::File::create(path).unwrap());
+    image::codecs::hdr::HdrEncoder::new(file)
+        .encode(&rgb, img.width as usize, img.height as usize)
+        .unwrap();
+    Ok(())
+}
@@ -0,0 +1,58 @@
+// Tone mapping pass, from the HDR output of the lighting pass to a displayable image.
+//
+// With many lights, the accumulated radiance easily exceeds 1.0, which an 8 bits texture clamps,
+// burning the brightest areas to white or to saturated colors.
+// In HDR mode, the lighting pass renders into a float texture instead,
+// and this pass maps its values into [0, 1] with a tone mapping operator.
+// As the lighting pass, it draws a single triangle covering the whole screen.
+// There is one fragment entry point per operator.
+
+/// Tone mapping parameters (padded to 16 bytes on the Rust side)
+struct ToneMappingParams {
+    // Multiplier of the HDR values before the tone mapping, as the exposure of a camera
+    exposure: f32,
+}
+
+// Float textures of the lighting pass, read with textureLoad() only (see the layout in main.rs)
+@group(0) @binding(0) var hdr_texture: texture_2d<f32>;
+@group(0) @binding(1) var<uniform> params: ToneMappingParams;
+
+// Vertex shader
+
+@vertex
+fn vertex_main(@builtin(vertex_index) in_vertex_index: u32) -> @builtin(position) vec4<f32> {
+    // Same full screen triangle as the lighting pass
+    let x = f32(i32(in_vertex_index & 1u) * 4 - 1);
+    let y = f32(i32(in_vertex_index >> 1u) * 4 - 1);
+    return vec4<f32>(x, y, 0.0, 1.0);
+}
+
+// Fragment shaders
+
+/// HDR color of the pixel, multiplied by the exposure
+fn exposed_color(frag_position: vec4<f32>) -> vec3<f32> {
+    let pixel = vec2<i32>(floor(frag_position.xy));
+    return params.exposure * textureLoad(hdr_texture, pixel, 0).rgb;
+}
+
+/// No tone mapping: the values above 1.0 are clamped, as with an 8 bits lighting output
+@fragment
+fn fragment_clamp(@builtin(position) frag_position: vec4<f32>) -> @location(0) vec4<f32> {
+    return vec4<f32>(clamp(exposed_color(frag_position), vec3<f32>(0.0), vec3<f32>(1.0)), 1.0);
+}
+
+/// Reinhard operator: x / (1 + x), compressing the high values without ever reaching 1.0
+@fragment
+fn fragment_reinhard(@builtin(position) frag_position: vec4<f32>) -> @location(0) vec4<f32> {
+    let color = exposed_color(frag_position);
+    return vec4<f32>(color / (1.0 + color), 1.0);
+}
+
+/// ACES filmic curve, with the fit of Krzysztof Narkowicz.
+/// More contrasted than Reinhard, with a "toe" in the dark values and a "shoulder" in the bright ones.
+@fragment
+fn fragment_aces(@builtin(position) frag_position: vec4<f32>) -> @location(0) vec4<f32> {
+    let x = exposed_color(frag_position);
+    let color = (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14);
+    return vec4<f32>(clamp(color, vec3<f32>(0.0), vec3<f32>(1.0)), 1.0);
+}
@@ -0,0 +1,121 @@
+// Image filters, as compute shaders.
+//
+// Each entry point is one pass of the filters chain: it reads the input texture,
+// and writes one pixel of the output storage texture per thread.
+// The textures are swapped between passes ("ping-pong"), as in the game of life example.
+//
+// Only some entry points use the parameters or the blur weights,
+// so their implicit bind group layouts differ.
+
+/// Parameters of the filters
+struct FilterParams {
+    // Luminance above which the threshold filter outputs white
+    threshold: f32,
+}
+
+@group(0) @binding(0) var input_image: texture_2d<f32>;
+@group(0) @binding(1) var output_image: texture_storage_2d<rgba8unorm, write>;
+@group(0) @binding(2) var<uniform> params: FilterParams;
+// Weights of the gaussian blur for the pixels at a distance of 0 to radius,
+// normalized so that the whole kernel sums up to 1
+@group(0) @binding(3) var<storage, read> blur_weights: array<f32>;
+
+/// Luminance of a color (Rec. 709 coefficients)
+fn luminance(color: vec3<f32>) -> f32 {
+    return dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+}
+
+/// Read the input image, with the coordinates clamped to its borders
+fn load_clamped(pixel: vec2<i32>) -> vec4<f32> {
+    let max_pixel = vec2<i32>(textureDimensions(input_image)) - 1;
+    return textureLoad(input_image, clamp(pixel, vec2<i32>(0), max_pixel), 0);
+}
+
+/// Whether the thread is outside of the image.
+/// The image size is not always a multiple of the workgroup size (8x8),
+/// so the workgroups on the right and bottom borders have some threads in excess.
+fn is_outside(pixel: vec2<i32>) -> bool {
+    return any(pixel >= vec2<i32>(textureDimensions(input_image)));
+}
+
+@compute
+@workgroup_size(8, 8)
+fn grayscale(@builtin(global_invocation_id) global_id: vec3u) {
+    let pixel = vec2<i32>(global_id.xy);
+    if is_outside(pixel) {
+        return;
+    }
+    let color = textureLoad(input_image, pixel, 0);
+    textureStore(output_image, pixel, vec4<f32>(vec3<f32>(luminance(color.rgb)), color.a));
+}
+
+// Gaussian blur
+//
+// A 2D gaussian kernel is separable: it is the product of a horizontal and a vertical 1D kernels.
+// So instead of reading (2 * radius + 1)^2 pixels, we blur in two passes of 2 * radius + 1 pixels each.
+
+fn gaussian_blur(pixel: vec2<i32>, direction: vec2<i32>) -> vec4<f32> {
+    let radius = i32(arrayLength(&blur_weights)) - 1;
+    var sum = vec4<f32>(0.0);
+    for (var i = -radius; i <= radius; i++) {
+        sum += blur_weights[abs(i)] * load_clamped(pixel + i * direction);
+    }
+    return sum;
+}
+
+@compute
+@workgroup_size(8, 8)
+fn blur_horizontal(@builtin(global_invocation_id) global_id: vec3u) {
+    let pixel = vec2<i32>(global_id.xy);
+    if is_outside(pixel) {
+        return;
+    }
+    textureStore(output_image, pixel, gaussian_blur(pixel, vec2<i32>(1, 0)));
+}
+
+@compute
+@workgroup_size(8, 8)
+fn blur_vertical(@builtin(global_invocation_id) global_id: vec3u) {
+    let pixel = vec2<i32>(global_id.xy);
+    if is_outside(pixel) {
+        return;
+    }
+    textureStore(output_image, pixel, gaussian_blur(pixel, vec2<i32>(0, 1)));
+}
+
+// Sobel edge detection
+//
+// The horizontal and vertical gradients of the luminance are approximated
+// by convolutions with the 3x3 Sobel kernels, and the output is the gradient magnitude.
+
+@compute
+@workgroup_size(8, 8)
+fn sobel(@builtin(global_invocation_id) global_id: vec3u) {
+    let pixel = vec2<i32>(global_id.xy);
+    if is_outside(pixel) {
+        return;
+    }
+    // Luminance of the 3x3 neighbourhood, l[1 + dy][1 + dx]
+    var l: array<array<f32, 3>, 3>;
+    for (var dy = -1; dy <= 1; dy++) {
+        for (var dx = -1; dx <= 1; dx++) {
+            l[dy + 1][dx + 1] = luminance(load_clamped(pixel + vec2<i32>(dx, dy)).rgb);
+        }
+    }
+    let gx = (l[0][2] + 2.0 * l[1][2] + l[2][2]) - (l[0][0] + 2.0 * l[1][0] + l[2][0]);
+    let gy = (l[2][0] + 2.0 * l[2][1] + l[2][2]) - (l[0][0] + 2.0 * l[0][1] + l[0][2]);
+    let magnitude = length(vec2<f32>(gx, gy));
+    textureStore(output_image, pixel, vec4<f32>(vec3<f32>(magnitude), 1.0));
+}
+
+@compute
+@workgroup_size(8, 8)
+fn threshold(@builtin(global_invocation_id) global_id: vec3u) {
+    let pixel = vec2<i32>(global_id.xy);
+    if is_outside(pixel) {
+        return;
+    }
+    let color = textureLoad(input_image, pixel, 0);
+    let value = select(0.0, 1.0, luminance(color.rgb) > params.threshold);
+    textureStore(output_image, pixel, vec4<f32>(vec3<f32>(value), 1.0));
+}
@@ -0,0 +1,475 @@
+//! This example shows how to process an image with a chain of compute shader filters.
+//!
+//! The input image (PNG or JPEG) is loaded on the CPU, and uploaded into a texture.
+//! Then each filter of the chain is applied by one or more compute passes,
+//! reading the current image from a texture and writing the filtered one into a storage texture.
+//! As in the game of life example, two textures are used in turn as input and output ("ping-pong").
+//! Finally, the last output texture is read back to the CPU through a staging buffer.
+//!
+//! The available filters are a gaussian blur (in two separable passes), Sobel edge detection,
+//! grayscale conversion and thresholding. The chain is given on the command line, for example:
+//! `cargo run -- --input input.png --filters grayscale,blur,sobel,threshold --sigma 1.5 --threshold 0.2`
+//!
+//! 1. (async) Initialize the connection with the GPU device
+//! 2. Load the input image, and convert it to RGBA
+//! 3. Initialize 2 wgpu Texture objects that will serve as input and output data to the compute shaders.
+//!    Their usages include "storage binding" to write to them, and "texture binding" to read them.
+//! 4. Upload the input image into the first texture
+//! 5. Initialize a wgpu Buffer where the last output texture will be transferred to.
+//!    Its rows are padded, since texture copies need rows aligned to 256 bytes.
+//! 6. Load the shader module, containing one compute shader per filter pass,
+//!    put the filters parameters into a uniform buffer,
+//!    and the weights of the gaussian blur kernel into a storage buffer
+//! 7. Define our command encoder:
+//!    1. For each pass of the filters chain:
+//!       - define the compute pipeline of its entry point
+//!       - create a bind group with the current input and output textures,
+//!         and the parameters or blur weights
+//!       - dispatch a grid of workgroups covering the whole image
+//!    2. Copy the last output texture into the staging buffer
+//! 8. Submit our commands to the device queue
+//! 9. (async) Transfer the staging buffer into an image, removing the row padding, and save it to disk
+
+use wgpu::util::DeviceExt; // Utility trait to create and initialize buffers with device.create_buffer_init()
+
+fn main() {
+    // Make the main async
+    pollster::block_on(run());
+}
+
+async fn run() {
+    // Input image, filters and their parameters, provided as command line arguments
+    let args: Vec<String> = std::env::args().collect();
+    let arg_value = |name: &str| {
+        let position = args.iter().position(|arg| arg == name)?;
+        Some(
+            args.get(position + 1)
+                .expect("Missing argument value")
+                .clone(),
+        )
+    };
+    let input_path = arg_value("--input").unwrap_or("input.png".to_string());
+    let filters: Vec<Filter> = arg_value("--filters")
+        .unwrap_or("blur,sobel".to_string())
+        .split(',')
+        .map(Filter::from_name)
+        .collect();
+    // The blur weights are computed from sigma on the CPU, only the threshold is a uniform
+    let sigma = arg_value("--sigma").map_or(2.0, |value| parse_sigma(&value));
+    let params = FilterParams {
+        threshold: arg_value("--threshold").map_or(0.2, |value| value.parse().unwrap()),
+        _padding: [0; 3],
+    };
+
+    // (1) Initializing WebGPU
+    println!("Initializing WebGPU ...");
+    let (device, queue) = init_wgpu_device().await.unwrap();
+
+    // (2) Load the input image, with its format deduced from the file extension
+    println!("Loading {input_path} ...");
+    let input_image = image::open(&input_path).unwrap().to_rgba8();
+    let (width, height) = input_image.dimensions();
+
+    // (3) Initialize two textures, alternately used as input and output of the filters
+    let textures = [
+        init_image_texture(&device, width, height),
+        init_image_texture(&device, width, height),
+    ];
+    let texture_views = [
+        textures[0].create_view(&Default::default()),
+        textures[1].create_view(&Default::default()),
+    ];
+
+    // (4) Upload the input image into the first texture.
+    // Contrary to texture to buffer copies, rows do not need any alignment here.
+    queue.write_texture(
+        wgpu::ImageCopyTexture {
+            texture: &textures[0],
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        &input_image,
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(4 * width), // texel size of rgba8unorm texture is 4 bytes
+            rows_per_image: Some(height),
+        },
+        textures[0].size(),
+    );
+
+    // (5) Create a staging buffer for retrieving the content of the last output texture
+    let padded_bytes_per_row = padded_bytes_per_row(width);
+    let staging_buffer = init_buffer(&device, padded_bytes_per_row, height);
+
+    // (6) Load the shader module, containing one compute shader per filter pass
+    let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("filters_shader"),
+        source: wgpu::ShaderSource::Wgsl(include_str!("filters.wgsl").into()),
+    });
+
+    // (6) Put the parameters of the filters into a uniform buffer
+    let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Filter Params Buffer"),
+        contents: bytemuck::bytes_of(&params),
+        usage: wgpu::BufferUsages::UNIFORM,
+    });
+    // (6) And the weights of the gaussian blur into a storage buffer,
+    // since their number depends on sigma
+    let blur_weights_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Blur Weights Buffer"),
+        contents: bytemuck::cast_slice(&gaussian_weights(sigma)),
+        usage: wgpu::BufferUsages::STORAGE,
+    });
+
+    // Initialize a command encoder
+    let mut encoder = device.create_command_encoder(&Default::default());
+
+    // (7.1) Add one compute pass per filter pass, alternating the input and output textures
+    let passes: Vec<(Filter, &str)> = filters
+        .iter()
+        .flat_map(|&filter| {
+            filter
+                .entry_points()
+                .iter()
+                .map(move |&entry| (filter, entry))
+        })
+        .collect();
+    for (i, &(filter, entry_point)) in passes.iter().enumerate() {
+        println!("Filter pass {i}: {entry_point}");
+        let pipeline = build_pipeline(&device, &shader_module, entry_point);
+        let input_view = &texture_views[i % 2];
+        let output_view = &texture_views[(i + 1) % 2];
+        let mut entries = vec![
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(input_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(output_view),
+            },
+        ];
+        // The implicit layout only contains the bindings used by the entry point
+        match filter {
+            Filter::GaussianBlur => entries.push(wgpu::BindGroupEntry {
+                binding: 3,
+                resource: blur_weights_buffer.as_entire_binding(),
+            }),
+            Filter::Threshold => entries.push(wgpu::BindGroupEntry {
+                binding: 2,
+                resource: params_buffer.as_entire_binding(),
+            }),
+            Filter::Sobel | Filter::Grayscale => {}
+        }
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(entry_point),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &entries,
+        });
+        launch_kernel(&mut encoder, &pipeline, &bind_group, &textures[0].size());
+    }
+
+    // (7.2) Copy the last output texture (the input one if there is no filter) to the staging buffer
+    let output_texture = &textures[passes.len() % 2];
+    copy_texture_to_buffer(
+        &mut encoder,
+        output_texture,
+        &staging_buffer,
+        padded_bytes_per_row,
+    );
+
+    // (8) Finalize the command encoder and send it to the queue
+    println!("Submitting commands to the queue ...");
+    queue.submit(Some(encoder.finish()));
+
+    // (9) Transfer the staging buffer into a CPU image and save it to disk.
+    // New scope to encapsulate the BufferView and drop it before unmapping.
+    {
+        println!("Saving the image to disk ...");
+        let data = retrieve_buffer_data(&device, &staging_buffer).await;
+        // Only keep the first 4 * width bytes of each padded row
+        let pixels: Vec<u8> = data
+            .chunks_exact(padded_bytes_per_row as usize)
+            .flat_map(|row| &row[..4 * width as usize])
+            .copied()
+            .collect();
+        let img = image::RgbaImage::from_raw(width, height, pixels).unwrap();
+        img.save("image.png").unwrap();
+    }
+    // Flushes any pending write operations and unmaps the buffer from host memory
+    staging_buffer.unmap();
+
+    println!("Terminating the program ...")
+}
+
+/// An image filter of the chain
+#[derive(Clone, Copy, Debug)]
+enum Filter {
+    GaussianBlur,
+    Sobel,
+    Grayscale,
+    Threshold,
+}
+
+impl Filter {
+    fn from_name(name: &str) -> Self {
+        match name {
+            "blur" => Self::GaussianBlur,
+            "sobel" => Self::Sobel,
+            "grayscale" => Self::Grayscale,
+            "threshold" => Self::Threshold,
+            _ => panic!("Unknown filter: {name} (expected blur, sobel, grayscale or threshold)"),
+        }
+    }
+
+    /// The shader entry points of the filter, one per compute pass
+    fn entry_points(&self) -> &'static [&'static str] {
+        match self {
+            // The gaussian blur is separable, in one horizontal and one vertical passes
+            Self::GaussianBlur => &["blur_horizontal", "blur_vertical"],
+            Self::Sobel => &["sobel"],
+            Self::Grayscale => &["grayscale"],
+            Self::Threshold => &["threshold"],
+        }
+    }
+}
+
+/// Parameters of the filters, in a uniform buffer.
+/// Bytemuck is used to enable easy casting to a &[u8].
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct FilterParams {
+    /// Luminance threshold, between 0.0 and 1.0 (--threshold)
+    threshold: f32,
+    _padding: [u32; 3],
+}
+
+/// Parse the standard deviation of the gaussian blur (--sigma),
+/// which must be a positive number of pixels
+fn parse_sigma(value: &str) -> f32 {
+    let sigma: f32 = value
+        .parse()
+        .unwrap_or_else(|_| panic!("Invalid sigma: {value} (expected a number of pixels)"));
+    assert!(
+        sigma.is_finite() && sigma > 0.0,
+        "Invalid sigma: {value} (expected a positive number of pixels)"
+    );
+    sigma
+}
+
+/// (6) Weights of the 1D gaussian kernel for the pixels at a distance of 0 to radius.
+/// Beyond 3 sigmas, the weights are negligible, so the radius is ceil(3 * sigma).
+/// They are normalized so that the whole kernel (the center and both sides) sums up to 1.
+fn gaussian_weights(sigma: f32) -> Vec<f32> {
+    let radius = (3.0 * sigma).ceil() as u32;
+    let weights: Vec<f32> = (0..=radius)
+        .map(|i| (-((i * i) as f32) / (2.0 * sigma * sigma)).exp())
+        .collect();
+    let total = weights[0] + 2.0 * weights[1..].iter().sum::<f32>();
+    weights.iter().map(|weight| weight / total).collect()
+}
+
+/// (1) Initializing WebGPU
+async fn init_wgpu_device() -> Result<(wgpu::Device, wgpu::Queue), wgpu::RequestDeviceError> {
+    // Start an "Instance", which is the context for all things wgpu.
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: wgpu::Backends::all(),
+        ..Default::default()
+    });
+
+    // An "Adapter" is a handle to a physical graphics/compute device.
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(), // None | LowPower | HighPerformance
+            compatible_surface: None,
+            force_fallback_adapter: false, // If needed to force CPU fallback?
+        })
+        .await
+        .unwrap();
+
+    // Request a connection to a physical device,
+    // and also access to the queue for its command buffers.
+    // It is possible, if necessary, to add a description of required features.
+    adapter.request_device(&Default::default(), None).await
+}
+
+/// (3) Initialize a texture for the image, with the same size as the input image.
+/// Rgba8Unorm is one of the few formats usable as a storage texture on all devices.
+fn init_image_texture(device: &wgpu::Device, width: u32, height: u32) -> wgpu::Texture {
+    let desc = wgpu::TextureDescriptor {
+        label: None,
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba8Unorm,
+        usage: wgpu::TextureUsages::COPY_DST
+            | wgpu::TextureUsages::COPY_SRC
+            | wgpu::TextureUsages::STORAGE_BINDING
+            | wgpu::TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    };
+
+    device.create_texture(&desc)
+}
+
+/// (5) Number of bytes per row of the staging buffer.
+/// Copies from a texture to a buffer require rows aligned to 256 bytes
+/// (wgpu::COPY_BYTES_PER_ROW_ALIGNMENT), so the rows are padded if needed.
+fn padded_bytes_per_row(width: u32) -> u32 {
+    let bytes_per_row = 4 * width; // texel size of rgba8unorm texture is 4 bytes
+    let alignment = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    bytes_per_row.div_ceil(alignment) * alignment
+}
+
+/// (5) Initialize a staging buffer. Required because we can't retrieve the
+/// texture content directly when they're used as pipeline data.
+fn init_buffer(device: &wgpu::Device, padded_bytes_per_row: u32, height: u32) -> wgpu::Buffer {
+    let desc = wgpu::BufferDescriptor {
+        label: None,
+        size: (padded_bytes_per_row * height).into(),
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    };
+
+    device.create_buffer(&desc)
+}
+
+/// (7.1) Initialize the compute pipeline of a filter pass
+fn build_pipeline(
+    device: &wgpu::Device,
+    shader_module: &wgpu::ShaderModule,
+    entry_point: &str,
+) -> wgpu::ComputePipeline {
+    let desc = wgpu::ComputePipelineDescriptor {
+        label: Some(entry_point),
+        layout: None, // "auto"
+        module: shader_module,
+        entry_point,
+    };
+
+    device.create_compute_pipeline(&desc)
+}
+
+/// (7.1) Dispatch our compute shader into workgroups, as many as required to cover the whole image
+/// (one thread per pixel)
+fn launch_kernel(
+    encoder: &mut wgpu::CommandEncoder,
+    pipeline: &wgpu::ComputePipeline,
+    bind_group: &wgpu::BindGroup,
+    image_size: &wgpu::Extent3d,
+) {
+    // Setup a compute pass
+    let desc = wgpu::ComputePassDescriptor {
+        label: None,
+        timestamp_writes: None,
+    };
+    let mut pass = encoder.begin_compute_pass(&desc);
+
+    pass.set_pipeline(pipeline);
+    pass.set_bind_group(0, bind_group, &[]);
+
+    // Workgroups are arranged in a 8x8 grid (as declared in the shader code).
+    // Any image size is accepted, so we round up the number of workgroups,
+    // and the shader ignores the threads outside of the image.
+    pass.dispatch_workgroups(
+        image_size.width.div_ceil(8),
+        image_size.height.div_ceil(8),
+        1,
+    );
+}
+
+/// (7.2) Copy the texture output into a buffer, with padded rows
+fn copy_texture_to_buffer(
+    encoder: &mut wgpu::CommandEncoder,
+    texture: &wgpu::Texture,
+    output_buffer: &wgpu::Buffer,
+    padded_bytes_per_row: u32,
+) {
+    encoder.copy_texture_to_buffer(
+        // source
+        wgpu::ImageCopyTexture {
+            aspect: wgpu::TextureAspect::All,
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+        },
+        // destination
+        wgpu::ImageCopyBuffer {
+            buffer: output_buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_bytes_per_row),
+                rows_per_image: Some(texture.height()),
+            },
+        },
+        // copy_size
+        texture.size(),
+    );
+}
+
+/// (9) Retrieve the buffer data from the GPU
+async fn retrieve_buffer_data<'a>(
+    device: &wgpu::Device,
+    buffer: &'a wgpu::Buffer,
+) -> wgpu::BufferView<'a> {
+    let buffer_slice = buffer.slice(..);
+
+    // NOTE: We have to create the mapping THEN device.poll() before await the future.
+    // Otherwise the application will freeze.
+    let (tx, rx) = oneshot::channel();
+    buffer_slice.map_async(wgpu::MapMode::Read, move |result| {
+        tx.send(result).unwrap();
+    });
+    device.poll(wgpu::Maintain::Wait);
+    rx.await.unwrap().unwrap();
+
+    // Synchronously and immediately map a buffer for reading.
+    // Will panic if buffer_slice.map_async() did not finish yet.
+    buffer_slice.get_mapped_range()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gaussian_weights_cover_3_sigmas() {
+        assert_eq!(gaussian_weights(2.0).len(), 7);
+        assert_eq!(gaussian_weights(1.5).len(), 6);
+        // Even a tiny blur reads the direct neighbors
+        assert_eq!(gaussian_weights(0.1).len(), 2);
+    }
+
+    #[test]
+    fn gaussian_weights_are_normalized_and_decreasing() {
+        for sigma in [0.1, 0.5, 1.5, 2.0, 10.0] {
+            let weights = gaussian_weights(sigma);
+            let total = weights[0] + 2.0 * weights[1..].iter().sum::<f32>();
+            assert!((total - 1.0).abs() < 1e-6, "sigma {sigma}: total {total}");
+            assert!(weights.windows(2).all(|pair| pair[0] > pair[1]));
+            // Ratio between the weights at a distance 1 and 0
+            let ratio = (-1.0 / (2.0 * sigma * sigma)).exp();
+            assert!((weights[1] / weights[0] - ratio).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn parse_valid_sigma() {
+        assert_eq!(parse_sigma("1.5"), 1.5);
+        assert_eq!(parse_sigma("2"), 2.0);
+    }
+
+    #[test]
+    fn parse_invalid_sigma() {
+        for value in ["0", "-1.5", "inf", "NaN", "abc", ""] {
+            let result = std::panic::catch_unwind(|| parse_sigma(value));
+            assert!(result.is_err(), "sigma {value:?} should be rejected");
+        }
+    }
+}
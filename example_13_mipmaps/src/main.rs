@@ -0,0 +1,475 @@
+//! This example shows how to generate the mipmaps of a texture uploaded from an image,
+//! and how they remove the aliasing of textured surfaces seen from afar.
+//!
+//! A mipmap is a chain of smaller and smaller versions of a texture (its mip levels),
+//! each one half the size of the previous one, down to a single texel.
+//! When a texture is sampled, the GPU picks the mip levels whose texel size matches the pixel size,
+//! instead of skipping most of the texels of the full resolution texture.
+//! WebGPU does not generate the mip levels, so we fill them with a render pass per level,
+//! downsampling the previous level with a linear filtering.
+//! The texture uses an sRGB format, so the filtering happens on linear values, as it should.
+//!
+//! The input image (PNG or JPEG, `--input`) is drawn on a plane going up to the horizon.
+//! Compare the result with `cargo run -- --no-mipmaps`, which only samples the full resolution level.
+//! The steps of this minimal program are the following.
+//!
+//! 1. (async) Initialize the connection with the GPU device
+//! 2. Load the input image, and convert it to RGBA
+//! 3. **(new)** Create the texture with all its mip levels:
+//!    1. Initialize a wgpu Texture with `mip_level_count > 1`, and upload the image into its first level
+//!    2. Define the downsampling render pipeline
+//!    3. For each level, render the previous level into it, with a linear sampler
+//! 4. Initialize a wgpu Texture object that will serve as a write target for our pipeline,
+//!    and a wgpu Buffer where it will be transferred to
+//! 5. Load the shader module, containing both the vertex and fragment shaders
+//! 6. Define our render pipeline, with the plane hardcoded in the vertex shader
+//! 7. **(new)** Create a sampler using the mipmaps (or not, with `--no-mipmaps`),
+//!    and a bind group with the texture and the sampler
+//! 8. Define our command encoder:
+//!    1. Start by defining our render pass, drawing the textured plane
+//!    2. Add a command to copy the output texture into the output buffer,
+//!       and **(new)** every mip level into its own buffer
+//! 9. Submit our commands to the device queue
+//! 10. (async) Transfer the output buffers into images we can save to disk
+
+fn main() {
+    // Make the main async
+    pollster::block_on(run());
+}
+
+async fn run() {
+    // Input image and mipmaps usage, provided as command line arguments
+    let args: Vec<String> = std::env::args().collect();
+    let arg_value = |name: &str| {
+        let position = args.iter().position(|arg| arg == name)?;
+        Some(
+            args.get(position + 1)
+                .expect("Missing argument value")
+                .clone(),
+        )
+    };
+    let input_path = arg_value("--input").unwrap_or("input.png".to_string());
+    let use_mipmaps = !args.iter().any(|arg| arg == "--no-mipmaps");
+
+    // (1) Initializing WebGPU
+    println!("Initializing WebGPU ...");
+    let (device, queue) = init_wgpu_device().await.unwrap();
+
+    // (2) Load the input image, with its format deduced from the file extension
+    println!("Loading {input_path} ...");
+    let image = image::open(&input_path).unwrap().to_rgba8();
+
+    // (3) Create the texture, with its mip levels generated on the GPU
+    println!("Generating the mipmaps ...");
+    let texture = create_texture_with_mipmaps(&device, &queue, &image);
+    let texture_view = texture.create_view(&Default::default());
+
+    // (4) Initialize the output texture and buffer
+    let width = 512;
+    let height = 512;
+    let output_texture = init_output_texture(&device, width, height);
+    let output_texture_view = output_texture.create_view(&Default::default());
+    let output_buffer = init_buffer(&device, output_texture.size());
+
+    // (5) Load the shader module, containing both the vertex and fragment shaders
+    let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("plane_shader"),
+        source: wgpu::ShaderSource::Wgsl(include_str!("plane.wgsl").into()),
+    });
+
+    // (6) Define our pipeline
+    let pipeline = build_simple_pipeline(&device, &shader_module, output_texture.format());
+
+    // (7) Create the sampler of the texture, repeated over the plane.
+    // The mipmap filter interpolates between the two closest mip levels ("trilinear" filtering).
+    // Without mipmaps, we simply clamp the sampled mip levels to the first one.
+    let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+        label: Some("plane_sampler"),
+        address_mode_u: wgpu::AddressMode::Repeat,
+        address_mode_v: wgpu::AddressMode::Repeat,
+        mag_filter: wgpu::FilterMode::Linear,
+        min_filter: wgpu::FilterMode::Linear,
+        mipmap_filter: wgpu::FilterMode::Linear,
+        lod_max_clamp: if use_mipmaps { 32.0 } else { 0.0 },
+        ..Default::default()
+    });
+    let bind_group = create_texture_bind_group(
+        &device,
+        &pipeline.get_bind_group_layout(0),
+        &texture_view,
+        &sampler,
+    );
+
+    // Initialize a command encoder
+    let mut encoder = device.create_command_encoder(&Default::default());
+
+    // (8.1) Draw our pipeline: the 6 vertices of the plane
+    draw_pipeline(
+        &mut encoder,
+        &pipeline,
+        &bind_group,
+        &output_texture_view,
+        6,
+    );
+
+    // (8.2) Add commands to copy the output texture and every mip level into buffers
+    copy_texture_to_buffer(&mut encoder, &output_texture, 0, &output_buffer);
+    let mip_buffers: Vec<wgpu::Buffer> = (0..texture.mip_level_count())
+        .map(|level| {
+            let size = texture.size().mip_level_size(level, wgpu::TextureDimension::D2);
+            let mip_buffer = init_buffer(&device, size);
+            copy_texture_to_buffer(&mut encoder, &texture, level, &mip_buffer);
+            mip_buffer
+        })
+        .collect();
+
+    // (9) Finalize the command encoder and send it to the queue
+    println!("Submitting commands to the queue ...");
+    queue.submit(Some(encoder.finish()));
+
+    // (10) Transfer the output buffers into images and save them to disk
+    println!("Saving the image to disk ...");
+    save_buffer_image(&device, &output_buffer, output_texture.size(), "image.png").await;
+    println!("Saving the {} mip levels to disk ...", mip_buffers.len());
+    for (level, mip_buffer) in mip_buffers.iter().enumerate() {
+        let size = texture
+            .size()
+            .mip_level_size(level as u32, wgpu::TextureDimension::D2);
+        save_buffer_image(&device, mip_buffer, size, &format!("mip_{level}.png")).await;
+    }
+
+    println!("Terminating the program ...")
+}
+
+/// (1) Initializing WebGPU
+async fn init_wgpu_device() -> Result<(wgpu::Device, wgpu::Queue), wgpu::RequestDeviceError> {
+    // Start an "Instance", which is the context for all things wgpu.
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: wgpu::Backends::all(),
+        ..Default::default()
+    });
+
+    // An "Adapter" is a handle to a physical graphics/compute device.
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(), // None | LowPower | HighPerformance
+            compatible_surface: None,
+            force_fallback_adapter: false, // If needed to force CPU fallback?
+        })
+        .await
+        .unwrap();
+
+    // Request a connection to a physical device,
+    // and also access to the queue for its command buffers.
+    // It is possible, if necessary, to add a description of required features.
+    adapter.request_device(&Default::default(), None).await
+}
+
+/// (3) Create a texture from an image, with all its mip levels.
+fn create_texture_with_mipmaps(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    image: &image::RgbaImage,
+) -> wgpu::Texture {
+    // (3.1) Initialize the texture, with as many mip levels as needed to reach a single texel
+    let size = wgpu::Extent3d {
+        width: image.width(),
+        height: image.height(),
+        depth_or_array_layers: 1,
+    };
+    let texture_desc = wgpu::TextureDescriptor {
+        label: Some("mipmapped_texture"),
+        size,
+        dimension: wgpu::TextureDimension::D2,
+        // For example 10 levels for a 512x512 image: 512x512, 256x256, ..., 1x1
+        mip_level_count: size.max_mips(wgpu::TextureDimension::D2),
+        sample_count: 1, // sample_count > 1 would indicate a multisampled texture
+        // The image colors are sRGB encoded, and converted to linear values when sampled
+        format: wgpu::TextureFormat::Rgba8UnormSrgb,
+        // TEXTURE_BINDING -> so that shaders can sample the texture
+        // COPY_DST -> so that we can upload the image into the texture
+        // RENDER_ATTACHMENT -> so that the GPU can render the mip levels
+        // COPY_SRC -> so that we can pull the mip levels out of the texture
+        usage: wgpu::TextureUsages::TEXTURE_BINDING
+            | wgpu::TextureUsages::COPY_DST
+            | wgpu::TextureUsages::RENDER_ATTACHMENT
+            | wgpu::TextureUsages::COPY_SRC,
+        // Specify the allowed formats when calling "texture.create_view()"
+        view_formats: &[],
+    };
+    let texture = device.create_texture(&texture_desc);
+
+    // (3.1) Upload the image into the first mip level.
+    // Contrary to texture to buffer copies, rows do not need any alignment here.
+    queue.write_texture(
+        wgpu::ImageCopyTexture {
+            texture: &texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        image,
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(4 * size.width),
+            rows_per_image: Some(size.height),
+        },
+        size,
+    );
+
+    generate_mipmaps(device, queue, &texture);
+    texture
+}
+
+/// (3.2 & 3.3) Fill the mip levels of a texture from its first one.
+///
+/// Each level is rendered from the previous one, so the texture needs the RENDER_ATTACHMENT usage.
+/// We use a render pass rather than a compute pass, since sRGB formats are not usable
+/// as storage textures, but they are as render targets, with the sRGB conversions for free.
+/// For odd sizes, the linear sampling of each 2x2 block slightly misses some texels,
+/// which is good enough for this example.
+fn generate_mipmaps(device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::Texture) {
+    // (3.2) Define the downsampling pipeline
+    let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("mipmap_shader"),
+        source: wgpu::ShaderSource::Wgsl(include_str!("mipmap.wgsl").into()),
+    });
+    let pipeline = build_simple_pipeline(device, &shader_module, texture.format());
+    // Linear filtering, averaging the 2x2 texels between the sampled texture coordinates
+    let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+        label: Some("mipmap_sampler"),
+        mag_filter: wgpu::FilterMode::Linear,
+        min_filter: wgpu::FilterMode::Linear,
+        ..Default::default()
+    });
+
+    // One view per mip level, to read from one level and render into the next one
+    let mip_views: Vec<wgpu::TextureView> = (0..texture.mip_level_count())
+        .map(|level| {
+            texture.create_view(&wgpu::TextureViewDescriptor {
+                label: Some("mip_view"),
+                base_mip_level: level,
+                mip_level_count: Some(1),
+                ..Default::default()
+            })
+        })
+        .collect();
+
+    // (3.3) Render each level from the previous one.
+    // The render passes are executed in order, so each level is complete before being read.
+    let mut encoder = device.create_command_encoder(&Default::default());
+    for level in 1..mip_views.len() {
+        let bind_group = create_texture_bind_group(
+            device,
+            &pipeline.get_bind_group_layout(0),
+            &mip_views[level - 1],
+            &sampler,
+        );
+        // A single triangle covering the whole level
+        draw_pipeline(&mut encoder, &pipeline, &bind_group, &mip_views[level], 3);
+    }
+    queue.submit(Some(encoder.finish()));
+}
+
+/// (4) Initialize the output texture
+fn init_output_texture(device: &wgpu::Device, width: u32, height: u32) -> wgpu::Texture {
+    let texture_desc = wgpu::TextureDescriptor {
+        label: Some("output_texture"),
+        // The texture size. (layers is set to 1)
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        dimension: wgpu::TextureDimension::D2,
+        mip_level_count: 1, // the number of mip levels the texture will contain
+        sample_count: 1,    // sample_count > 1 would indicate a multisampled texture
+        // The shader outputs linear colors, converted to sRGB when written, like the input image
+        format: wgpu::TextureFormat::Rgba8UnormSrgb,
+        // RENDER_ATTACHMENT -> so that the GPU can render to the texture
+        // COPY_SRC -> so that we can pull data out of the texture
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        // Specify the allowed formats when calling "texture.create_view()"
+        view_formats: &[],
+    };
+    device.create_texture(&texture_desc)
+}
+
+/// (4 & 8.2) Number of bytes per row of a buffer receiving a texture copy.
+/// Copies from a texture to a buffer require rows aligned to 256 bytes
+/// (wgpu::COPY_BYTES_PER_ROW_ALIGNMENT), so the rows are padded if needed.
+fn padded_bytes_per_row(width: u32) -> u32 {
+    let bytes_per_row = 4 * width; // texel size of rgba8 textures is 4 bytes
+    let alignment = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    bytes_per_row.div_ceil(alignment) * alignment
+}
+
+/// (4 & 8.2) Initialize a buffer where a texture (level) of the given size will be transferred to
+fn init_buffer(device: &wgpu::Device, size: wgpu::Extent3d) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: (padded_bytes_per_row(size.width) * size.height).into(),
+        usage: wgpu::BufferUsages::COPY_DST
+            // this tells wpgu that we want to read this buffer from the cpu
+            | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    })
+}
+
+/// (3.2 & 6) Define a simple render pipeline, without vertex buffer nor depth,
+/// since the vertices are hardcoded in the vertex shaders
+fn build_simple_pipeline(
+    device: &wgpu::Device,
+    shader_module: &wgpu::ShaderModule,
+    texture_format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Render Pipeline"),
+        layout: None, // "auto"
+        vertex: wgpu::VertexState {
+            module: shader_module,
+            entry_point: "vertex_main",
+            buffers: &[],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader_module,
+            entry_point: "fragment_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format: texture_format,
+                blend: Some(wgpu::BlendState::REPLACE),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        // If the pipeline will be used with a multiview render pass, this
+        // indicates how many array layers the attachments will have.
+        multiview: None,
+    })
+}
+
+/// (3.3 & 7) Create a bind group with a texture and its sampler
+fn create_texture_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    texture_view: &wgpu::TextureView,
+    sampler: &wgpu::Sampler,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("texture_bind_group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(texture_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+        ],
+    })
+}
+
+/// (3.3 & 8.1) Draw our pipeline (add render pass to the command encoder).
+fn draw_pipeline(
+    encoder: &mut wgpu::CommandEncoder,
+    pipeline: &wgpu::RenderPipeline,
+    bind_group: &wgpu::BindGroup,
+    texture_view: &wgpu::TextureView,
+    num_vertices: u32,
+) {
+    // Setup the pass that will render into our texture
+    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("Render Pass"),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: texture_view,   // the output texture
+            resolve_target: None, // only useful for multisampling
+            ops: wgpu::Operations {
+                // "load" specifies how data is read.
+                // "Clear" is the lightest way to initialize the texture.
+                load: wgpu::LoadOp::Clear(wgpu::Color {
+                    r: 0.1,
+                    g: 0.2,
+                    b: 0.3,
+                    a: 1.0,
+                }),
+                // whether data is written to or not. Store | Discard
+                store: wgpu::StoreOp::Store,
+            },
+        })],
+        ..Default::default()
+    });
+
+    // Draw the render pass for our pipeline
+    render_pass.set_pipeline(pipeline);
+    render_pass.set_bind_group(0, bind_group, &[]);
+    render_pass.draw(0..num_vertices, 0..1);
+}
+
+/// (8.2) Copy a texture mip level into a buffer, with padded rows
+fn copy_texture_to_buffer(
+    encoder: &mut wgpu::CommandEncoder,
+    texture: &wgpu::Texture,
+    mip_level: u32,
+    output_buffer: &wgpu::Buffer,
+) {
+    let size = texture
+        .size()
+        .mip_level_size(mip_level, wgpu::TextureDimension::D2);
+    encoder.copy_texture_to_buffer(
+        // source
+        wgpu::ImageCopyTexture {
+            aspect: wgpu::TextureAspect::All,
+            texture,
+            mip_level,
+            origin: wgpu::Origin3d::ZERO,
+        },
+        // destination
+        wgpu::ImageCopyBuffer {
+            buffer: output_buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_bytes_per_row(size.width)),
+                rows_per_image: Some(size.height),
+            },
+        },
+        // copy_size
+        size,
+    );
+}
+
+/// (10) Retrieve the buffer data from the GPU, and save it as an image,
+/// removing the padding at the end of each row.
+async fn save_buffer_image(
+    device: &wgpu::Device,
+    buffer: &wgpu::Buffer,
+    size: wgpu::Extent3d,
+    path: &str,
+) {
+    let buffer_slice = buffer.slice(..);
+
+    // NOTE: We have to create the mapping THEN device.poll() before await the future.
+    // Otherwise the application will freeze.
+    let (tx, rx) = oneshot::channel();
+    buffer_slice.map_async(wgpu::MapMode::Read, move |result| {
+        tx.send(result).unwrap();
+    });
+    device.poll(wgpu::Maintain::Wait);
+    rx.await.unwrap().unwrap();
+
+    // New scope to encapsulate the BufferView and drop it before unmapping.
+    {
+        let data = buffer_slice.get_mapped_range();
+        let pixels: Vec<u8> = data
+            .chunks_exact(padded_bytes_per_row(size.width) as usize)
+            .flat_map(|row| &row[..4 * size.width as usize])
+            .copied()
+            .collect();
+        let img = image::RgbaImage::from_raw(size.width, size.height, pixels).unwrap();
+        img.save(path).unwrap();
+    }
+    // Flushes any pending write operations and unmaps the buffer from host memory
+    buffer.unmap();
+}
@@ -0,0 +1,32 @@
+// Downsampling of a mip level into the next one, with a render pass.
+//
+// A single triangle covers the whole next (smaller) level,
+// and each fragment samples the previous level at its texture coordinates,
+// with a linear filtering averaging the 2x2 texels it covers.
+// With an sRGB texture, the sampled texels are converted to linear values before the filtering,
+// and the output is converted back to sRGB when written, so averages are computed in linear space.
+
+@group(0) @binding(0) var source_texture: texture_2d<f32>;
+@group(0) @binding(1) var source_sampler: sampler;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vertex_main(@builtin(vertex_index) in_vertex_index: u32) -> VertexOutput {
+    // Texture coordinates (0, 0), (2, 0) and (0, 2): a triangle covering the [0, 1] square
+    let uv = vec2<f32>(f32((in_vertex_index << 1u) & 2u), f32(in_vertex_index & 2u));
+    return VertexOutput(
+        // clip_position, Y being up in clip space but down in texture coordinates
+        vec4<f32>(2.0 * uv.x - 1.0, 1.0 - 2.0 * uv.y, 0.0, 1.0),
+        // uv
+        uv,
+    );
+}
+
+@fragment
+fn fragment_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(source_texture, source_sampler, in.uv);
+}
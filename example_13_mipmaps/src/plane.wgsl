@@ -0,0 +1,69 @@
+// Textured ground plane, going from the camera to the horizon.
+//
+// Far away, many texels fall into each pixel. With a single mip level,
+// each pixel only samples one (or 4 with linear filtering) of them, which aliases badly.
+// With mipmaps, the sampler picks the mip levels whose texels match the pixel size,
+// from the screen-space derivatives of the texture coordinates.
+
+@group(0) @binding(0) var plane_texture: texture_2d<f32>;
+@group(0) @binding(1) var plane_sampler: sampler;
+
+// Perspective camera, 1 unit above the plane, looking slightly down
+const EYE = vec3<f32>(0.0, 1.0, 0.0);
+const CENTER = vec3<f32>(0.0, 0.0, 8.0);
+const FOCAL = 1.5;
+const NEAR = 0.1;
+const FAR = 200.0;
+
+// The plane covers [-100, 100] x [1, 201], with the texture repeated every 4 units.
+// It starts a bit in front of the camera, just below the bottom of the screen.
+const HALF_SIZE = 100.0;
+const START = 1.0;
+const TILE_SIZE = 4.0;
+
+/// Projection from world coordinates to clip coordinates,
+/// see the camera and shadow examples for details
+fn view_proj() -> mat4x4<f32> {
+    let forward = normalize(CENTER - EYE);
+    let right = normalize(cross(vec3<f32>(0.0, 1.0, 0.0), forward));
+    let up = cross(forward, right);
+    let view_mat = transpose(mat4x4f(
+        right.x  , right.y  , right.z  , -dot(right, EYE)  ,
+        up.x     , up.y     , up.z     , -dot(up, EYE)     ,
+        forward.x, forward.y, forward.z, -dot(forward, EYE),
+        0.0      , 0.0      , 0.0      , 1.0               ,
+    ));
+    let proj_mat = transpose(mat4x4f(
+        FOCAL, 0.0  , 0.0               , 0.0                       ,
+        0.0  , FOCAL, 0.0               , 0.0                       ,
+        0.0  , 0.0  , FAR / (FAR - NEAR), -FAR * NEAR / (FAR - NEAR),
+        0.0  , 0.0  , 1.0               , 0.0                       ,
+    ));
+    return proj_mat * view_mat;
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vertex_main(@builtin(vertex_index) in_vertex_index: u32) -> VertexOutput {
+    // Two triangles for the plane, with corners hardcoded as in the triangle example
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, 0.0), vec2<f32>(1.0, 0.0), vec2<f32>(1.0, 2.0),
+        vec2<f32>(-1.0, 0.0), vec2<f32>(1.0, 2.0), vec2<f32>(-1.0, 2.0),
+    );
+    let xz = HALF_SIZE * corners[in_vertex_index] + vec2<f32>(0.0, START);
+    return VertexOutput(
+        // clip_position
+        view_proj() * vec4<f32>(xz.x, 0.0, xz.y, 1.0),
+        // uv, repeated by the sampler outside of [0, 1]
+        xz / TILE_SIZE,
+    );
+}
+
+@fragment
+fn fragment_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(plane_texture, plane_sampler, in.uv);
+}
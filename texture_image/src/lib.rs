@@ -0,0 +1,551 @@
+//! Decoding of the texture data read back from the GPU, shared by the examples
+//! reading back formats that need more than a cast of the bytes
+//! (half and packed floats, or a format chosen at runtime).
+//! The examples with fixed 8 bits or 32 bits formats simply cast their buffers with bytemuck.
+//!
+//! The data copied from a texture into a buffer is decoded into typed texel values
+//! according to the texture format, and can then be converted into an `image::DynamicImage`
+//! to save it as PNG.
+
+use std::fmt;
+
+/// Number of bytes per row of a texture copied into a buffer,
+/// padded to the alignment required by wgpu (`COPY_BYTES_PER_ROW_ALIGNMENT`, 256 bytes).
+/// For depth/stencil formats, pass the format of the copied aspect (see `aspect_specific_format`).
+pub fn padded_bytes_per_row(format: wgpu::TextureFormat, width: u32) -> u32 {
+    let texel_size = format
+        .block_copy_size(None)
+        .expect("The format cannot be copied into a buffer");
+    (texel_size * width).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+}
+
+/// Texel values read back from a texture, with their type and number of channels
+/// matching the texture format, and the rows in order without padding.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Texels {
+    U8(Vec<u8>),
+    I8(Vec<i8>),
+    U16(Vec<u16>),
+    I16(Vec<i16>),
+    U32(Vec<u32>),
+    I32(Vec<i32>),
+    F32(Vec<f32>),
+}
+
+/// Why some texture data could not be decoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    /// Compressed and multi-planar formats, Depth24Plus (whose layout is up to the device),
+    /// and combined depth-stencil formats, unless one of their aspects is selected
+    UnsupportedFormat(wgpu::TextureFormat),
+    /// The rows are shorter than the texels they should contain
+    BytesPerRowTooSmall { bytes_per_row: u32, row_size: u32 },
+    /// The data is shorter than the rows it should contain
+    DataTooShort { expected: usize, actual: usize },
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::UnsupportedFormat(format) => {
+                write!(f, "Decoding textures of format {format:?} is not supported")
+            }
+            DecodeError::BytesPerRowTooSmall {
+                bytes_per_row,
+                row_size,
+            } => write!(
+                f,
+                "{bytes_per_row} bytes per row cannot hold a row of {row_size} bytes"
+            ),
+            DecodeError::DataTooShort { expected, actual } => {
+                write!(
+                    f,
+                    "Expected at least {expected} bytes of data, got {actual}"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Image read back from a texture of any uncompressed format that can be copied into a buffer
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextureImage {
+    pub width: u32,
+    pub height: u32,
+    /// Between 1 (R, depth, stencil) and 4 (RGBA)
+    pub channels: u32,
+    pub texels: Texels,
+}
+
+impl TextureImage {
+    /// Decode the data copied from the given aspect of a texture,
+    /// whose rows start every `bytes_per_row` bytes (the padding is skipped).
+    ///
+    /// Normalized formats keep their integer values (0-255 for 8 bits, -128-127 for snorm...),
+    /// half floats are converted to f32, and packed formats are unpacked into one value per channel:
+    /// f32 for Rgb10a2Unorm (normalized between 0.0 and 1.0), Rg11b10Float and Rgb9e5Ufloat,
+    /// u32 for Rgb10a2Uint.
+    pub fn decode(
+        format: wgpu::TextureFormat,
+        aspect: wgpu::TextureAspect,
+        data: &[u8],
+        width: u32,
+        height: u32,
+        bytes_per_row: u32,
+    ) -> Result<Self, DecodeError> {
+        use wgpu::TextureFormat as F;
+        let format = format
+            .aspect_specific_format(aspect)
+            .ok_or(DecodeError::UnsupportedFormat(format))?;
+        // Compressed formats have a copy size per block of texels, not per texel
+        let texel_size = format
+            .block_copy_size(None)
+            .filter(|_| !format.is_compressed())
+            .ok_or(DecodeError::UnsupportedFormat(format))?;
+        let data = unpad_rows(data, texel_size * width, height, bytes_per_row)?;
+
+        let u16s = || {
+            data.chunks_exact(2)
+                .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        };
+        let u32s = || {
+            data.chunks_exact(4)
+                .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        };
+        let i8s = || Texels::I8(data.iter().map(|&b| b as i8).collect());
+        let i16s = || Texels::I16(u16s().map(|x| x as i16).collect());
+        let i32s = || Texels::I32(u32s().map(|x| x as i32).collect());
+        let f32s = || Texels::F32(u32s().map(f32::from_bits).collect());
+        let f16s = || Texels::F32(u16s().map(f16_to_f32).collect());
+        let (channels, texels) = match format {
+            F::R8Unorm | F::R8Uint | F::Stencil8 => (1, Texels::U8(data)),
+            F::Rg8Unorm | F::Rg8Uint => (2, Texels::U8(data)),
+            F::Rgba8Unorm | F::Rgba8UnormSrgb | F::Rgba8Uint => (4, Texels::U8(data)),
+            // Swap the red and blue channels back
+            F::Bgra8Unorm | F::Bgra8UnormSrgb => {
+                let rgba = data
+                    .chunks_exact(4)
+                    .flat_map(|bgra| [bgra[2], bgra[1], bgra[0], bgra[3]]);
+                (4, Texels::U8(rgba.collect()))
+            }
+            F::R8Snorm | F::R8Sint => (1, i8s()),
+            F::Rg8Snorm | F::Rg8Sint => (2, i8s()),
+            F::Rgba8Snorm | F::Rgba8Sint => (4, i8s()),
+            F::R16Unorm | F::R16Uint | F::Depth16Unorm => (1, Texels::U16(u16s().collect())),
+            F::Rg16Unorm | F::Rg16Uint => (2, Texels::U16(u16s().collect())),
+            F::Rgba16Unorm | F::Rgba16Uint => (4, Texels::U16(u16s().collect())),
+            F::R16Snorm | F::R16Sint => (1, i16s()),
+            F::Rg16Snorm | F::Rg16Sint => (2, i16s()),
+            F::Rgba16Snorm | F::Rgba16Sint => (4, i16s()),
+            F::R16Float => (1, f16s()),
+            F::Rg16Float => (2, f16s()),
+            F::Rgba16Float => (4, f16s()),
+            F::R32Uint => (1, Texels::U32(u32s().collect())),
+            F::Rg32Uint => (2, Texels::U32(u32s().collect())),
+            F::Rgba32Uint => (4, Texels::U32(u32s().collect())),
+            F::R32Sint => (1, i32s()),
+            F::Rg32Sint => (2, i32s()),
+            F::Rgba32Sint => (4, i32s()),
+            F::R32Float | F::Depth32Float => (1, f32s()),
+            F::Rg32Float => (2, f32s()),
+            F::Rgba32Float => (4, f32s()),
+            // Packed formats: 10 bits for R, G and B, and 2 bits for A, from the lowest bits
+            F::Rgb10a2Unorm => {
+                let rgba = u32s()
+                    .flat_map(|x| unpack_rgb10a2(x).map(|(value, max)| value as f32 / max as f32));
+                (4, Texels::F32(rgba.collect()))
+            }
+            F::Rgb10a2Uint => {
+                let rgba = u32s().flat_map(|x| unpack_rgb10a2(x).map(|(value, _)| value));
+                (4, Texels::U32(rgba.collect()))
+            }
+            // 11 bits floats for R and G, and a 10 bits float for B, from the lowest bits
+            F::Rg11b10Float => {
+                let rgb = u32s().flat_map(|x| {
+                    [
+                        ufloat_to_f32(x & 0x7ff, 6),
+                        ufloat_to_f32((x >> 11) & 0x7ff, 6),
+                        ufloat_to_f32(x >> 22, 5),
+                    ]
+                });
+                (3, Texels::F32(rgb.collect()))
+            }
+            // 9 bits mantissas for R, G and B, sharing the 5 bits exponent in the highest bits
+            // (with a bias of 15, and no implicit leading 1)
+            F::Rgb9e5Ufloat => {
+                let rgb = u32s().flat_map(|x| {
+                    let scale = 2f32.powi((x >> 27) as i32 - 15 - 9);
+                    [0, 9, 18].map(|shift| ((x >> shift) & 0x1ff) as f32 * scale)
+                });
+                (3, Texels::F32(rgb.collect()))
+            }
+            F::Depth24Plus
+            | F::Depth24PlusStencil8
+            | F::Depth32FloatStencil8
+            | F::NV12
+            | F::Bc1RgbaUnorm
+            | F::Bc1RgbaUnormSrgb
+            | F::Bc2RgbaUnorm
+            | F::Bc2RgbaUnormSrgb
+            | F::Bc3RgbaUnorm
+            | F::Bc3RgbaUnormSrgb
+            | F::Bc4RUnorm
+            | F::Bc4RSnorm
+            | F::Bc5RgUnorm
+            | F::Bc5RgSnorm
+            | F::Bc6hRgbUfloat
+            | F::Bc6hRgbFloat
+            | F::Bc7RgbaUnorm
+            | F::Bc7RgbaUnormSrgb
+            | F::Etc2Rgb8Unorm
+            | F::Etc2Rgb8UnormSrgb
+            | F::Etc2Rgb8A1Unorm
+            | F::Etc2Rgb8A1UnormSrgb
+            | F::Etc2Rgba8Unorm
+            | F::Etc2Rgba8UnormSrgb
+            | F::EacR11Unorm
+            | F::EacR11Snorm
+            | F::EacRg11Unorm
+            | F::EacRg11Snorm
+            | F::Astc { .. } => return Err(DecodeError::UnsupportedFormat(format)),
+        };
+        Ok(TextureImage {
+            width,
+            height,
+            channels,
+            texels,
+        })
+    }
+
+    /// Convert into an image that can be saved as PNG: 8 bits values stay 8 bits,
+    /// and the other ones become 16 bits, since PNG does not support floats.
+    /// Floats are expected between 0.0 and 1.0 (and clamped), like normalized formats,
+    /// 32 bits integers saturate between 0 and 65535,
+    /// and 8 and 16 bits signed integers are offset to start at 0 (-128 becomes 0).
+    /// Two channels images get a zero blue channel, since PNG does not support RG images.
+    pub fn to_dynamic_image(&self) -> image::DynamicImage {
+        match &self.texels {
+            Texels::U8(values) => self.to_dynamic_image_u8(values.clone()),
+            Texels::I8(values) => {
+                let values = values.iter().map(|&x| (x as u8) ^ 0x80);
+                self.to_dynamic_image_u8(values.collect())
+            }
+            Texels::U16(values) => self.to_dynamic_image_u16(values.clone()),
+            Texels::I16(values) => {
+                let values = values.iter().map(|&x| (x as u16) ^ 0x8000);
+                self.to_dynamic_image_u16(values.collect())
+            }
+            Texels::U32(values) => {
+                let values = values.iter().map(|&x| x.min(u16::MAX as u32) as u16);
+                self.to_dynamic_image_u16(values.collect())
+            }
+            Texels::I32(values) => {
+                let values = values.iter().map(|&x| x.clamp(0, u16::MAX as i32) as u16);
+                self.to_dynamic_image_u16(values.collect())
+            }
+            Texels::F32(values) => {
+                let values = values
+                    .iter()
+                    .map(|x| (x.clamp(0.0, 1.0) * u16::MAX as f32).round() as u16);
+                self.to_dynamic_image_u16(values.collect())
+            }
+        }
+    }
+
+    /// 8 bits image with the channels of this image
+    fn to_dynamic_image_u8(&self, values: Vec<u8>) -> image::DynamicImage {
+        let values = self.expand_rg(&values);
+        let (width, height) = (self.width, self.height);
+        match self.channels {
+            1 => image::GrayImage::from_raw(width, height, values)
+                .unwrap()
+                .into(),
+            2 | 3 => image::RgbImage::from_raw(width, height, values)
+                .unwrap()
+                .into(),
+            _ => image::RgbaImage::from_raw(width, height, values)
+                .unwrap()
+                .into(),
+        }
+    }
+
+    /// 16 bits image with the channels of this image
+    fn to_dynamic_image_u16(&self, values: Vec<u16>) -> image::DynamicImage {
+        let values = self.expand_rg(&values);
+        let (width, height) = (self.width, self.height);
+        match self.channels {
+            1 => image::ImageBuffer::<image::Luma<u16>, _>::from_raw(width, height, values)
+                .unwrap()
+                .into(),
+            2 | 3 => image::ImageBuffer::<image::Rgb<u16>, _>::from_raw(width, height, values)
+                .unwrap()
+                .into(),
+            _ => image::ImageBuffer::<image::Rgba<u16>, _>::from_raw(width, height, values)
+                .unwrap()
+                .into(),
+        }
+    }
+
+    /// Add a zero third channel (blue) to two channels values, leave the other ones unchanged
+    fn expand_rg<T: Copy + Default>(&self, values: &[T]) -> Vec<T> {
+        if self.channels == 2 {
+            values
+                .chunks_exact(2)
+                .flat_map(|rg| [rg[0], rg[1], T::default()])
+                .collect()
+        } else {
+            values.to_vec()
+        }
+    }
+}
+
+/// Concatenate the first `row_size` bytes of each of the `height` rows,
+/// which start every `bytes_per_row` bytes
+fn unpad_rows(
+    data: &[u8],
+    row_size: u32,
+    height: u32,
+    bytes_per_row: u32,
+) -> Result<Vec<u8>, DecodeError> {
+    if bytes_per_row < row_size {
+        return Err(DecodeError::BytesPerRowTooSmall {
+            bytes_per_row,
+            row_size,
+        });
+    }
+    // The last row does not need its padding
+    let expected = match height {
+        0 => 0,
+        _ => ((height - 1) * bytes_per_row + row_size) as usize,
+    };
+    if data.len() < expected {
+        return Err(DecodeError::DataTooShort {
+            expected,
+            actual: data.len(),
+        });
+    }
+    let rows = (0..height as usize).map(|row| {
+        let start = row * bytes_per_row as usize;
+        &data[start..start + row_size as usize]
+    });
+    Ok(rows.flatten().copied().collect())
+}
+
+/// The R, G, B and A values of a Rgb10a2 texel, with their maximum value
+fn unpack_rgb10a2(x: u32) -> [(u32, u32); 4] {
+    [
+        (x & 0x3ff, 0x3ff),
+        ((x >> 10) & 0x3ff, 0x3ff),
+        ((x >> 20) & 0x3ff, 0x3ff),
+        (x >> 30, 0x3),
+    ]
+}
+
+/// Convert a half precision float (16 bits) into a single precision float (32 bits).
+/// Half floats have 1 sign bit, 5 exponent bits (with a bias of 15) and 10 mantissa bits.
+pub fn f16_to_f32(half: u16) -> f32 {
+    let magnitude = ufloat_to_f32((half & 0x7fff) as u32, 10);
+    if half & 0x8000 == 0 {
+        magnitude
+    } else {
+        -magnitude
+    }
+}
+
+/// Convert an unsigned float made of 5 exponent bits (with a bias of 15)
+/// followed by `mantissa_bits` mantissa bits into a single precision float.
+/// This is the magnitude of a half float (10 mantissa bits),
+/// and the 11 and 10 bits floats of Rg11b10Float (6 and 5 mantissa bits).
+fn ufloat_to_f32(bits: u32, mantissa_bits: u32) -> f32 {
+    let exponent = ((bits >> mantissa_bits) & 0x1f) as i32;
+    let mantissa = (bits & ((1 << mantissa_bits) - 1)) as f32 / (1 << mantissa_bits) as f32;
+    match exponent {
+        // Subnormal numbers (including zero), without the implicit leading 1
+        0 => mantissa * 2f32.powi(-14),
+        // Infinity and NaN
+        31 if mantissa == 0.0 => f32::INFINITY,
+        31 => f32::NAN,
+        _ => (1.0 + mantissa) * 2f32.powi(exponent - 15),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wgpu::TextureAspect as A;
+    use wgpu::TextureFormat as F;
+
+    /// Decode a single row, without padding
+    fn decode_row(format: F, data: &[u8], width: u32) -> Result<TextureImage, DecodeError> {
+        TextureImage::decode(format, A::All, data, width, 1, data.len() as u32)
+    }
+
+    #[test]
+    fn f16_normal_numbers() {
+        assert_eq!(f16_to_f32(0x3c00), 1.0);
+        assert_eq!(f16_to_f32(0xc000), -2.0);
+        assert_eq!(f16_to_f32(0x3555), 0.333_251_95);
+        // Largest half float
+        assert_eq!(f16_to_f32(0x7bff), 65504.0);
+        // Smallest positive normal half float
+        assert_eq!(f16_to_f32(0x0400), 2f32.powi(-14));
+    }
+
+    #[test]
+    fn f16_zeros_and_subnormals() {
+        assert_eq!(f16_to_f32(0x0000), 0.0);
+        assert!(f16_to_f32(0x0000).is_sign_positive());
+        assert_eq!(f16_to_f32(0x8000), 0.0);
+        assert!(f16_to_f32(0x8000).is_sign_negative());
+        // Smallest and largest subnormals
+        assert_eq!(f16_to_f32(0x0001), 2f32.powi(-24));
+        assert_eq!(f16_to_f32(0x83ff), -1023.0 * 2f32.powi(-24));
+    }
+
+    #[test]
+    fn f16_infinities_and_nans() {
+        assert_eq!(f16_to_f32(0x7c00), f32::INFINITY);
+        assert_eq!(f16_to_f32(0xfc00), f32::NEG_INFINITY);
+        assert!(f16_to_f32(0x7e00).is_nan());
+        assert!(f16_to_f32(0x7c01).is_nan());
+        assert!(f16_to_f32(0xfe00).is_nan());
+    }
+
+    #[test]
+    fn decode_skips_row_padding() {
+        // 2x2 Rgba8 texels, with rows of 12 bytes (4 bytes of padding)
+        let mut data = Vec::new();
+        for row in 0..2u8 {
+            data.extend((0..8).map(|i| 10 * row + i));
+            data.extend([0xff; 4]);
+        }
+        let img = TextureImage::decode(F::Rgba8Unorm, A::All, &data, 2, 2, 12).unwrap();
+        assert_eq!(img.channels, 4);
+        let expected: Vec<u8> = (0..8).chain(10..18).collect();
+        assert_eq!(img.texels, Texels::U8(expected));
+
+        // The padding of the last row is optional
+        let img = TextureImage::decode(F::Rgba8Unorm, A::All, &data[..20], 2, 2, 12).unwrap();
+        assert_eq!(img.texels, Texels::U8((0..8).chain(10..18).collect()));
+    }
+
+    #[test]
+    fn decode_rejects_invalid_layouts() {
+        let data = [0; 16];
+        assert_eq!(
+            TextureImage::decode(F::Rgba8Unorm, A::All, &data, 2, 2, 4),
+            Err(DecodeError::BytesPerRowTooSmall {
+                bytes_per_row: 4,
+                row_size: 8,
+            })
+        );
+        assert_eq!(
+            TextureImage::decode(F::Rgba8Unorm, A::All, &data, 2, 2, 12),
+            Err(DecodeError::DataTooShort {
+                expected: 20,
+                actual: 16,
+            })
+        );
+    }
+
+    #[test]
+    fn decode_8_and_16_bits_formats() {
+        let img = decode_row(F::Bgra8Unorm, &[1, 2, 3, 4], 1).unwrap();
+        assert_eq!(img.texels, Texels::U8(vec![3, 2, 1, 4]));
+
+        let img = decode_row(F::Rg8Snorm, &[0x80, 0x7f, 0xff, 0x00], 2).unwrap();
+        assert_eq!(img.channels, 2);
+        assert_eq!(img.texels, Texels::I8(vec![-128, 127, -1, 0]));
+
+        let img = decode_row(F::R16Sint, &[0x00, 0x80, 0xfe, 0xff], 2).unwrap();
+        assert_eq!(img.texels, Texels::I16(vec![-32768, -2]));
+
+        let img = decode_row(F::Rg16Float, &[0x00, 0x3c, 0x00, 0xfc], 1).unwrap();
+        assert_eq!(img.texels, Texels::F32(vec![1.0, f32::NEG_INFINITY]));
+    }
+
+    #[test]
+    fn decode_32_bits_formats() {
+        let data: Vec<u8> = [-3i32, 7].iter().flat_map(|x| x.to_le_bytes()).collect();
+        let img = decode_row(F::Rg32Sint, &data, 1).unwrap();
+        assert_eq!(img.texels, Texels::I32(vec![-3, 7]));
+
+        let img = decode_row(F::R32Float, &0.25f32.to_le_bytes(), 1).unwrap();
+        assert_eq!(img.texels, Texels::F32(vec![0.25]));
+    }
+
+    #[test]
+    fn decode_packed_formats() {
+        // R = 1023, G = 0, B = 341, A = 3
+        let rgb10a2 = 1023 | (341 << 20) | (3 << 30);
+        let img = decode_row(F::Rgb10a2Unorm, &u32::to_le_bytes(rgb10a2), 1).unwrap();
+        assert_eq!(img.texels, Texels::F32(vec![1.0, 0.0, 341.0 / 1023.0, 1.0]));
+        let img = decode_row(F::Rgb10a2Uint, &u32::to_le_bytes(rgb10a2), 1).unwrap();
+        assert_eq!(img.texels, Texels::U32(vec![1023, 0, 341, 3]));
+
+        // R = 1.0 (exponent 15), G = 0.75 (exponent 14 and mantissa 0.5),
+        // B = 2.0 (exponent 16)
+        let rg11b10 = (15 << 6) | (((14 << 6) | 32) << 11) | ((16 << 5) << 22);
+        let img = decode_row(F::Rg11b10Float, &u32::to_le_bytes(rg11b10), 1).unwrap();
+        assert_eq!(img.channels, 3);
+        assert_eq!(img.texels, Texels::F32(vec![1.0, 0.75, 2.0]));
+
+        // Shared exponent 16: R = 256 * 2^-8 = 1.0, G = 0, B = 384 * 2^-8 = 1.5
+        let rgb9e5 = 256 | (384 << 18) | (16 << 27);
+        let img = decode_row(F::Rgb9e5Ufloat, &u32::to_le_bytes(rgb9e5), 1).unwrap();
+        assert_eq!(img.texels, Texels::F32(vec![1.0, 0.0, 1.5]));
+    }
+
+    #[test]
+    fn decode_depth_and_stencil_aspects() {
+        let img = decode_row(F::Depth16Unorm, &[0xff, 0xff], 1).unwrap();
+        assert_eq!(img.texels, Texels::U16(vec![u16::MAX]));
+
+        // The stencil aspect of a combined format is copied as Stencil8
+        let img = TextureImage::decode(F::Depth32FloatStencil8, A::StencilOnly, &[3, 4], 2, 1, 2)
+            .unwrap();
+        assert_eq!(img.texels, Texels::U8(vec![3, 4]));
+        let img = TextureImage::decode(
+            F::Depth32FloatStencil8,
+            A::DepthOnly,
+            &[0, 0, 0x80, 0x3f],
+            1,
+            1,
+            4,
+        )
+        .unwrap();
+        assert_eq!(img.texels, Texels::F32(vec![1.0]));
+    }
+
+    #[test]
+    fn decode_rejects_unsupported_formats() {
+        let data = [0; 16];
+        for format in [F::Depth24Plus, F::Depth24PlusStencil8, F::Bc1RgbaUnorm] {
+            assert_eq!(
+                decode_row(format, &data, 1),
+                Err(DecodeError::UnsupportedFormat(format))
+            );
+        }
+        // The stencil only format has no depth aspect
+        assert_eq!(
+            TextureImage::decode(F::Stencil8, A::DepthOnly, &data, 1, 1, 1),
+            Err(DecodeError::UnsupportedFormat(F::Stencil8))
+        );
+    }
+
+    #[test]
+    fn signed_values_are_offset_in_images() {
+        let img = decode_row(F::R8Snorm, &[0x80, 0x00, 0x7f], 3).unwrap();
+        let gray = img.to_dynamic_image().into_luma8();
+        assert_eq!(gray.into_raw(), vec![0, 128, 255]);
+    }
+
+    #[test]
+    fn padded_rows_are_aligned() {
+        assert_eq!(padded_bytes_per_row(F::Rgba8Unorm, 1), 256);
+        assert_eq!(padded_bytes_per_row(F::R32Float, 64), 256);
+        assert_eq!(padded_bytes_per_row(F::R32Float, 65), 512);
+    }
+}